@@ -0,0 +1,181 @@
+#ifndef THUD_H
+#define THUD_H
+
+/* Generated by cbindgen from src/ffi.rs, do not edit by hand */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Why a game ended, mirroring [`EndReason`](../enum.EndReason.html)
+ */
+typedef enum ThudEndReason {
+  THUD_END_REASON_NO_MOVES = 0,
+  THUD_END_REASON_AGREEMENT,
+  THUD_END_REASON_RESIGNATION,
+  THUD_END_REASON_REPETITION,
+  THUD_END_REASON_MOVE_LIMIT,
+  THUD_END_REASON_OUT_OF_TIME,
+} ThudEndReason;
+
+/**
+ * Whose turn it is, or who won
+ */
+typedef enum ThudPlayer {
+  /**
+   * No player, ie. the game has ended or it is a draw
+   */
+  THUD_PLAYER_NONE = 0,
+  THUD_PLAYER_DWARF,
+  THUD_PLAYER_TROLL,
+} ThudPlayer;
+
+/**
+ * Outcome of a call into the C API
+ */
+typedef enum ThudStatus {
+  THUD_STATUS_OK = 0,
+  THUD_STATUS_INVALID_POSITION,
+  /**
+   * Any of the errors about a move breaking the rules, besides the two below
+   */
+  THUD_STATUS_ILLEGAL_MOVE,
+  THUD_STATUS_OBSTACLE,
+  THUD_STATUS_LINE_TOO_SHORT,
+  THUD_STATUS_INVALID_DIRECTION,
+  THUD_STATUS_BAD_ACTION,
+  THUD_STATUS_INVALID_BOARD,
+  THUD_STATUS_INVALID_NOTATION,
+  THUD_STATUS_NULL_POINTER,
+  THUD_STATUS_INVALID_RULES,
+} ThudStatus;
+
+/**
+ * Opaque handle to a game of Thud
+ */
+typedef struct ThudGame ThudGame;
+
+/**
+ * The result of a finished game; `winner` is `None` for a draw
+ */
+typedef struct ThudOutcome {
+  enum ThudPlayer winner;
+  enum ThudEndReason reason;
+} ThudOutcome;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Start a new game, which must later be freed with [`thud_free()`](fn.thud_free.html).
+ */
+struct ThudGame *thud_new(void);
+
+/**
+ * Free a game made with [`thud_new()`](fn.thud_new.html).
+ *
+ * # Safety
+ *
+ * `game` must be a handle returned by [`thud_new()`](fn.thud_new.html) which has not yet been
+ * freed, or null.
+ */
+void thud_free(struct ThudGame *game);
+
+/**
+ * Wrapper for [`Thud::move_piece()`](../struct.Thud.html#method.move_piece)
+ *
+ * # Safety
+ *
+ * `game` must be a live handle from [`thud_new()`](fn.thud_new.html), or null.
+ */
+enum ThudStatus thud_move(struct ThudGame *game,
+                          size_t src_x,
+                          size_t src_y,
+                          size_t dest_x,
+                          size_t dest_y);
+
+/**
+ * Wrapper for [`Thud::attack()`](../struct.Thud.html#method.attack), which will hurl a dwarf
+ * or shove a troll depending on whose turn it is
+ *
+ * # Safety
+ *
+ * `game` must be a live handle from [`thud_new()`](fn.thud_new.html), or null.
+ */
+enum ThudStatus thud_attack(struct ThudGame *game,
+                            size_t src_x,
+                            size_t src_y,
+                            size_t dest_x,
+                            size_t dest_y);
+
+/**
+ * Wrapper for [`Thud::troll_cap()`](../struct.Thud.html#method.troll_cap)
+ *
+ * `directions` should point to `len` direction numbers; it may be null if `len` is 0.
+ *
+ * # Safety
+ *
+ * `game` must be a live handle from [`thud_new()`](fn.thud_new.html), or null, and
+ * `directions` must be valid for reads of `len` bytes.
+ */
+enum ThudStatus thud_capture(struct ThudGame *game,
+                             size_t troll_x,
+                             size_t troll_y,
+                             const uint8_t *directions,
+                             size_t len);
+
+/**
+ * Find whose turn it is, or [`ThudPlayer::None`](enum.ThudPlayer.html) if the game is over
+ *
+ * # Safety
+ *
+ * `game` must be a live handle from [`thud_new()`](fn.thud_new.html), or null.
+ */
+enum ThudPlayer thud_turn(const struct ThudGame *game);
+
+/**
+ * Copy the board into `out`, a 225-byte array indexed by `x * 15 + y`
+ *
+ * # Safety
+ *
+ * `game` must be a live handle from [`thud_new()`](fn.thud_new.html), or null, and `out` must
+ * be valid for writes of 225 bytes.
+ */
+enum ThudStatus thud_board(const struct ThudGame *game, uint8_t *out);
+
+/**
+ * Wrapper for [`Thud::score()`](../struct.Thud.html#method.score)
+ *
+ * # Safety
+ *
+ * `game` must be a live handle from [`thud_new()`](fn.thud_new.html), or null, and `dwarf`
+ * and `troll` must be valid for writes.
+ */
+enum ThudStatus thud_score(const struct ThudGame *game, size_t *dwarf, size_t *troll);
+
+/**
+ * Wrapper for [`Thud::winner()`](../struct.Thud.html#method.winner)
+ *
+ * Returns `true` and fills in `out` if the game has ended, otherwise returns `false`.
+ *
+ * # Safety
+ *
+ * `game` must be a live handle from [`thud_new()`](fn.thud_new.html), or null, and `out` must
+ * be valid for writes.
+ */
+bool thud_winner(struct ThudGame *game, struct ThudOutcome *out);
+
+/**
+ * Get a human-readable, static, NUL-terminated description of a status
+ */
+const char *thud_status_message(enum ThudStatus status);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* THUD_H */
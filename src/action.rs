@@ -0,0 +1,151 @@
+use crate::{Coord, Direction, Player, ThudError};
+#[cfg(feature = "serialize")]
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Something a player can do in a game of Thud
+///
+/// Apply one to a game with [`Thud::apply()`](struct.Thud.html#method.apply); each variant
+/// corresponds to one of the methods on [`Thud`](struct.Thud.html).
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialize", serde(rename_all = "snake_case"))]
+#[derive(Debug, PartialEq, Clone)]
+pub enum Action {
+    /// See [`Thud::move_piece()`](struct.Thud.html#method.move_piece)
+    Move(Coord, Coord),
+    /// See [`Thud::attack()`](struct.Thud.html#method.attack)
+    Attack(Coord, Coord),
+    /// See [`Thud::troll_cap()`](struct.Thud.html#method.troll_cap)
+    Capture(Coord, Vec<Direction>),
+    /// See [`Thud::resign()`](struct.Thud.html#method.resign)
+    Resign(Player),
+    /// See [`Thud::offer_end()`](struct.Thud.html#method.offer_end)
+    OfferEnd,
+    /// See [`Thud::accept_end()`](struct.Thud.html#method.accept_end)
+    AcceptEnd,
+    /// See [`Thud::decline_end()`](struct.Thud.html#method.decline_end)
+    DeclineEnd,
+}
+
+/// Compass points used for capture directions in notation, in the order of
+/// [`Direction::all()`](enum.Direction.html#method.all)
+const COMPASS: [&str; 8] = ["N", "NE", "E", "SE", "S", "SW", "W", "NW"];
+
+fn compass(direction: Direction) -> &'static str {
+    let index = Direction::all()
+        .iter()
+        .position(|&other| other == direction)
+        .unwrap();
+    COMPASS[index]
+}
+
+/// Write the `Action` in move notation:
+///
+/// - [`Move`](#variant.Move) is the two squares joined by `-`, eg. `F1-F6`
+/// - [`Attack`](#variant.Attack) is the two squares joined by `x`, eg. `F1xF6`
+/// - [`Capture`](#variant.Capture) is the troll's square, `*`, and the compass points to capture
+///   in separated by `,`, eg. `G9*N,NE` or just `G9*` to capture nothing, where `N` is
+///   [`Direction::Up`](enum.Direction.html)
+/// - The rest are the words `resign dwarf`, `resign troll`, `offer`, `accept` and `decline`
+///
+/// Squares are in standard Thud notation, see [`Coord`](struct.Coord.html).
+///
+/// ```
+/// use thud::{Action, Direction};
+///
+/// let capture = Action::Capture((6, 8).into(), vec![Direction::Up, Direction::UpRight]);
+/// assert_eq!(capture.to_string(), "G9*N,NE");
+/// assert_eq!("g9*n,ne".parse(), Ok(capture));
+/// ```
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::Move(src, target) => write!(f, "{}-{}", src, target),
+            Action::Attack(src, target) => write!(f, "{}x{}", src, target),
+            Action::Capture(troll, targets) => {
+                let targets: Vec<&str> = targets.iter().map(|&dir| compass(dir)).collect();
+                write!(f, "{}*{}", troll, targets.join(","))
+            }
+            Action::Resign(Player::Dwarf) => write!(f, "resign dwarf"),
+            Action::Resign(Player::Troll) => write!(f, "resign troll"),
+            Action::OfferEnd => write!(f, "offer"),
+            Action::AcceptEnd => write!(f, "accept"),
+            Action::DeclineEnd => write!(f, "decline"),
+        }
+    }
+}
+
+/// Read an `Action` in the move notation written by its `Display` implementation, ignoring case
+/// and surrounding whitespace.
+///
+/// Returns [`Err(ThudError::InvalidNotation)`](enum.ThudError.html) if the notation can't be
+/// read, or [`Err(ThudError::InvalidPosition)`](enum.ThudError.html) if a square is off the
+/// board.
+impl FromStr for Action {
+    type Err = ThudError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_ascii_uppercase();
+        let words: Vec<&str> = s.split_whitespace().collect();
+        match words[..] {
+            ["RESIGN", "DWARF"] => return Ok(Action::Resign(Player::Dwarf)),
+            ["RESIGN", "TROLL"] => return Ok(Action::Resign(Player::Troll)),
+            ["OFFER"] => return Ok(Action::OfferEnd),
+            ["ACCEPT"] => return Ok(Action::AcceptEnd),
+            ["DECLINE"] => return Ok(Action::DeclineEnd),
+            [_] => (),
+            _ => return Err(ThudError::InvalidNotation),
+        }
+
+        if let Some((troll, targets)) = s.split_once('*') {
+            let targets = targets
+                .split(',')
+                .filter(|point| !point.is_empty())
+                .map(|point| {
+                    COMPASS
+                        .iter()
+                        .position(|&other| other == point)
+                        .map(|index| Direction::all()[index])
+                        .ok_or(ThudError::InvalidNotation)
+                })
+                .collect::<Result<_, _>>()?;
+            Ok(Action::Capture(troll.parse()?, targets))
+        } else if let Some((src, target)) = s.split_once('X') {
+            Ok(Action::Attack(src.parse()?, target.parse()?))
+        } else if let Some((src, target)) = s.split_once('-') {
+            Ok(Action::Move(src.parse()?, target.parse()?))
+        } else {
+            Err(ThudError::InvalidNotation)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(Action::Move((5, 0).into(), (5, 5).into()), "F1-F6")]
+    #[test_case(Action::Attack((5, 0).into(), (5, 5).into()), "F1xF6")]
+    #[test_case(Action::Capture((9, 7).into(), vec![]), "K8*")]
+    #[test_case(Action::Capture((9, 7).into(), vec![Direction::DownLeft, Direction::UpLeft]), "K8*SW,NW")]
+    #[test_case(Action::Resign(Player::Troll), "resign troll")]
+    #[test_case(Action::OfferEnd, "offer")]
+    #[test_case(Action::AcceptEnd, "accept")]
+    #[test_case(Action::DeclineEnd, "decline")]
+    fn notation(action: Action, notation: &str) {
+        assert_eq!(action.to_string(), notation);
+        assert_eq!(notation.parse(), Ok(action));
+    }
+
+    #[test_case(" f1-f6 " => Ok(Action::Move((5, 0).into(), (5, 5).into())))]
+    #[test_case("Resign  Dwarf" => Ok(Action::Resign(Player::Dwarf)))]
+    #[test_case("F1F6" => Err(ThudError::InvalidNotation))]
+    #[test_case("K8*UP" => Err(ThudError::InvalidNotation))]
+    #[test_case("resign" => Err(ThudError::InvalidNotation))]
+    #[test_case("A1-F6" => Err(ThudError::InvalidPosition(0, 0)))]
+    fn parse(notation: &str) -> Result<Action, ThudError> {
+        notation.parse()
+    }
+}
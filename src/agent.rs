@@ -0,0 +1,190 @@
+use crate::{Action, Player, Thud};
+
+/// Something which can choose [`Action`s](enum.Action.html) to play in a game of Thud
+///
+/// An agent is asked to act whenever it has the turn, including to finish a troll turn with a
+/// capture and to answer an [end offer](struct.Thud.html#method.offer_end) from its opponent.
+pub trait Agent {
+    /// Choose an action to take in `game`, or `None` if there is nothing to be done.
+    ///
+    /// The action should be one of [`Thud::legal_actions()`](struct.Thud.html#method.legal_actions).
+    fn choose(&mut self, game: &Thud) -> Option<Action>;
+}
+
+impl<A: Agent + ?Sized> Agent for Box<A> {
+    fn choose(&mut self, game: &Thud) -> Option<Action> {
+        (**self).choose(game)
+    }
+}
+
+/// An [`Agent`](trait.Agent.html) which picks uniformly at random between the legal actions
+///
+/// The same seed always gives the same choices, which makes it handy for testing.
+#[derive(Debug, Clone)]
+pub struct RandomAgent {
+    state: u64,
+}
+
+impl RandomAgent {
+    /// Make a `RandomAgent` with its random number generator seeded with `seed`
+    pub fn new(seed: u64) -> Self {
+        // xorshift gets stuck at zero
+        RandomAgent {
+            state: seed ^ 0x9e37_79b9_7f4a_7c15,
+        }
+    }
+
+    /// Get a random number below `bound`, which must not be zero
+    fn below(&mut self, bound: usize) -> usize {
+        // xorshift64*
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        let value = self.state.wrapping_mul(0x2545_f491_4f6c_dd1d);
+        (value % bound as u64) as usize
+    }
+
+    fn pick(&mut self, mut actions: Vec<Action>) -> Option<Action> {
+        if actions.is_empty() {
+            None
+        } else {
+            let index = self.below(actions.len());
+            Some(actions.swap_remove(index))
+        }
+    }
+}
+
+impl Agent for RandomAgent {
+    fn choose(&mut self, game: &Thud) -> Option<Action> {
+        self.pick(game.legal_actions())
+    }
+}
+
+/// An [`Agent`](trait.Agent.html) which takes whichever action leaves it furthest ahead on
+/// [score](struct.Board.html#method.score) at the end of its turn, choosing randomly between
+/// equally good actions
+///
+/// It accepts an end offer only if it would win or draw.
+#[derive(Debug, Clone)]
+pub struct GreedyAgent {
+    random: RandomAgent,
+}
+
+impl GreedyAgent {
+    /// Make a `GreedyAgent`, which breaks ties with a random number generator seeded with `seed`
+    pub fn new(seed: u64) -> Self {
+        GreedyAgent {
+            random: RandomAgent::new(seed),
+        }
+    }
+}
+
+/// How far `player` is ahead on score
+fn lead(game: &Thud, player: Player) -> isize {
+    let (dwarf, troll) = game.score();
+    let lead = dwarf as isize - troll as isize;
+    match player {
+        Player::Dwarf => lead,
+        Player::Troll => -lead,
+    }
+}
+
+/// How far `player` can get ahead by the end of their turn after taking `action`
+fn evaluate(game: &Thud, player: Player, action: Action) -> isize {
+    let mut game = game.clone();
+    if game.apply(action).is_err() {
+        return isize::MIN;
+    }
+    if game.turn() == Some(player) && game.end_offer().is_none() {
+        // The troll's turn continues with a capture
+        if let Some(best) = game
+            .legal_actions()
+            .into_iter()
+            .map(|action| evaluate(&game, player, action))
+            .max()
+        {
+            return best;
+        }
+    }
+    lead(&game, player)
+}
+
+impl Agent for GreedyAgent {
+    fn choose(&mut self, game: &Thud) -> Option<Action> {
+        let player = game.turn()?;
+        if let Some(offered_by) = game.end_offer() {
+            let action = if lead(game, offered_by.opponent()) >= 0 {
+                Action::AcceptEnd
+            } else {
+                Action::DeclineEnd
+            };
+            return Some(action);
+        }
+
+        let mut best = Vec::new();
+        let mut best_value = isize::MIN;
+        for action in game.legal_actions() {
+            let value = evaluate(game, player, action.clone());
+            if value > best_value {
+                best_value = value;
+                best.clear();
+            }
+            if value == best_value {
+                best.push(action);
+            }
+        }
+        self.random.pick(best)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Board, Piece};
+
+    #[test]
+    fn random_is_seeded() {
+        let game = Thud::new();
+        let choices = |seed| {
+            let mut agent = RandomAgent::new(seed);
+            (0..10)
+                .map(|_| agent.choose(&game).unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(choices(7), choices(7));
+        assert_ne!(choices(7), choices(8));
+        for action in choices(0) {
+            assert!(game.legal_actions().contains(&action));
+        }
+    }
+
+    #[test]
+    fn random_plays_out() {
+        let mut game = Thud::new();
+        let mut agent = RandomAgent::new(1);
+        for _ in 0..200 {
+            match agent.choose(&game) {
+                Some(action) => game.apply(action).unwrap(),
+                None => break,
+            }
+        }
+        assert!(game.history().len() > 1);
+    }
+
+    #[test]
+    fn greedy_captures() {
+        let mut board = Board::default();
+        board.place((7, 7).into(), Piece::Thudstone);
+        board.place((5, 5).into(), Piece::Troll);
+        board.place((5, 7).into(), Piece::Dwarf);
+        board.place((10, 10).into(), Piece::Dwarf);
+        let mut game = Thud::from_board(board, Player::Troll).unwrap();
+
+        let mut agent = GreedyAgent::new(0);
+        let action = agent.choose(&game).unwrap();
+        game.apply(action).unwrap();
+        let capture = agent.choose(&game).unwrap();
+        game.apply(capture).unwrap();
+        assert_eq!(game.score(), (1, 4));
+    }
+}
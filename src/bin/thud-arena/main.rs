@@ -0,0 +1,558 @@
+//! Run tournaments between Thud engines speaking the protocol in `thud::protocol`.
+//!
+//! Each game is played between freshly started engines, with every action checked by `Thud`.
+//! An engine which makes an illegal or incomplete turn, sends something unreadable, runs out of
+//! time or exits loses the game. A game still going after the maximum number of plies is drawn,
+//! so engines which keep offering and declining an end can't hold up the tournament.
+
+use std::env;
+use std::fs::File;
+use std::io::{self, BufReader, Write};
+use std::process::{self, Child, Command, Stdio};
+use std::time::Duration;
+use thud::protocol::{Driver, Limits, ProtocolError, Reply};
+use thud::{GameRecord, Outcome, Player, Rules, Thud};
+
+mod tournament;
+
+use tournament::Standings;
+
+const USAGE: &str = "\
+Usage: thud-arena [OPTIONS] --engine <ENGINE> --engine <ENGINE>...
+
+Options:
+  --engine <[NAME=]COMMAND>  Enter an engine, started with COMMAND
+  --swiss <ROUNDS>           Play this many rounds of a Swiss tournament, rather than a round robin
+  --cycles <N>               Play through a round robin N times [default: 1]
+  --movetime <MS>            Thinking time to ask for each turn
+  --timeout <MS>             Forfeit an engine which takes longer for a turn [default: 10000]
+  --move-limit <N>           Draw after N turns without a capture [default: 100]
+  --repetition <N>           Draw when a position comes up N times [default: 3]
+  --max-plies <N>            Draw a game still going after N actions [default: 1000]
+  --records <FILE>           Write a record of every game to FILE
+  -h, --help                 Show this message";
+
+/// An engine's name and the command line which starts it
+#[derive(Debug, Clone, PartialEq)]
+struct EngineSpec {
+    name: String,
+    command: Vec<String>,
+}
+
+impl EngineSpec {
+    fn parse(spec: &str) -> Result<Self, String> {
+        let (name, command) = match spec.split_once('=') {
+            Some((name, command))
+                if !name.is_empty() && !name.contains(|c: char| c.is_whitespace() || c == '/') =>
+            {
+                (name.to_string(), command)
+            }
+            _ => (spec.trim().to_string(), spec),
+        };
+        let command: Vec<String> = command.split_whitespace().map(str::to_string).collect();
+        if command.is_empty() {
+            return Err(format!("No command given for engine {:?}", spec));
+        }
+        Ok(EngineSpec { name, command })
+    }
+}
+
+/// How the games are to be played
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    limits: Limits,
+    timeout: Option<Duration>,
+    rules: Rules,
+    max_plies: usize,
+}
+
+struct Options {
+    engines: Vec<EngineSpec>,
+    swiss: Option<usize>,
+    cycles: usize,
+    settings: Settings,
+    records: Option<String>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        engines: Vec::new(),
+        swiss: None,
+        cycles: 1,
+        settings: Settings {
+            limits: Limits::default(),
+            timeout: Some(Duration::from_millis(10_000)),
+            rules: Rules {
+                repetition: Some(3),
+                move_limit: Some(100),
+            },
+            max_plies: 1000,
+        },
+        records: None,
+    };
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+        let number = |value: String| {
+            value
+                .parse::<u64>()
+                .map_err(|_| format!("{} needs a number", arg))
+        };
+        match arg.as_str() {
+            "--engine" => options.engines.push(EngineSpec::parse(&value()?)?),
+            "--swiss" => options.swiss = Some(number(value()?)? as usize),
+            "--cycles" => options.cycles = number(value()?)? as usize,
+            "--movetime" => {
+                options.settings.limits.movetime = Some(Duration::from_millis(number(value()?)?))
+            }
+            "--timeout" => {
+                options.settings.timeout = Some(Duration::from_millis(number(value()?)?))
+            }
+            "--move-limit" => options.settings.rules.move_limit = Some(number(value()?)? as usize),
+            "--repetition" => options.settings.rules.repetition = Some(number(value()?)? as usize),
+            "--max-plies" => options.settings.max_plies = number(value()?)? as usize,
+            "--records" => options.records = Some(value()?),
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            _ => return Err(format!("Unknown argument {:?}\n\n{}", arg, USAGE)),
+        }
+    }
+    if options.engines.len() < 2 {
+        return Err(format!("At least two engines are needed\n\n{}", USAGE));
+    }
+    options
+        .settings
+        .rules
+        .validate()
+        .map_err(|error| error.to_string())?;
+
+    // Tell apart engines entered more than once under the same name
+    for i in 1..options.engines.len() {
+        let name = options.engines[i].name.clone();
+        let copies = options.engines[..i]
+            .iter()
+            .filter(|other| other.name == name || other.name.starts_with(&format!("{} (", name)))
+            .count();
+        if copies > 0 {
+            options.engines[i].name = format!("{} ({})", name, copies + 1);
+        }
+    }
+    Ok(options)
+}
+
+/// A running engine
+struct Connection {
+    driver: Driver<Box<dyn Write>>,
+    child: Option<Child>,
+}
+
+impl Connection {
+    /// Start `spec` as a subprocess talking over its stdin and stdout
+    fn spawn(spec: &EngineSpec) -> io::Result<Self> {
+        let mut child = Command::new(&spec.command[0])
+            .args(&spec.command[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+        Ok(Connection {
+            driver: Driver::new(BufReader::new(stdout), Box::new(stdin)),
+            child: Some(child),
+        })
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        if let Some(child) = &mut self.child {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+/// Find who has to act next, which is the opponent if an end has been offered
+fn to_act(game: &Thud) -> Option<Player> {
+    match game.end_offer() {
+        Some(player) => Some(player.opponent()),
+        None => game.turn(),
+    }
+}
+
+/// Get the `Termination` tag for a failure to talk to an engine
+fn failure(error: ProtocolError) -> &'static str {
+    match error {
+        ProtocolError::Timeout => "time_forfeit",
+        ProtocolError::Malformed(_) => "malformed_reply",
+        ProtocolError::Closed | ProtocolError::Io(_) => "crash",
+    }
+}
+
+/// Have `engine` take a turn for `player`, giving the `Termination` tag if it forfeits
+fn take_turn(
+    engine: &mut Connection,
+    game: &mut Thud,
+    player: Player,
+    settings: &Settings,
+) -> Result<(), &'static str> {
+    engine.driver.position(game).map_err(failure)?;
+    let actions = match engine
+        .driver
+        .go(settings.limits, settings.timeout)
+        .map_err(failure)?
+    {
+        Reply::Resign => {
+            game.resign(player).map_err(|_| "illegal_action")?;
+            return Ok(());
+        }
+        Reply::BestMove(actions) => actions,
+        Reply::Info(_) => unreachable!("info lines are collected by the driver"),
+    };
+
+    if actions.is_empty() {
+        return Err("illegal_action");
+    }
+    for action in actions {
+        // The engine may only act for its own side, not answer its own offer or move for both
+        if to_act(game) != Some(player) {
+            return Err("illegal_action");
+        }
+        game.apply(action).map_err(|_| "illegal_action")?;
+    }
+    if game.winner().is_none() && to_act(game) == Some(player) {
+        return Err("incomplete_turn");
+    }
+    Ok(())
+}
+
+/// Play a game between two running engines, recording it with the result
+fn play_game(dwarf: &mut Connection, troll: &mut Connection, settings: &Settings) -> GameRecord {
+    let mut game = Thud::with_rules(settings.rules).expect("the rules were checked");
+    let mut forfeit = None;
+    for (player, engine) in [(Player::Dwarf, &mut *dwarf), (Player::Troll, &mut *troll)] {
+        if let Err(error) = engine.driver.new_game() {
+            forfeit = forfeit.or(Some((player, failure(error))));
+        }
+    }
+    while forfeit.is_none() && game.winner().is_none() {
+        if game.history().len() >= settings.max_plies {
+            let mut record = GameRecord::from_game(&game);
+            record.set_outcome(Some(Outcome::Draw));
+            record.set_tag("Termination", "max_plies");
+            return record;
+        }
+        let player = to_act(&game).expect("the game is still going");
+        let engine = match player {
+            Player::Dwarf => &mut *dwarf,
+            Player::Troll => &mut *troll,
+        };
+        if let Err(termination) = take_turn(engine, &mut game, player, settings) {
+            forfeit = Some((player, termination));
+        }
+    }
+
+    let mut record = GameRecord::from_game(&game);
+    if let Some((player, termination)) = forfeit {
+        record.set_outcome(Some(Outcome::Won(player.opponent())));
+        record.set_tag("Termination", termination);
+    }
+    record
+}
+
+/// Runs a tournament, starting engines with `connect`
+struct Arena<C> {
+    engines: Vec<EngineSpec>,
+    settings: Settings,
+    connect: C,
+    standings: Standings,
+    records: Vec<GameRecord>,
+}
+
+impl<C: FnMut(&EngineSpec) -> io::Result<Connection>> Arena<C> {
+    fn new(engines: Vec<EngineSpec>, settings: Settings, connect: C) -> Self {
+        let standings = Standings::new(engines.iter().map(|spec| spec.name.clone()).collect());
+        Arena {
+            engines,
+            settings,
+            connect,
+            standings,
+            records: Vec::new(),
+        }
+    }
+
+    /// Play one game, giving it to `log` once it is over
+    fn game(
+        &mut self,
+        round: usize,
+        dwarf: usize,
+        troll: usize,
+        log: &mut impl FnMut(&GameRecord),
+    ) {
+        let connections = (
+            (self.connect)(&self.engines[dwarf]),
+            (self.connect)(&self.engines[troll]),
+        );
+        let mut record = match connections {
+            (Ok(mut dwarf), Ok(mut troll)) => play_game(&mut dwarf, &mut troll, &self.settings),
+            (dwarf, _) => {
+                // An engine which can't be started forfeits, the dwarf first
+                let mut record = GameRecord::from_game(
+                    &Thud::with_rules(self.settings.rules).expect("the rules were checked"),
+                );
+                let loser = if dwarf.is_err() {
+                    Player::Dwarf
+                } else {
+                    Player::Troll
+                };
+                record.set_outcome(Some(Outcome::Won(loser.opponent())));
+                record.set_tag("Termination", "crash");
+                record
+            }
+        };
+
+        let mut tags = vec![
+            ("Event".to_string(), "Arena".to_string()),
+            ("Round".to_string(), round.to_string()),
+            ("Dwarf".to_string(), self.engines[dwarf].name.clone()),
+            ("Troll".to_string(), self.engines[troll].name.clone()),
+        ];
+        tags.append(&mut record.tags);
+        record.tags = tags;
+
+        let outcome = record.outcome().expect("arena games are played out");
+        self.standings.add(dwarf, troll, outcome);
+        log(&record);
+        self.records.push(record);
+    }
+
+    /// Play a match of two games between `a` and `b`, one on each side
+    fn pairing(&mut self, round: usize, a: usize, b: usize, log: &mut impl FnMut(&GameRecord)) {
+        self.standings.pair(a, b);
+        self.game(round, a, b, log);
+        self.game(round, b, a, log);
+    }
+
+    fn round_robin(&mut self, cycles: usize, log: &mut impl FnMut(&GameRecord)) {
+        let rounds = tournament::round_robin(self.engines.len(), cycles);
+        for (round, pairings) in rounds.into_iter().enumerate() {
+            for (a, b) in pairings {
+                self.pairing(round + 1, a, b, log);
+            }
+        }
+    }
+
+    fn swiss(&mut self, rounds: usize, log: &mut impl FnMut(&GameRecord)) {
+        for round in 1..=rounds {
+            let (pairings, bye) = tournament::swiss(&self.standings);
+            if let Some(bye) = bye {
+                self.standings.0[bye].byes += 1;
+            }
+            for (a, b) in pairings {
+                self.pairing(round, a, b, log);
+            }
+        }
+    }
+}
+
+fn main() {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}", message);
+            process::exit(2);
+        }
+    };
+
+    let mut records = match &options.records {
+        Some(path) => match File::create(path) {
+            Ok(file) => Some(file),
+            Err(error) => {
+                eprintln!("Couldn't create {}: {}", path, error);
+                process::exit(1);
+            }
+        },
+        None => None,
+    };
+    let mut log = |record: &GameRecord| {
+        eprintln!(
+            "Round {}: {} (dwarf) vs {} (troll): {} ({})",
+            record.tag("Round").unwrap_or("?"),
+            record.tag("Dwarf").unwrap_or("?"),
+            record.tag("Troll").unwrap_or("?"),
+            record.tag("Result").unwrap_or("*"),
+            record.tag("Termination").unwrap_or("?"),
+        );
+        if let Some(file) = &mut records {
+            if let Err(error) = writeln!(file, "{}", record) {
+                eprintln!("Couldn't write the game record: {}", error);
+                process::exit(1);
+            }
+        }
+    };
+
+    let mut arena = Arena::new(options.engines, options.settings, Connection::spawn);
+    match options.swiss {
+        Some(rounds) => arena.swiss(rounds, &mut log),
+        None => arena.round_robin(options.cycles, &mut log),
+    }
+    print!("{}", arena.standings);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufRead;
+    use std::thread;
+    use thud::protocol::{Engine, ARENA_TERMINATIONS};
+    use thud::{Action, Agent, RandomAgent};
+
+    /// Start an engine on a thread: `random` plays randomly, `stubborn` offers and declines
+    /// ends forever, `cheat` offers an end and accepts it itself, `silent` never replies and
+    /// `crash` exits straight away
+    fn connect(spec: &EngineSpec) -> io::Result<Connection> {
+        let (command_reader, command_writer) = io::pipe()?;
+        let (reply_reader, mut reply_writer) = io::pipe()?;
+        let kind = spec.command[0].clone();
+        thread::spawn(move || {
+            let input = BufReader::new(command_reader);
+            match kind.as_str() {
+                "random" => {
+                    let _ = Engine::new(RandomAgent::new(5)).run(input, reply_writer);
+                }
+                "stubborn" => {
+                    let _ = Engine::new(Stubborn).run(input, reply_writer);
+                }
+                "cheat" => {
+                    for line in input.lines().map_while(Result::ok) {
+                        if line.starts_with("go") {
+                            let _ = writeln!(reply_writer, "bestmove offer accept");
+                            let _ = reply_writer.flush();
+                        }
+                    }
+                }
+                "silent" => {
+                    for _ in input.lines() {}
+                    let _ = reply_writer.flush();
+                }
+                _ => (),
+            }
+        });
+        Ok(Connection {
+            driver: Driver::new(BufReader::new(reply_reader), Box::new(command_writer)),
+            child: None,
+        })
+    }
+
+    fn engines(kinds: &[&str]) -> Vec<EngineSpec> {
+        kinds
+            .iter()
+            .map(|kind| EngineSpec::parse(kind).unwrap())
+            .collect()
+    }
+
+    fn settings() -> Settings {
+        Settings {
+            limits: Limits::default(),
+            timeout: Some(Duration::from_millis(200)),
+            rules: Rules {
+                repetition: Some(3),
+                move_limit: Some(20),
+            },
+            max_plies: 1000,
+        }
+    }
+
+    /// Offers an end whenever it can, and declines any offer made to it
+    struct Stubborn;
+
+    impl Agent for Stubborn {
+        fn choose(&mut self, game: &Thud) -> Option<Action> {
+            if game.end_offer().is_some() {
+                Some(Action::DeclineEnd)
+            } else if game.pending_capture().is_none() {
+                Some(Action::OfferEnd)
+            } else {
+                game.legal_actions().into_iter().next()
+            }
+        }
+    }
+
+    #[test]
+    fn engine_spec() {
+        assert_eq!(
+            EngineSpec::parse("greedy=thud-engine --seed 1").unwrap(),
+            EngineSpec {
+                name: "greedy".to_string(),
+                command: vec!["thud-engine".into(), "--seed".into(), "1".into()],
+            }
+        );
+        assert_eq!(
+            EngineSpec::parse("./engine --depth=3").unwrap().name,
+            "./engine --depth=3"
+        );
+        assert!(EngineSpec::parse("name=").is_err());
+
+        let args = ["--engine", "a", "--engine", "a", "--engine", "a"];
+        let options = parse_args(args.iter().map(|arg| arg.to_string())).unwrap();
+        let names: Vec<_> = options.engines.iter().map(|spec| &spec.name).collect();
+        assert_eq!(names, vec!["a", "a (2)", "a (3)"]);
+
+        let args = ["--engine", "a", "--engine", "b", "--repetition", "1"];
+        assert!(parse_args(args.iter().map(|arg| arg.to_string())).is_err());
+    }
+
+    #[test]
+    fn plays_out() {
+        let mut arena = Arena::new(engines(&["random", "random"]), settings(), connect);
+        let mut logged = 0;
+        arena.round_robin(1, &mut |_| logged += 1);
+        assert_eq!(logged, 2);
+        for record in &arena.records {
+            assert!(record.outcome().is_some());
+            let mut game = record.replay().unwrap();
+            assert!(game.winner().is_some());
+        }
+        assert_eq!(arena.standings.0[0].games(), 2);
+    }
+
+    #[test_case::test_case("silent", "time_forfeit")]
+    #[test_case::test_case("crash", "crash")]
+    #[test_case::test_case("cheat", "illegal_action")]
+    fn forfeits(kind: &str, termination: &str) {
+        let mut arena = Arena::new(engines(&["random", kind]), settings(), connect);
+        arena.round_robin(1, &mut |_| ());
+        for record in &arena.records {
+            assert_eq!(record.tag("Termination"), Some(termination));
+            assert!(record.validate_trusting(&ARENA_TERMINATIONS).is_ok());
+        }
+        assert_eq!(arena.standings.0[0].wins, 2);
+        assert_eq!(arena.standings.0[1].losses, 2);
+    }
+
+    #[test]
+    fn max_plies() {
+        let settings = Settings {
+            max_plies: 50,
+            ..settings()
+        };
+        let mut arena = Arena::new(engines(&["stubborn", "stubborn"]), settings, connect);
+        arena.round_robin(1, &mut |_| ());
+        for record in &arena.records {
+            assert_eq!(record.outcome(), Some(Outcome::Draw));
+            assert_eq!(record.tag("Termination"), Some("max_plies"));
+            assert!(record.validate_trusting(&ARENA_TERMINATIONS).is_ok());
+            assert_eq!(record.actions.len(), 50);
+        }
+        assert_eq!(arena.standings.0[0].draws, 2);
+    }
+
+    #[test]
+    fn swiss() {
+        let kinds = ["random", "random", "crash"];
+        let mut arena = Arena::new(engines(&kinds), settings(), connect);
+        arena.swiss(2, &mut |_| ());
+        assert_eq!(arena.records.len(), 4);
+        let byes: usize = arena.standings.0.iter().map(|standing| standing.byes).sum();
+        assert_eq!(byes, 2);
+    }
+}
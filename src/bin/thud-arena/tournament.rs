@@ -0,0 +1,249 @@
+//! Pairings and standings for round-robin and Swiss tournaments.
+//!
+//! Thud is not symmetric, so every pairing is a match of two games with the engines swapping
+//! sides.
+
+use std::cmp::Ordering;
+use std::fmt;
+use thud::{Outcome, Player};
+
+/// One engine's results so far
+#[derive(Debug, Clone, Default)]
+pub struct Standing {
+    pub name: String,
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+    /// Rounds of a Swiss tournament sat out, each worth a win
+    pub byes: usize,
+    /// Indices of the engines already paired with this one
+    pub opponents: Vec<usize>,
+}
+
+impl Standing {
+    pub fn games(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Points scored, counting a win or a bye as 1 and a draw as half
+    pub fn points(&self) -> f64 {
+        (self.wins + self.byes) as f64 + self.draws as f64 / 2.0
+    }
+}
+
+/// Every engine's results, which can be written as a table
+#[derive(Debug, Clone)]
+pub struct Standings(pub Vec<Standing>);
+
+impl Standings {
+    pub fn new(names: Vec<String>) -> Self {
+        Standings(
+            names
+                .into_iter()
+                .map(|name| Standing {
+                    name,
+                    ..Standing::default()
+                })
+                .collect(),
+        )
+    }
+
+    /// Count the result of a game between `dwarf` and `troll`
+    pub fn add(&mut self, dwarf: usize, troll: usize, outcome: Outcome) {
+        match outcome {
+            Outcome::Won(Player::Dwarf) => {
+                self.0[dwarf].wins += 1;
+                self.0[troll].losses += 1;
+            }
+            Outcome::Won(Player::Troll) => {
+                self.0[troll].wins += 1;
+                self.0[dwarf].losses += 1;
+            }
+            Outcome::Draw => {
+                self.0[dwarf].draws += 1;
+                self.0[troll].draws += 1;
+            }
+        }
+    }
+
+    /// Note that `a` and `b` have been paired
+    pub fn pair(&mut self, a: usize, b: usize) {
+        self.0[a].opponents.push(b);
+        self.0[b].opponents.push(a);
+    }
+
+    /// Get the engines' indices from first place to last
+    pub fn ranking(&self) -> Vec<usize> {
+        let mut ranking: Vec<usize> = (0..self.0.len()).collect();
+        ranking.sort_by(|&a, &b| {
+            let (a_standing, b_standing) = (&self.0[a], &self.0[b]);
+            b_standing
+                .points()
+                .partial_cmp(&a_standing.points())
+                .unwrap_or(Ordering::Equal)
+                .then(b_standing.wins.cmp(&a_standing.wins))
+                .then(a.cmp(&b))
+        });
+        ranking
+    }
+}
+
+impl fmt::Display for Standings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .0
+            .iter()
+            .map(|standing| standing.name.len())
+            .max()
+            .unwrap_or(0)
+            .max("Engine".len());
+        writeln!(
+            f,
+            "Rank  {:width$}  Games  Wins  Draws  Losses  Points",
+            "Engine",
+            width = width
+        )?;
+        for (rank, &index) in self.ranking().iter().enumerate() {
+            let standing = &self.0[index];
+            writeln!(
+                f,
+                "{:>4}  {:width$}  {:>5}  {:>4}  {:>5}  {:>6}  {:>6.1}",
+                rank + 1,
+                standing.name,
+                standing.games(),
+                standing.wins,
+                standing.draws,
+                standing.losses,
+                standing.points(),
+                width = width
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Pair every engine with every other once per cycle, giving the pairings of each round
+///
+/// Rounds follow the circle method, so nobody plays twice in a round.
+pub fn round_robin(engines: usize, cycles: usize) -> Vec<Vec<(usize, usize)>> {
+    // An odd field gets a dummy entrant, and whoever meets it sits the round out
+    let size = engines + engines % 2;
+    let mut rounds = Vec::new();
+    for _ in 0..cycles {
+        for round in 0..size.saturating_sub(1) {
+            let mut circle: Vec<usize> = (1..size).collect();
+            circle.rotate_left(round);
+            circle.insert(0, 0);
+            let pairings = (0..size / 2)
+                .map(|i| (circle[i], circle[size - 1 - i]))
+                .filter(|&(a, b)| a < engines && b < engines)
+                .collect();
+            rounds.push(pairings);
+        }
+    }
+    rounds
+}
+
+/// Pair `unpaired`, in order of preference, so that nobody meets an engine they have already met
+fn pair_fresh(standings: &Standings, unpaired: &[usize]) -> Option<Vec<(usize, usize)>> {
+    let (&first, rest) = match unpaired.split_first() {
+        Some(split) => split,
+        None => return Some(Vec::new()),
+    };
+    for (i, &other) in rest.iter().enumerate() {
+        if standings.0[first].opponents.contains(&other) {
+            continue;
+        }
+        let mut left = rest.to_vec();
+        left.remove(i);
+        if let Some(mut pairings) = pair_fresh(standings, &left) {
+            pairings.insert(0, (first, other));
+            return Some(pairings);
+        }
+    }
+    None
+}
+
+/// Pair engines for the next round of a Swiss tournament, giving the pairings and the engine
+/// which sits the round out, if any
+///
+/// Engines are taken from the top of the standings down, each meeting the highest engine it
+/// has not met yet which still lets everyone else be paired. Once that can't be done, rematches
+/// are allowed.
+pub fn swiss(standings: &Standings) -> (Vec<(usize, usize)>, Option<usize>) {
+    let mut unpaired = standings.ranking();
+    let mut bye = None;
+    if unpaired.len() % 2 == 1 {
+        // The bye goes to the lowest ranked of the engines with the fewest byes
+        let index = (0..unpaired.len())
+            .rev()
+            .min_by_key(|&i| standings.0[unpaired[i]].byes)
+            .unwrap_or(0);
+        bye = Some(unpaired.remove(index));
+    }
+
+    let pairings = pair_fresh(standings, &unpaired)
+        .unwrap_or_else(|| unpaired.chunks(2).map(|pair| (pair[0], pair[1])).collect());
+    (pairings, bye)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn round_robin_meets_everyone() {
+        for engines in 2..7 {
+            let rounds = round_robin(engines, 2);
+            let mut met = HashSet::new();
+            for round in &rounds {
+                let mut seen = HashSet::new();
+                for &(a, b) in round {
+                    assert!(seen.insert(a) && seen.insert(b));
+                    met.insert((a.min(b), a.max(b)));
+                }
+            }
+            let pairings: usize = rounds.iter().map(Vec::len).sum();
+            assert_eq!(pairings, engines * (engines - 1));
+            assert_eq!(met.len(), engines * (engines - 1) / 2);
+        }
+    }
+
+    #[test]
+    fn swiss_avoids_rematches() {
+        let mut standings = Standings::new((0..5).map(|i| i.to_string()).collect());
+        let mut byes = Vec::new();
+        for _ in 0..4 {
+            let (pairings, bye) = swiss(&standings);
+            assert_eq!(pairings.len(), 2);
+            let bye = bye.unwrap();
+            standings.0[bye].byes += 1;
+            byes.push(bye);
+            for (a, b) in pairings {
+                assert!(!standings.0[a].opponents.contains(&b));
+                standings.pair(a, b);
+                standings.add(a, b, Outcome::Won(Player::Dwarf));
+                standings.add(b, a, Outcome::Draw);
+            }
+        }
+        let unique: HashSet<_> = byes.iter().collect();
+        assert_eq!(unique.len(), byes.len());
+    }
+
+    #[test]
+    fn table() {
+        let mut standings = Standings::new(vec!["greedy".to_string(), "random".to_string()]);
+        standings.add(1, 0, Outcome::Won(Player::Dwarf));
+        standings.add(0, 1, Outcome::Draw);
+        standings.add(0, 1, Outcome::Won(Player::Dwarf));
+        assert_eq!(
+            standings.to_string(),
+            "\
+Rank  Engine  Games  Wins  Draws  Losses  Points
+   1  greedy      3     1      1       1     1.5
+   2  random      3     1      1       1     1.5
+"
+        );
+    }
+}
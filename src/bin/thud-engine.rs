@@ -0,0 +1,49 @@
+//! A Thud engine speaking the protocol in `thud::protocol` on stdin and stdout, playing with one
+//! of the built-in agents.
+
+use std::env;
+use std::io;
+use std::process;
+use thud::protocol::Engine;
+use thud::{Agent, GreedyAgent, RandomAgent};
+
+const USAGE: &str = "\
+Usage: thud-engine [OPTIONS]
+
+Options:
+  --random      Play randomly rather than greedily
+  --seed <N>    Seed the engine's choices
+  -h, --help    Show this message";
+
+fn main() {
+    let mut random = false;
+    let mut seed = 0;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--random" => random = true,
+            "--seed" => match args.next().and_then(|value| value.parse().ok()) {
+                Some(value) => seed = value,
+                None => {
+                    eprintln!("--seed needs a number");
+                    process::exit(2);
+                }
+            },
+            _ => {
+                eprintln!("{}", USAGE);
+                process::exit(2);
+            }
+        }
+    }
+
+    let agent: Box<dyn Agent> = if random {
+        Box::new(RandomAgent::new(seed))
+    } else {
+        Box::new(GreedyAgent::new(seed))
+    };
+    let stdin = io::stdin();
+    if let Err(error) = Engine::new(agent).run(stdin.lock(), io::stdout()) {
+        eprintln!("{}", error);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,41 @@
+//! Host games of Thud over HTTP and WebSockets, as described in `thud::server`.
+
+use std::env;
+use std::process;
+use thud::server::Server;
+
+const USAGE: &str = "\
+Usage: thud-server [OPTIONS]
+
+Options:
+  --bind <ADDRESS>  Listen on ADDRESS [default: 127.0.0.1:8080]
+  -h, --help        Show this message";
+
+fn main() {
+    let mut address = "127.0.0.1:8080".to_string();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("--bind", Some(value)) => address = value,
+            _ => {
+                eprintln!("{}", USAGE);
+                process::exit(2);
+            }
+        }
+    }
+
+    let server = match Server::bind(&address) {
+        Ok(server) => server,
+        Err(error) => {
+            eprintln!("Couldn't listen on {}: {}", address, error);
+            process::exit(1);
+        }
+    };
+    if let Ok(address) = server.local_addr() {
+        eprintln!("Listening on http://{}", address);
+    }
+    if let Err(error) = server.run() {
+        eprintln!("{}", error);
+        process::exit(1);
+    }
+}
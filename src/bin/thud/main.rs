@@ -0,0 +1,335 @@
+//! Play Thud in the terminal, hot-seat or against a built-in agent.
+
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+use thud::{Action, Agent, Coord, EndState, GreedyAgent, Player, RandomAgent, Thud};
+
+#[cfg(feature = "tui")]
+mod tui;
+
+const USAGE: &str = "\
+Usage: thud [OPTIONS]
+
+Options:
+  --agent <dwarf|troll>  Let the computer play one side
+  --random               Make the computer play randomly rather than greedily
+  --seed <N>             Seed the computer's choices
+  --load <FILE>          Carry on a saved game
+  --tui                  Play full-screen, selecting pieces with the cursor keys
+  -h, --help             Show this message";
+
+const HELP: &str = "\
+Commands:
+  F1-F6          Move a piece
+  F1xF6          Hurl a dwarf or shove a troll
+  G9*N,NE        Capture with a troll in some compass directions, or G9* for none
+  moves <square> List where the piece on a square can go
+  offer          Propose ending the game; answer with accept or decline
+  resign         Concede the game
+  undo           Take back the last action
+  save <file>    Save the game
+  load <file>    Load a saved game
+  help           Show this message
+  quit           Leave";
+
+struct Options {
+    agent: Option<Player>,
+    random: bool,
+    seed: u64,
+    load: Option<String>,
+    tui: bool,
+}
+
+fn parse_player(name: &str) -> Result<Player, String> {
+    match name.to_ascii_lowercase().as_str() {
+        "dwarf" => Ok(Player::Dwarf),
+        "troll" => Ok(Player::Troll),
+        _ => Err(format!("Unknown side {:?}", name)),
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        agent: None,
+        random: false,
+        seed: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_nanos() as u64)
+            .unwrap_or_default(),
+        load: None,
+        tui: false,
+    };
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--agent" => options.agent = Some(parse_player(&value()?)?),
+            "--random" => options.random = true,
+            "--seed" => {
+                options.seed = value()?
+                    .parse()
+                    .map_err(|_| "--seed needs a number".to_string())?
+            }
+            "--load" => options.load = Some(value()?),
+            "--tui" if cfg!(feature = "tui") => options.tui = true,
+            "--tui" => return Err("thud was built without the tui feature".to_string()),
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            _ => return Err(format!("Unknown argument {:?}\n\n{}", arg, USAGE)),
+        }
+    }
+    Ok(options)
+}
+
+/// The computer player, if there is one, and the side it plays
+type Opponent = Option<(Player, Box<dyn Agent>)>;
+
+fn name(player: Player) -> &'static str {
+    match player {
+        Player::Dwarf => "Dwarf",
+        Player::Troll => "Troll",
+    }
+}
+
+fn outcome(end: EndState) -> String {
+    let reason = end.reason();
+    match end {
+        EndState::Won(player, _) => format!("{} won ({}).", name(player), reason),
+        EndState::Draw(_) => format!("Drawn ({}).", reason),
+    }
+}
+
+/// Find who has to act next, which is the opponent if an end has been offered
+fn to_act(game: &Thud) -> Option<Player> {
+    match game.end_offer() {
+        Some(player) => Some(player.opponent()),
+        None => game.turn(),
+    }
+}
+
+/// Let the computer take actions until the player has to act, returning what it did
+fn play_agent(game: &mut Thud, agent: &mut Opponent) -> io::Result<Vec<(Player, Action)>> {
+    let mut played = Vec::new();
+    if let Some((side, agent)) = agent {
+        while game.winner().is_none() && to_act(game) == Some(*side) {
+            match agent.choose(game) {
+                Some(action) => {
+                    game.apply(action.clone()).map_err(io::Error::other)?;
+                    played.push((*side, action));
+                }
+                None => break,
+            }
+        }
+    }
+    Ok(played)
+}
+
+/// Take back the last action, along with the computer's actions before it so it is the player's
+/// turn again
+fn take_back(game: &mut Thud, agent: &Opponent) -> Option<Action> {
+    let action = game.undo()?;
+    if let Some((side, _)) = agent {
+        while to_act(game) == Some(*side) && game.undo().is_some() {}
+    }
+    Some(action)
+}
+
+fn show(game: &mut Thud, output: &mut impl Write) -> io::Result<()> {
+    let (dwarf, troll) = game.score();
+    writeln!(output, "\n{}\n", game.board())?;
+    write!(output, "Dwarves {}, trolls {}. ", dwarf, troll)?;
+
+    if let Some(end) = game.winner() {
+        writeln!(output, "{}", outcome(end))
+    } else if let Some(player) = game.end_offer() {
+        writeln!(
+            output,
+            "{} offers to end the game; {}, accept or decline?",
+            name(player),
+            name(player.opponent())
+        )
+    } else if let Some(troll) = game.pending_capture() {
+        let options = game.capture_options(troll).map_err(io::Error::other)?;
+        let points: Vec<String> = options
+            .subsets()
+            .map(|targets| Action::Capture(troll, targets).to_string())
+            .collect();
+        writeln!(output, "Troll to capture: {}", points.join(" "))
+    } else {
+        let player = game.turn().expect("game has not ended");
+        writeln!(output, "{} to move.", name(player))
+    }
+}
+
+/// Play a game, reading commands from `input`, until it runs out or the player quits.
+fn run(
+    mut game: Thud,
+    mut agent: Opponent,
+    input: impl BufRead,
+    output: &mut impl Write,
+) -> io::Result<Thud> {
+    let mut lines = input.lines();
+    loop {
+        for (side, action) in play_agent(&mut game, &mut agent)? {
+            writeln!(output, "{} plays {}", name(side), action)?;
+        }
+
+        show(&mut game, output)?;
+        write!(output, "> ")?;
+        output.flush()?;
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => return Ok(game),
+        };
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words[..] {
+            [] => (),
+            ["quit"] | ["exit"] => return Ok(game),
+            ["help"] | ["?"] => writeln!(output, "{}", HELP)?,
+            ["undo"] => match take_back(&mut game, &agent) {
+                Some(action) => writeln!(output, "Took back {}", action)?,
+                None => writeln!(output, "Nothing to take back")?,
+            },
+            ["moves", square] => match square.parse::<Coord>() {
+                Ok(square) => {
+                    let targets: Vec<String> = game
+                        .board()
+                        .available_moves(square)
+                        .iter()
+                        .map(Coord::to_string)
+                        .collect();
+                    writeln!(output, "{}: {}", square, targets.join(" "))?
+                }
+                Err(error) => writeln!(output, "Error: {}", error)?,
+            },
+            ["save", path] => match fs::write(path, game.encode()) {
+                Ok(()) => writeln!(output, "Saved to {}", path)?,
+                Err(error) => writeln!(output, "Error: {}", error)?,
+            },
+            ["load", path] => match fs::read(path) {
+                Ok(bytes) => match Thud::decode(&bytes) {
+                    Ok(loaded) => game = loaded,
+                    Err(error) => writeln!(output, "Error: {}", error)?,
+                },
+                Err(error) => writeln!(output, "Error: {}", error)?,
+            },
+            ["resign"] => match to_act(&game) {
+                Some(player) => {
+                    if let Err(error) = game.resign(player) {
+                        writeln!(output, "Error: {}", error)?
+                    }
+                }
+                None => writeln!(output, "Error: the game is over")?,
+            },
+            _ => match line.parse::<Action>() {
+                Ok(action) => {
+                    if let Err(error) = game.apply(action) {
+                        writeln!(output, "Error: {}", error)?
+                    }
+                }
+                Err(error) => writeln!(output, "Error: {}; type help for commands", error)?,
+            },
+        }
+    }
+}
+
+fn main() {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}", message);
+            process::exit(2);
+        }
+    };
+
+    let game = match &options.load {
+        Some(path) => {
+            let loaded = fs::read(path)
+                .map_err(|error| error.to_string())
+                .and_then(|bytes| Thud::decode(&bytes).map_err(|error| error.to_string()));
+            match loaded {
+                Ok(game) => game,
+                Err(error) => {
+                    eprintln!("Couldn't load {}: {}", path, error);
+                    process::exit(1);
+                }
+            }
+        }
+        None => Thud::new(),
+    };
+
+    let agent = options.agent.map(|side| {
+        let agent: Box<dyn Agent> = if options.random {
+            Box::new(RandomAgent::new(options.seed))
+        } else {
+            Box::new(GreedyAgent::new(options.seed))
+        };
+        (side, agent)
+    });
+
+    #[cfg(feature = "tui")]
+    if options.tui {
+        if let Err(error) = tui::play(game, agent) {
+            eprintln!("{}", error);
+            process::exit(1);
+        }
+        return;
+    }
+
+    println!("Thud! Type help for commands.");
+    let stdin = io::stdin();
+    if let Err(error) = run(game, agent, stdin.lock(), &mut io::stdout()) {
+        eprintln!("{}", error);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use thud::{Board, Piece};
+
+    fn play(agent: Option<(Player, Box<dyn Agent>)>, input: &str) -> (Thud, String) {
+        let mut output = Vec::new();
+        let game = run(Thud::new(), agent, input.as_bytes(), &mut output).unwrap();
+        (game, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn hot_seat() {
+        let (game, output) = play(None, "G1-G6\nH9-H10\nG1-G2\nH10*\nundo\n");
+        assert!(output.contains("Troll to capture: H10*"));
+        assert!(output.contains("Error: Action not allowed at this point in the game"));
+        assert_eq!(
+            game.history(),
+            &[
+                Action::Move((6, 0).into(), (6, 5).into()),
+                Action::Move((7, 8).into(), (7, 9).into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn against_agent() {
+        let agent: Box<dyn Agent> = Box::new(RandomAgent::new(3));
+        let (game, output) = play(Some((Player::Troll, agent)), "G1-G6\nundo\nresign\n");
+        assert!(output.contains("Troll plays "));
+        assert_eq!(game.history(), &[Action::Resign(Player::Dwarf)]);
+        assert!(output.contains("Troll won (resignation)."));
+    }
+
+    #[test]
+    fn no_moves_left() {
+        let mut board = Board::default();
+        board.place((7, 7).into(), Piece::Thudstone);
+        board.place((5, 5).into(), Piece::Troll);
+        board.place((5, 7).into(), Piece::Dwarf);
+        let start = Thud::from_board(board, Player::Troll).unwrap();
+        let mut output = Vec::new();
+        run(start, None, "F6-F7\nF7*N\n".as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("Troll won (no moves left)."));
+    }
+}
@@ -0,0 +1,466 @@
+//! Full-screen interface, enabled with the `tui` feature.
+
+use crate::{name, outcome, play_agent, take_back, to_act, Opponent};
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use crossterm::style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use std::io::{self, Write};
+use thud::{Action, Coord, Direction, Piece, Player, Thud};
+
+const KEYS: &str = "arrows: move  enter/space: select, move or toggle capture  x: attack  \
+c: capture  esc: cancel  u: undo  o: offer end  y/n: accept/decline  R: resign  q: quit";
+
+/// Column the side panel starts at
+const PANEL: u16 = 36;
+
+/// Number of history entries shown in the side panel
+const HISTORY_LINES: usize = 14;
+
+/// Something the player asked for with a key
+#[derive(Debug, PartialEq, Copy, Clone)]
+enum Key {
+    Up,
+    Down,
+    Left,
+    Right,
+    Select,
+    Attack,
+    Capture,
+    Cancel,
+    Undo,
+    Offer,
+    Accept,
+    Decline,
+    Resign,
+    Quit,
+}
+
+impl Key {
+    fn from_event(event: KeyEvent) -> Option<Self> {
+        if event.kind != KeyEventKind::Press {
+            return None;
+        }
+        Some(match event.code {
+            KeyCode::Up | KeyCode::Char('k') => Key::Up,
+            KeyCode::Down | KeyCode::Char('j') => Key::Down,
+            KeyCode::Left | KeyCode::Char('h') => Key::Left,
+            KeyCode::Right | KeyCode::Char('l') => Key::Right,
+            KeyCode::Enter | KeyCode::Char(' ') => Key::Select,
+            KeyCode::Char('x') => Key::Attack,
+            KeyCode::Char('c') => Key::Capture,
+            KeyCode::Esc => Key::Cancel,
+            KeyCode::Char('u') => Key::Undo,
+            KeyCode::Char('o') => Key::Offer,
+            KeyCode::Char('y') => Key::Accept,
+            KeyCode::Char('n') => Key::Decline,
+            KeyCode::Char('R') => Key::Resign,
+            KeyCode::Char('q') => Key::Quit,
+            _ => return None,
+        })
+    }
+}
+
+/// Everything on screen, apart from the game itself
+struct App {
+    game: Thud,
+    agent: Opponent,
+    cursor: Coord,
+    selected: Option<Coord>,
+    captures: Vec<Direction>,
+    message: String,
+    quit: bool,
+}
+
+impl App {
+    fn new(game: Thud, agent: Opponent) -> Self {
+        let mut app = App {
+            game,
+            agent,
+            cursor: (7, 7).into(),
+            selected: None,
+            captures: Vec::new(),
+            message: String::new(),
+            quit: false,
+        };
+        app.let_agent_play();
+        app
+    }
+
+    fn let_agent_play(&mut self) {
+        match play_agent(&mut self.game, &mut self.agent) {
+            Ok(played) => {
+                if let Some((side, _)) = played.first() {
+                    let actions: Vec<String> = played.iter().map(|(_, a)| a.to_string()).collect();
+                    self.message = format!("{} played {}", name(*side), actions.join(" "));
+                }
+            }
+            Err(error) => self.message = format!("Error: {}", error),
+        }
+    }
+
+    /// Take an action, reporting any error, and let the computer reply
+    fn take(&mut self, action: Action) {
+        match self.game.apply(action) {
+            Ok(()) => {
+                self.selected = None;
+                self.captures.clear();
+                self.message.clear();
+                self.let_agent_play();
+            }
+            Err(error) => self.message = format!("Error: {}", error),
+        }
+    }
+
+    /// Squares the selected piece could go to
+    fn targets(&self) -> Vec<Coord> {
+        match self.selected {
+            Some(piece) => self.game.board().available_moves(piece),
+            None => Vec::new(),
+        }
+    }
+
+    /// Dwarves the troll which just moved could capture, and the direction of each
+    fn capture_choices(&self) -> Vec<(Direction, Coord)> {
+        let troll = match self.game.pending_capture() {
+            Some(troll) => troll,
+            None => return Vec::new(),
+        };
+        match self.game.capture_options(troll) {
+            Ok(options) => options
+                .directions()
+                .iter()
+                .filter_map(|&dir| dir.modify(troll).ok().map(|square| (dir, square)))
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    fn move_cursor(&mut self, (dx, dy): (isize, isize)) {
+        let (mut x, mut y) = (
+            self.cursor.value().0 as isize,
+            self.cursor.value().1 as isize,
+        );
+        // Skip over the corners which aren't on the board
+        loop {
+            x += dx;
+            y += dy;
+            if !(0..15).contains(&x) || !(0..15).contains(&y) {
+                return;
+            }
+            if let Ok(square) = Coord::zero_based(x as usize, y as usize) {
+                self.cursor = square;
+                return;
+            }
+        }
+    }
+
+    fn own_piece(&self, square: Coord) -> bool {
+        let piece = self.game.board().get(square);
+        match to_act(&self.game) {
+            Some(Player::Dwarf) => piece == Piece::Dwarf,
+            Some(Player::Troll) => piece == Piece::Troll,
+            None => false,
+        }
+    }
+
+    fn select(&mut self) {
+        if let Some(troll) = self.game.pending_capture() {
+            if self.cursor == troll {
+                self.capture();
+            } else if let Some(&(dir, _)) = self
+                .capture_choices()
+                .iter()
+                .find(|(_, square)| *square == self.cursor)
+            {
+                match self.captures.iter().position(|&other| other == dir) {
+                    Some(index) => {
+                        self.captures.remove(index);
+                    }
+                    None => self.captures.push(dir),
+                }
+            } else {
+                self.message = "Choose dwarves next to the troll, then press c".to_string();
+            }
+            return;
+        }
+
+        match self.selected {
+            Some(piece) if piece == self.cursor => self.selected = None,
+            _ if self.own_piece(self.cursor) => {
+                self.selected = Some(self.cursor);
+                self.message.clear();
+            }
+            Some(piece) => {
+                // Prefer a plain move, but fall back to an attack
+                let (mut moved, mut attacked) = (self.game.clone(), self.game.clone());
+                match moved.move_piece(piece, self.cursor) {
+                    Ok(()) => self.take(Action::Move(piece, self.cursor)),
+                    Err(_) if attacked.attack(piece, self.cursor).is_ok() => {
+                        self.take(Action::Attack(piece, self.cursor))
+                    }
+                    Err(error) => self.message = format!("Error: {}", error),
+                }
+            }
+            None => self.message = "Select one of your pieces".to_string(),
+        }
+    }
+
+    fn capture(&mut self) {
+        match self.game.pending_capture() {
+            Some(troll) => {
+                let captures = self.captures.clone();
+                self.take(Action::Capture(troll, captures));
+            }
+            None => self.message = "There is nothing to capture".to_string(),
+        }
+    }
+
+    fn handle(&mut self, key: Key) {
+        match key {
+            Key::Up => self.move_cursor((0, 1)),
+            Key::Down => self.move_cursor((0, -1)),
+            Key::Left => self.move_cursor((-1, 0)),
+            Key::Right => self.move_cursor((1, 0)),
+            Key::Select => self.select(),
+            Key::Attack => match self.selected {
+                Some(piece) => self.take(Action::Attack(piece, self.cursor)),
+                None => self.message = "Select one of your pieces".to_string(),
+            },
+            Key::Capture => self.capture(),
+            Key::Cancel => {
+                self.selected = None;
+                self.captures.clear();
+                self.message.clear();
+            }
+            Key::Undo => {
+                self.selected = None;
+                self.captures.clear();
+                self.message = match take_back(&mut self.game, &self.agent) {
+                    Some(action) => format!("Took back {}", action),
+                    None => "Nothing to take back".to_string(),
+                };
+            }
+            Key::Offer => self.take(Action::OfferEnd),
+            Key::Accept => self.take(Action::AcceptEnd),
+            Key::Decline => self.take(Action::DeclineEnd),
+            Key::Resign => {
+                if let Some(player) = to_act(&self.game) {
+                    self.take(Action::Resign(player));
+                }
+            }
+            Key::Quit => self.quit = true,
+        }
+    }
+
+    /// Describe whose turn it is and what they need to do
+    fn status(&mut self) -> String {
+        if let Some(end) = self.game.winner() {
+            outcome(end)
+        } else if let Some(player) = self.game.end_offer() {
+            format!(
+                "{} offers to end; {} to answer",
+                name(player),
+                name(player.opponent())
+            )
+        } else if self.game.pending_capture().is_some() {
+            "Troll to capture".to_string()
+        } else {
+            let player = self.game.turn().expect("game has not ended");
+            format!("{} to move", name(player))
+        }
+    }
+
+    fn draw(&mut self, out: &mut impl Write) -> io::Result<()> {
+        queue!(out, Clear(ClearType::All))?;
+
+        let targets = self.targets();
+        let choices = self.capture_choices();
+        let board = self.game.board();
+        for y in 0..15 {
+            let row = 15 - y as u16;
+            queue!(out, MoveTo(0, row), Print(format!("{:>2} ", y + 1)))?;
+            for x in 0..15 {
+                let square = match Coord::zero_based(x, y) {
+                    Ok(square) => square,
+                    Err(_) => {
+                        queue!(out, Print("  "))?;
+                        continue;
+                    }
+                };
+                let (symbol, colour) = match board.get(square) {
+                    Piece::Dwarf => ('d', Color::Yellow),
+                    Piece::Troll => ('T', Color::Green),
+                    Piece::Thudstone => ('O', Color::White),
+                    Piece::Empty => ('.', Color::DarkGrey),
+                };
+                let background = if square == self.cursor {
+                    Color::Grey
+                } else if Some(square) == self.selected {
+                    Color::DarkBlue
+                } else if choices
+                    .iter()
+                    .any(|&(dir, other)| other == square && self.captures.contains(&dir))
+                {
+                    Color::DarkRed
+                } else if targets.contains(&square) || choices.iter().any(|&(_, s)| s == square) {
+                    Color::DarkGreen
+                } else {
+                    Color::Reset
+                };
+                queue!(
+                    out,
+                    SetBackgroundColor(background),
+                    SetForegroundColor(colour),
+                    Print(format!("{} ", symbol)),
+                    ResetColor
+                )?;
+            }
+        }
+        queue!(
+            out,
+            MoveTo(3, 0),
+            Print("A B C D E F G H J K L M N O P"),
+            MoveTo(3, 16),
+            Print("A B C D E F G H J K L M N O P")
+        )?;
+
+        let (dwarf, troll) = self.game.score();
+        let status = self.status();
+        let lines = [
+            format!("Dwarves: {:>3}", dwarf),
+            format!("Trolls:  {:>3}", troll),
+            String::new(),
+            status,
+            format!("Cursor: {}", self.cursor),
+            String::new(),
+            "History:".to_string(),
+        ];
+        for (row, line) in lines.iter().enumerate() {
+            queue!(out, MoveTo(PANEL, row as u16), Print(line))?;
+        }
+        let history = self.game.history();
+        let skip = history.len().saturating_sub(HISTORY_LINES);
+        for (row, (ply, action)) in history.iter().enumerate().skip(skip).enumerate() {
+            queue!(
+                out,
+                MoveTo(PANEL, (lines.len() + row) as u16),
+                Print(format!("{:>4}. {}", ply + 1, action))
+            )?;
+        }
+
+        queue!(
+            out,
+            MoveTo(0, 18),
+            Print(&self.message),
+            MoveTo(0, 20),
+            SetForegroundColor(Color::DarkGrey),
+            Print(KEYS),
+            ResetColor
+        )?;
+        out.flush()
+    }
+}
+
+/// Play a game full-screen until the player quits
+pub fn play(game: Thud, agent: Opponent) -> io::Result<Thud> {
+    let mut app = App::new(game, agent);
+    let mut out = io::stdout();
+
+    terminal::enable_raw_mode()?;
+    execute!(out, EnterAlternateScreen, Hide)?;
+    let result = (|| {
+        while !app.quit {
+            app.draw(&mut out)?;
+            if let Event::Key(event) = event::read()? {
+                if let Some(key) = Key::from_event(event) {
+                    app.handle(key);
+                }
+            }
+        }
+        Ok(())
+    })();
+    execute!(out, Show, LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+
+    result.map(|()| app.game)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(app: &mut App, keys: &[Key]) {
+        for &key in keys {
+            app.handle(key);
+        }
+    }
+
+    fn go_to(app: &mut App, square: Coord) {
+        app.cursor = square;
+    }
+
+    #[test]
+    fn select_and_move() {
+        let mut app = App::new(Thud::new(), None);
+        app.handle(Key::Select);
+        assert_eq!(app.message, "Select one of your pieces");
+
+        go_to(&mut app, (6, 0).into());
+        app.handle(Key::Select);
+        assert_eq!(app.selected, Some((6, 0).into()));
+        assert!(app.targets().contains(&(6, 5).into()));
+
+        press(&mut app, &[Key::Up; 5]);
+        app.handle(Key::Select);
+        assert_eq!(app.selected, None);
+        assert_eq!(
+            app.game.history(),
+            &[Action::Move((6, 0).into(), (6, 5).into())]
+        );
+    }
+
+    #[test]
+    fn toggle_captures() {
+        let mut app = App::new(Thud::new(), None);
+        app.game.move_piece((6, 0).into(), (6, 5).into()).unwrap();
+        go_to(&mut app, (6, 6).into());
+        app.handle(Key::Select);
+        app.handle(Key::Left);
+        app.handle(Key::Select);
+        assert_eq!(app.game.pending_capture(), Some((5, 6).into()));
+        assert_eq!(
+            app.capture_choices(),
+            vec![(Direction::DownRight, (6, 5).into())]
+        );
+
+        go_to(&mut app, (6, 5).into());
+        press(&mut app, &[Key::Select, Key::Select, Key::Select]);
+        assert_eq!(app.captures, vec![Direction::DownRight]);
+        app.handle(Key::Capture);
+        assert_eq!(app.game.score(), (31, 32));
+        assert_eq!(app.game.turn(), Some(Player::Dwarf));
+    }
+
+    #[test]
+    fn cursor_skips_corners() {
+        let mut app = App::new(Thud::new(), None);
+        go_to(&mut app, (5, 0).into());
+        app.handle(Key::Left);
+        assert_eq!(app.cursor, (5, 0).into());
+        go_to(&mut app, (0, 5).into());
+        app.handle(Key::Down);
+        assert_eq!(app.cursor, (0, 5).into());
+    }
+
+    #[test]
+    fn draws() {
+        let mut app = App::new(Thud::new(), None);
+        let mut screen = Vec::new();
+        app.draw(&mut screen).unwrap();
+        let screen = String::from_utf8(screen).unwrap();
+        assert!(screen.contains("Dwarf to move"));
+        assert!(screen.contains("History:"));
+    }
+}
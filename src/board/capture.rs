@@ -0,0 +1,93 @@
+use crate::Direction;
+#[cfg(feature = "serialize")]
+use serde::{Deserialize, Serialize};
+
+/// The dwarves a troll is able to capture once it has moved or shoved
+///
+/// Get one of these from [`Board::capture_options()`](struct.Board.html#method.capture_options)
+/// or [`Thud::capture_options()`](struct.Thud.html#method.capture_options).
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[derive(Debug, PartialEq, Clone)]
+pub struct CaptureOptions {
+    directions: Vec<Direction>,
+    after_shove: bool,
+}
+
+impl CaptureOptions {
+    pub(crate) fn new(directions: Vec<Direction>, after_shove: bool) -> Self {
+        CaptureOptions {
+            directions,
+            after_shove,
+        }
+    }
+
+    /// The [`Direction`s](enum.Direction.html) from the troll in which there is a dwarf to capture.
+    pub fn directions(&self) -> &[Direction] {
+        &self.directions
+    }
+
+    /// Whether at least one dwarf has to be captured, ie. the troll was shoved.
+    pub fn must_capture(&self) -> bool {
+        self.after_shove
+    }
+
+    /// Iterate over every valid set of captures, each of which can be passed to
+    /// [`Thud::troll_cap()`](struct.Thud.html#method.troll_cap).
+    ///
+    /// The empty set is only included (first) if the troll was not shoved. If the troll was
+    /// shoved and there is nothing to capture, the iterator will be empty.
+    ///
+    /// ```
+    /// use thud::{Board, Direction, Piece};
+    ///
+    /// let mut board = Board::default();
+    /// board.place((7, 7).into(), Piece::Troll);
+    /// board.place((7, 8).into(), Piece::Dwarf);
+    /// board.place((8, 7).into(), Piece::Dwarf);
+    ///
+    /// let options = board.capture_options((7, 7).into(), true).unwrap();
+    /// assert_eq!(options.subsets().count(), 3);
+    /// ```
+    pub fn subsets(&self) -> CaptureSubsets<'_> {
+        CaptureSubsets {
+            directions: &self.directions,
+            next: if self.after_shove { 1 } else { 0 },
+            end: 1 << self.directions.len(),
+        }
+    }
+}
+
+/// Iterator over the possible sets of captures in a [`CaptureOptions`](struct.CaptureOptions.html)
+pub struct CaptureSubsets<'a> {
+    directions: &'a [Direction],
+    // Bitmask over `directions` of the next subset to yield
+    next: usize,
+    end: usize,
+}
+
+impl<'a> Iterator for CaptureSubsets<'a> {
+    type Item = Vec<Direction>;
+    fn next(&mut self) -> Option<Vec<Direction>> {
+        if self.next >= self.end {
+            return None;
+        }
+
+        let mask = self.next;
+        self.next += 1;
+        Some(
+            self.directions
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| mask & (1 << i) != 0)
+                .map(|(_, dir)| *dir)
+                .collect(),
+        )
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.end.saturating_sub(self.next);
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a> ExactSizeIterator for CaptureSubsets<'a> {}
@@ -1,11 +1,17 @@
+mod capture;
 mod raycast;
+mod validate;
 use crate::coord::Coord;
 use crate::direction::Direction;
 use crate::piece::Piece;
-use crate::{EndState, Player, ThudError};
+use crate::{Action, EndReason, EndState, Symmetry, ThudError};
+
+pub use capture::{CaptureOptions, CaptureSubsets};
+pub use validate::BoardViolation;
 
 #[cfg(feature = "serialize")]
 use serde::{Deserialize, Serialize};
+use std::fmt;
 #[cfg(test)]
 mod tests;
 
@@ -15,9 +21,16 @@ mod tests;
 /// As a result, the movement methods provided only perform checks according to the pieces on the
 /// board, but they will *not* check whether the move is valid in terms of turn progress - you
 /// should use the methods on [`Thud`](struct.Thud.html) for that.
+///
+/// When deserialised with the `serialize` feature, the `Board` is checked with
+/// [`.validate()`](#method.validate); see
+/// [`Board::deserialize_unchecked()`](#method.deserialize_unchecked) to skip this.
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
-#[cfg_attr(feature = "serialize", serde(rename = "board"))]
-#[derive(Debug, Copy, Clone, Default)]
+#[cfg_attr(
+    feature = "serialize",
+    serde(rename = "board", try_from = "validate::RawBoard")
+)]
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
 pub struct Board {
     // 1-based indexing
     squares: [[Piece; 15]; 15],
@@ -38,11 +51,11 @@ impl Board {
         // Place the dwarves
         {
             // Diagonals
-            let calcs: Vec<Box<dyn Fn(usize) -> (usize, usize)>> = vec![
-                Box::new(|num| (num, 5 - num)),
-                Box::new(|num| (num + 9, num)),
-                Box::new(|num| (num, num + 9)),
-                Box::new(|num| (num + 9, 14 - num)),
+            let calcs: [fn(usize) -> (usize, usize); 4] = [
+                |num| (num, 5 - num),
+                |num| (num + 9, num),
+                |num| (num, num + 9),
+                |num| (num + 9, 14 - num),
             ];
             for calc in calcs {
                 for dwarf in (0..=5).map(|seed| calc(seed).into()) {
@@ -51,7 +64,7 @@ impl Board {
             }
 
             // Extras at corners
-            for dwarf in vec![
+            for dwarf in [
                 (0, 6),
                 (0, 8),
                 (14, 6),
@@ -125,20 +138,22 @@ impl Board {
 
     /// Move a troll.
     ///
-    /// Returns [`Err(ThudError::IllegalMove)`](enum.ThudError.html) if:
+    /// Returns:
     ///
-    /// - The `troll` square is not [`Piece::Troll`](enum.Piece.html)
-    /// - The `target` square is not [`Piece::Empty`](enum.Piece.html)
-    /// - The `target` square is more than 1 squares away from the `troll` square
+    /// - [`Err(ThudError::WrongPiece)`](enum.ThudError.html) if the `troll` square is not
+    ///   [`Piece::Troll`](enum.Piece.html)
+    /// - [`Err(ThudError::Occupied)`](enum.ThudError.html) if the `target` square is not
+    ///   [`Piece::Empty`](enum.Piece.html)
+    /// - [`Err(ThudError::TooFar)`](enum.ThudError.html) if the `target` square is more than 1
+    ///   square away from the `troll` square
     pub fn troll_move(&mut self, troll: Coord, target: Coord) -> MoveResult {
-        // Check the target is clear and the place we're moving from actually has a troll
-        if (self.get(troll), self.get(target)) != (Piece::Troll, Piece::Empty) {
-            return Err(ThudError::IllegalMove);
-        };
+        // Check the place we're moving from actually has a troll and the target is clear
+        self.expect(troll, Piece::Troll)?;
+        self.expect_empty(target)?;
 
         // Validate the move, ie. one space between them
         if troll.diff(target).max() != 1 {
-            return Err(ThudError::IllegalMove);
+            return Err(ThudError::TooFar(troll, target));
         }
 
         // Move the troll
@@ -150,20 +165,22 @@ impl Board {
 
     /// "Shove" a troll.
     ///
-    /// Returns [`Err(ThudError::IllegalMove)`](enum.ThudError.html) if:
-    ///
-    /// - The `troll` square is not [`Piece::Troll`](enum.Piece.html)
-    /// - The `target` square is not [`Piece::Empty`](enum.Piece.html)
-    /// - There are no [`Piece::Dwarf`s](enum.Piece.html) adjacent to the `target` square
-    ///
-    /// Returns [`Err(ThudError::Obstacle)`](enum.ThudError.html) if the target square is obstructed
+    /// Returns:
     ///
-    /// Returns [`Err(ThudError::LineTooShort)`](enum.ThudError.html) if the distance to the target
-    /// square is larger than the length of the line of trolls going in the other direction
+    /// - [`Err(ThudError::WrongPiece)`](enum.ThudError.html) if the `troll` square is not
+    ///   [`Piece::Troll`](enum.Piece.html)
+    /// - [`Err(ThudError::Occupied)`](enum.ThudError.html) if the `target` square is not
+    ///   [`Piece::Empty`](enum.Piece.html)
+    /// - [`Err(ThudError::NotStraight)`](enum.ThudError.html) if the `target` square is not in a
+    ///   straight line from the `troll` square
+    /// - [`Err(ThudError::Obstacle)`](enum.ThudError.html) if there is a piece in the way
+    /// - [`Err(ThudError::NoAdjacentDwarf)`](enum.ThudError.html) if there are no
+    ///   [`Piece::Dwarf`s](enum.Piece.html) adjacent to the `target` square
+    /// - [`Err(ThudError::LineTooShort)`](enum.ThudError.html) if the distance to the target
+    ///   square is larger than the length of the line of trolls going in the other direction
     pub fn troll_shove(&mut self, troll: Coord, target: Coord) -> MoveResult {
-        if (self.get(troll), self.get(target)) != (Piece::Troll, Piece::Empty) {
-            return Err(ThudError::IllegalMove);
-        }
+        self.expect(troll, Piece::Troll)?;
+        self.expect_empty(target)?;
         self.verify_clear(troll, target)?;
 
         let dwarves: Vec<(Coord, Piece)> = self
@@ -171,8 +188,8 @@ impl Board {
             .into_iter()
             .filter(|(_, x)| *x == Piece::Dwarf)
             .collect();
-        if dwarves.len() == 0 {
-            return Err(ThudError::IllegalMove);
+        if dwarves.is_empty() {
+            return Err(ThudError::NoAdjacentDwarf(target));
         }
 
         let troll_len = self.count_line(
@@ -203,15 +220,13 @@ impl Board {
     /// Note that any invalid (out of board limits) or duplicate
     /// [`Direction`s](enum.Direction.html) will be ignored.
     ///
-    /// Returns [`Err(ThudError::IllegalMove)`](enum.ThudError.html) if the piece at `troll` is not [`Piece::Troll`](enum.Piece.html).
+    /// Returns [`Err(ThudError::WrongPiece)`](enum.ThudError.html) if the piece at `troll` is not [`Piece::Troll`](enum.Piece.html).
     pub fn troll_capture(
         &mut self,
         troll: Coord,
         targets: Vec<Direction>,
     ) -> Result<usize, ThudError> {
-        if self.get(troll) != Piece::Troll {
-            return Err(ThudError::IllegalMove);
-        }
+        self.expect(troll, Piece::Troll)?;
 
         let mut captured = 0;
 
@@ -228,19 +243,45 @@ impl Board {
         Ok(captured)
     }
 
-    /// Move a dwarf.
+    /// Find which dwarves the troll at `troll` could capture.
     ///
-    /// Returns [`Err(ThudError::IllegalMove)`](enum.ThudError.html) if:
+    /// `after_shove` should be `true` if the troll has just been shoved, in which case at least
+    /// one dwarf must be captured.
+    ///
+    /// Returns [`Err(ThudError::WrongPiece)`](enum.ThudError.html) if the piece at `troll` is not [`Piece::Troll`](enum.Piece.html).
+    pub fn capture_options(
+        &self,
+        troll: Coord,
+        after_shove: bool,
+    ) -> Result<CaptureOptions, ThudError> {
+        self.expect(troll, Piece::Troll)?;
+
+        let directions = Direction::all()
+            .into_iter()
+            .filter(|dir| match dir.modify(troll) {
+                Ok(coord) => self.get(coord) == Piece::Dwarf,
+                Err(_) => false,
+            })
+            .collect();
+
+        Ok(CaptureOptions::new(directions, after_shove))
+    }
+
+    /// Move a dwarf.
     ///
-    /// - square `dwarf` is not [`Piece::Dwarf`](enum.Piece.html)
-    /// - square `target` is not [`Piece::Empty`](enum.Piece.html)
+    /// Returns:
     ///
-    /// Returns [`Err(ThudError::Obstacle)`](enum.ThudError.html) if there is a piece in the way.
+    /// - [`Err(ThudError::WrongPiece)`](enum.ThudError.html) if square `dwarf` is not
+    ///   [`Piece::Dwarf`](enum.Piece.html)
+    /// - [`Err(ThudError::Occupied)`](enum.ThudError.html) if square `target` is not
+    ///   [`Piece::Empty`](enum.Piece.html)
+    /// - [`Err(ThudError::NotStraight)`](enum.ThudError.html) if square `target` is not in a
+    ///   straight line from square `dwarf`
+    /// - [`Err(ThudError::Obstacle)`](enum.ThudError.html) if there is a piece in the way
     pub fn dwarf_move(&mut self, dwarf: Coord, target: Coord) -> MoveResult {
-        // Check the target is clear and the place we're moving from actually has a dwarf
-        if (self.get(dwarf), self.get(target)) != (Piece::Dwarf, Piece::Empty) {
-            return Err(ThudError::IllegalMove);
-        }
+        // Check the place we're moving from actually has a dwarf and the target is clear
+        self.expect(dwarf, Piece::Dwarf)?;
+        self.expect_empty(target)?;
         self.verify_clear(dwarf, target)?;
 
         // Move the dwarf
@@ -252,19 +293,19 @@ impl Board {
 
     /// "Hurl" a dwarf.
     ///
-    /// Returns [`Err(ThudError::IllegalMove)`](enum.ThudError.html) if:
-    ///
-    /// - square `dwarf` is not [`Piece::Dwarf`](enum.Piece.html)
-    /// - square `target` is not [`Piece::Troll`](enum.Piece.html)
-    ///
-    /// Returns [`Err(ThudError::Obstacle)`](enum.ThudError.html) if there is a piece in the way.
+    /// Returns:
     ///
-    /// Returns [`Err(ThudError::LineTooShort)`](enum.ThudError.html) if the distance to the target
-    /// square is larger than the length of the line of dwarves going in the other direction
+    /// - [`Err(ThudError::WrongPiece)`](enum.ThudError.html) if square `dwarf` is not
+    ///   [`Piece::Dwarf`](enum.Piece.html) or square `target` is not
+    ///   [`Piece::Troll`](enum.Piece.html)
+    /// - [`Err(ThudError::NotStraight)`](enum.ThudError.html) if square `target` is not in a
+    ///   straight line from square `dwarf`
+    /// - [`Err(ThudError::Obstacle)`](enum.ThudError.html) if there is a piece in the way
+    /// - [`Err(ThudError::LineTooShort)`](enum.ThudError.html) if the distance to the target
+    ///   square is larger than the length of the line of dwarves going in the other direction
     pub fn dwarf_hurl(&mut self, dwarf: Coord, target: Coord) -> MoveResult {
-        if self.get(dwarf) != Piece::Dwarf || self.get(target) != Piece::Troll {
-            return Err(ThudError::IllegalMove);
-        }
+        self.expect(dwarf, Piece::Dwarf)?;
+        self.expect(target, Piece::Troll)?;
         self.verify_clear(dwarf, target)?;
 
         // Make sure there are enough supporting dwarves
@@ -284,6 +325,46 @@ impl Board {
         Ok(())
     }
 
+    /// Explain why `action` can't be taken on this `Board`, or get `None` if it can
+    ///
+    /// As with the movement methods, only the pieces are checked; whose turn it is is left to
+    /// [`Thud`](struct.Thud.html), and ending the game is always allowed here.
+    ///
+    /// ```
+    /// use thud::{Action, Board};
+    ///
+    /// let board = Board::fresh();
+    /// let action: Action = "G1-H3".parse().unwrap();
+    /// assert_eq!(
+    ///     board.explain_illegal(&action).unwrap(),
+    ///     "There is no straight line from G1 to H3"
+    /// );
+    /// ```
+    pub fn explain_illegal(&self, action: &Action) -> Option<String> {
+        self.check(action).err().map(|error| error.to_string())
+    }
+
+    /// Try `action` out on a copy of the board
+    fn check(&self, action: &Action) -> MoveResult {
+        let mut board = *self;
+        match *action {
+            Action::Move(src, target) => match self.get(src) {
+                Piece::Dwarf => board.dwarf_move(src, target),
+                Piece::Troll => board.troll_move(src, target),
+                piece => Err(ThudError::Immovable(src, piece)),
+            },
+            Action::Attack(src, target) => match self.get(src) {
+                Piece::Dwarf => board.dwarf_hurl(src, target),
+                Piece::Troll => board.troll_shove(src, target),
+                piece => Err(ThudError::Immovable(src, piece)),
+            },
+            Action::Capture(troll, ref targets) => {
+                board.troll_capture(troll, targets.clone()).map(|_| ())
+            }
+            _ => Ok(()),
+        }
+    }
+
     /// Get a `Vec` of [`Coord`s](struct.Coord.html) that the piece at `loc` can make
     pub fn available_moves(&self, loc: Coord) -> Vec<Coord> {
         let mut avail: Vec<Coord> = Vec::new();
@@ -293,14 +374,14 @@ impl Board {
                     // Count the dwarves behind us
                     let line_behind = self.count_line(loc, dir.opposite(), Piece::Dwarf);
 
-                    for (count, (poss, piece)) in self.cast(loc, dir).into_iter().enumerate() {
+                    for (count, (poss, piece)) in self.cast(loc, dir).enumerate() {
                         match piece {
                             // If it's empty, we can move into it!
                             Piece::Empty => avail.push(poss),
                             // If there's a troll there, we can take it if we're not so far out
                             // that our line of dwarves can't support us (but cannot jump over it)
                             Piece::Troll => {
-                                if count <= line_behind {
+                                if count < line_behind {
                                     avail.push(poss);
                                 }
                                 break;
@@ -315,9 +396,7 @@ impl Board {
                 // any empty squares we find
                 for dir in Direction::all() {
                     let behind_line = self.count_line(loc, dir.opposite(), Piece::Troll);
-                    let mut cast = self.cast(loc, dir);
-                    cast.next();
-                    for (poss, piece) in cast.take(behind_line) {
+                    for (poss, piece) in self.cast(loc, dir).take(behind_line) {
                         match piece {
                             Piece::Empty => avail.push(poss),
                             _ => break,
@@ -335,8 +414,8 @@ impl Board {
     ///
     /// Returns:
     ///
-    /// - [`Some(EndState::Won(Player))`](enum.EndState.html) if a player has won the match
-    /// - [`Some(EndState::Draw)`](enum.EndState.html) if the match is a draw
+    /// - [`Some(EndState::Won(Player, EndReason::NoMoves))`](enum.EndState.html) if a player has won the match
+    /// - [`Some(EndState::Draw(EndReason::NoMoves))`](enum.EndState.html) if the match is a draw
     /// - `None` if the board still has moves to play
     pub fn winner(&self) -> Option<EndState> {
         // Check dwarves
@@ -352,14 +431,7 @@ impl Board {
         }
 
         if troll_moves == 0 || dwarf_moves == 0 {
-            let (dwarf_score, troll_score) = self.score();
-            if dwarf_score > troll_score {
-                Some(EndState::Won(Player::Dwarf))
-            } else if troll_score > dwarf_score {
-                Some(EndState::Won(Player::Troll))
-            } else {
-                Some(EndState::Draw)
-            }
+            Some(EndState::from_score(self.score(), EndReason::NoMoves))
         } else {
             None
         }
@@ -374,12 +446,72 @@ impl Board {
         (dwarves, trolls)
     }
 
-    fn cast(&self, loc: Coord, dir: Direction) -> raycast::RayCast {
+    /// Get the `Board` made by turning or flipping this one with `symmetry`
+    pub fn transform(&self, symmetry: Symmetry) -> Self {
+        let mut board = Board::default();
+        for x in 0..15 {
+            for y in 0..15 {
+                if let Ok(square) = Coord::zero_based(x, y) {
+                    board.place(square.transform(symmetry), self.get(square));
+                }
+            }
+        }
+        board
+    }
+
+    /// Get whichever of the 8 [symmetric](enum.Symmetry.html) versions of this `Board` has the
+    /// lowest [`.position_hash()`](#method.position_hash)
+    ///
+    /// Positions which are the same up to turning or flipping the board have the same canonical
+    /// `Board`. See [`.canonical_symmetry()`](#method.canonical_symmetry) for how to get to it.
+    ///
+    /// ```
+    /// use thud::{Board, Piece, Symmetry};
+    ///
+    /// let mut board = Board::fresh();
+    /// board.place((6, 0).into(), Piece::Empty);
+    /// let turned = board.transform(Symmetry::Rotate90);
+    /// assert_ne!(board, turned);
+    /// assert_eq!(board.canonical(), turned.canonical());
+    /// ```
+    pub fn canonical(&self) -> Self {
+        self.transform(self.canonical_symmetry())
+    }
+
+    /// Get the [`Symmetry`](enum.Symmetry.html) which takes this `Board` to its
+    /// [`.canonical()`](#method.canonical) version, the first in the order of
+    /// [`Symmetry::all()`](enum.Symmetry.html#method.all) if several do
+    pub fn canonical_symmetry(&self) -> Symmetry {
+        Symmetry::all()
+            .iter()
+            .copied()
+            .min_by_key(|&symmetry| self.transform(symmetry).position_hash())
+            .unwrap()
+    }
+
+    /// Get a hash of the position of the pieces on the board.
+    ///
+    /// Unlike [`std::hash::Hash`](https://doc.rust-lang.org/std/hash/trait.Hash.html), the value
+    /// returned is stable between runs and between versions of this library, so it is suitable
+    /// for storing.
+    pub fn position_hash(&self) -> u64 {
+        // 64-bit FNV-1a
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for column in self.squares.iter() {
+            for piece in column.iter() {
+                hash ^= piece.code() as u64;
+                hash = hash.wrapping_mul(0x0100_0000_01b3);
+            }
+        }
+        hash
+    }
+
+    fn cast(&self, loc: Coord, dir: Direction) -> raycast::RayCast<'_> {
         raycast::RayCast::new(self, loc, dir)
     }
 
     fn verify_clear(&self, src: Coord, dest: Coord) -> MoveResult {
-        let dir = dbg!(Direction::from_route(src, dest)?);
+        let dir = Direction::from_route(src, dest)?;
         // Skip the first element
         for (current, piece) in self.cast(src, dir) {
             if current == dest {
@@ -388,14 +520,31 @@ impl Board {
             }
             if piece != Piece::Empty {
                 // There is something in the way
-                let (x, y) = current.value();
-                return Err(ThudError::Obstacle(x, y));
+                return Err(ThudError::Obstacle(current, piece));
             }
         }
 
         Ok(())
     }
 
+    fn expect(&self, square: Coord, expected: Piece) -> MoveResult {
+        match self.get(square) {
+            found if found == expected => Ok(()),
+            found => Err(ThudError::WrongPiece {
+                square,
+                expected,
+                found,
+            }),
+        }
+    }
+
+    fn expect_empty(&self, square: Coord) -> MoveResult {
+        match self.get(square) {
+            Piece::Empty => Ok(()),
+            piece => Err(ThudError::Occupied(square, piece)),
+        }
+    }
+
     fn count_line(&self, start: Coord, dir: Direction, piece: Piece) -> usize {
         if self.get(start) != piece {
             return 0;
@@ -411,3 +560,32 @@ impl Board {
         length
     }
 }
+
+/// Draw the `Board` as text, with row numbers and column letters in standard Thud notation (see
+/// [`Coord`](struct.Coord.html)) and row 15 at the top.
+///
+/// Dwarves are `d`, trolls `T`, the thudstone `O` and empty squares `.`.
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let letters = "   A B C D E F G H J K L M N O P";
+        writeln!(f, "{}", letters)?;
+        for y in (0..15).rev() {
+            write!(f, "{:>2} ", y + 1)?;
+            for x in 0..15 {
+                let symbol = if !Coord::valid(x, y) {
+                    ' '
+                } else {
+                    match self.squares[x][y] {
+                        Piece::Dwarf => 'd',
+                        Piece::Troll => 'T',
+                        Piece::Thudstone => 'O',
+                        Piece::Empty => '.',
+                    }
+                };
+                write!(f, "{} ", symbol)?;
+            }
+            writeln!(f, "{}", y + 1)?;
+        }
+        write!(f, "{}", letters)
+    }
+}
@@ -2,10 +2,8 @@ use super::*;
 use test_case::test_case;
 
 // TODO write tests for:
-// - troll_capture
 // - score
 // - winner
-// - available_moves
 
 #[test_case((8,3), Piece::Troll)]
 fn place_piece(loc: (usize, usize), piece: Piece) {
@@ -104,11 +102,9 @@ fn cast_alone() {
 
     assert_eq!(cast.next(), Some(((7, 7).into(), Piece::Thudstone)));
 
-    let mut current = 2;
-    for (next, piece) in cast {
+    for (current, (next, piece)) in (2..).zip(cast) {
         assert_eq!(piece, Piece::Empty);
         assert_eq!(next, (7, 6 + current).into());
-        current += 1;
     }
 }
 
@@ -129,3 +125,145 @@ fn verify_clear(src: (usize, usize), dest: (usize, usize)) {
 fn count_line(loc: (usize, usize), dir: Direction, piece: Piece) -> usize {
     Board::fresh().count_line(loc.into(), dir, piece)
 }
+
+#[test_case(vec![], false => 1)]
+#[test_case(vec![], true => 0)]
+#[test_case(vec![(7, 8)], false => 2)]
+#[test_case(vec![(7, 8)], true => 1)]
+#[test_case(vec![(7, 8), (8, 8), (6, 6)], false => 8)]
+#[test_case(vec![(7, 8), (8, 8), (6, 6)], true => 7)]
+fn capture_subsets(dwarves: Vec<(usize, usize)>, after_shove: bool) -> usize {
+    let mut board = Board::default();
+    board.place((7, 7).into(), Piece::Troll);
+    for dwarf in dwarves {
+        board.place(dwarf.into(), Piece::Dwarf);
+    }
+    let options = board.capture_options((7, 7).into(), after_shove).unwrap();
+    assert!(options
+        .subsets()
+        .all(|set| !(after_shove && set.is_empty())));
+    options.subsets().count()
+}
+
+#[test]
+fn capture_options_directions() {
+    let mut board = Board::default();
+    board.place((7, 7).into(), Piece::Troll);
+    board.place((7, 8).into(), Piece::Dwarf);
+    board.place((6, 6).into(), Piece::Dwarf);
+    board.place((8, 7).into(), Piece::Troll);
+
+    let options = board.capture_options((7, 7).into(), false).unwrap();
+    assert_eq!(options.directions(), &[Direction::Up, Direction::DownLeft]);
+    assert!(board.capture_options((7, 8).into(), false).is_err());
+}
+
+#[test]
+fn troll_capture() {
+    let mut board = Board::default();
+    board.place((7, 7).into(), Piece::Troll);
+    board.place((7, 8).into(), Piece::Dwarf);
+    board.place((6, 6).into(), Piece::Dwarf);
+
+    let captured = board
+        .troll_capture((7, 7).into(), vec![Direction::Up, Direction::Right])
+        .unwrap();
+    assert_eq!(captured, 1);
+    assert_eq!(board.get((7, 8).into()), Piece::Empty);
+    assert_eq!(board.get((6, 6).into()), Piece::Dwarf);
+}
+
+#[test]
+fn position_hash() {
+    let mut board = Board::fresh();
+    assert_eq!(board.position_hash(), Board::fresh().position_hash());
+    assert_ne!(board.position_hash(), Board::default().position_hash());
+
+    board.troll_move((8, 7).into(), (9, 7).into()).unwrap();
+    assert_ne!(board.position_hash(), Board::fresh().position_hash());
+    board.troll_move((9, 7).into(), (8, 7).into()).unwrap();
+    assert_eq!(board.position_hash(), Board::fresh().position_hash());
+}
+
+#[test]
+fn validate() {
+    let mut board = Board::fresh();
+    assert_eq!(board.validate(), Ok(()));
+
+    board.place((3, 3).into(), Piece::Thudstone);
+    board.squares[0][0] = Piece::Dwarf;
+    assert_eq!(
+        board.validate(),
+        Err(vec![
+            BoardViolation::OutOfBounds(0, 0),
+            BoardViolation::ThudstoneCount(2)
+        ])
+    );
+}
+
+#[cfg(feature = "serialize")]
+#[test]
+fn deserialize_validated() {
+    let json = serde_json::to_string(&Board::fresh()).unwrap();
+    let board: Board = serde_json::from_str(&json).unwrap();
+    assert_eq!(board.full_raw(), Board::fresh().full_raw());
+
+    let json = serde_json::to_string(&Board::default()).unwrap();
+    assert!(serde_json::from_str::<Board>(&json).is_err());
+
+    #[derive(serde::Deserialize)]
+    struct Unchecked(#[serde(deserialize_with = "Board::deserialize_unchecked")] Board);
+    let board: Unchecked = serde_json::from_str(&json).unwrap();
+    assert_eq!(board.0.full_raw(), Board::default().full_raw());
+}
+
+// lone dwarf, troll next to it
+#[test_case((7, 3), vec![], (7, 4) => true)]
+// lone dwarf, troll 2 squares away
+#[test_case((7, 3), vec![], (7, 5) => false)]
+// line of 2 dwarves, troll 2 squares away
+#[test_case((7, 3), vec![(7, 2)], (7, 5) => true)]
+fn dwarf_available_hurl(
+    dwarf: (usize, usize),
+    line: Vec<(usize, usize)>,
+    troll: (usize, usize),
+) -> bool {
+    let mut board = Board::default();
+    board.place(dwarf.into(), Piece::Dwarf);
+    for place in line {
+        board.place(place.into(), Piece::Dwarf);
+    }
+    board.place(troll.into(), Piece::Troll);
+    board.available_moves(dwarf.into()).contains(&troll.into())
+}
+
+// lone troll can move one square in any direction
+#[test_case(vec![], (7, 7) => 8)]
+// against the edge of the board
+#[test_case(vec![], (0, 7) => 5)]
+// line of 2 trolls can go 2 squares away from the line, but is blocked by itself
+#[test_case(vec![(7, 6)], (7, 7) => 8)]
+// line of 3 trolls
+#[test_case(vec![(7, 6), (7, 5)], (7, 7) => 9)]
+fn troll_available_moves(line: Vec<(usize, usize)>, troll: (usize, usize)) -> usize {
+    let mut board = Board::default();
+    board.place(troll.into(), Piece::Troll);
+    for place in line {
+        board.place(place.into(), Piece::Troll);
+    }
+    board.available_moves(troll.into()).len()
+}
+
+#[test_case("F1-F6" => None)]
+#[test_case("resign dwarf" => None)]
+#[test_case("G1-H3" => Some("There is no straight line from G1 to H3".to_string()))]
+#[test_case("H8-H9" => Some("The Thudstone at H8 can't be moved".to_string()))]
+#[test_case("G1-G7" => Some("There is already a Troll at G7".to_string()))]
+#[test_case("G1-G10" => Some("The Troll at G7 is blocking that move".to_string()))]
+#[test_case("G9-G11" => Some("A troll can only move one square, not from G9 to G11".to_string()))]
+#[test_case("G1xG7" => Some("You need 6 pieces behind you to make that move but you only have 1".to_string()))]
+#[test_case("G7xG6" => Some("A troll can only be shoved next to a dwarf, and there is none by G6".to_string()))]
+#[test_case("G1*N" => Some("Expected a Troll at G1 but found Dwarf".to_string()))]
+fn explain_illegal(action: &str) -> Option<String> {
+    Board::fresh().explain_illegal(&action.parse().unwrap())
+}
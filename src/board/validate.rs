@@ -0,0 +1,105 @@
+use super::Board;
+use crate::{Coord, Piece};
+use thiserror::Error;
+
+#[cfg(feature = "serialize")]
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// A problem with a [`Board`](struct.Board.html), found by
+/// [`Board::validate()`](struct.Board.html#method.validate)
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialize", serde(rename_all = "snake_case"))]
+#[derive(Debug, PartialEq, Copy, Clone, Error)]
+pub enum BoardViolation {
+    #[error("There is a piece at ({0},{1}), which is out of bounds")]
+    OutOfBounds(usize, usize),
+    #[error("There should be exactly 1 thudstone but there are {0}")]
+    ThudstoneCount(usize),
+}
+
+impl Board {
+    /// Check the board is a position that could come up in a game of Thud.
+    ///
+    /// Returns `Err` with every [`BoardViolation`](enum.BoardViolation.html) found if:
+    ///
+    /// - There are pieces on squares outside the bounds of the board
+    /// - There is not exactly one [`Piece::Thudstone`](enum.Piece.html) on the board
+    ///
+    /// ```
+    /// use thud::{Board, BoardViolation, Piece};
+    ///
+    /// let mut board = Board::fresh();
+    /// assert_eq!(board.validate(), Ok(()));
+    ///
+    /// board.place((7, 7).into(), Piece::Troll);
+    /// assert_eq!(board.validate(), Err(vec![BoardViolation::ThudstoneCount(0)]));
+    /// ```
+    pub fn validate(&self) -> Result<(), Vec<BoardViolation>> {
+        let mut violations = Vec::new();
+        let mut stones = 0;
+
+        for (x, column) in self.squares.iter().enumerate() {
+            for (y, piece) in column.iter().enumerate() {
+                if *piece == Piece::Empty {
+                    continue;
+                }
+                if !Coord::valid(x, y) {
+                    violations.push(BoardViolation::OutOfBounds(x, y));
+                }
+                if *piece == Piece::Thudstone {
+                    stones += 1;
+                }
+            }
+        }
+        if stones != 1 {
+            violations.push(BoardViolation::ThudstoneCount(stones));
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    /// Deserialise a `Board` without checking it with [`.validate()`](#method.validate).
+    ///
+    /// By default, deserialising a `Board` will fail if it is not valid; use this with
+    /// `#[serde(deserialize_with = "Board::deserialize_unchecked")]` to opt out.
+    #[cfg(feature = "serialize")]
+    pub fn deserialize_unchecked<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        RawBoard::deserialize(deserializer).map(|raw| Board {
+            squares: raw.squares,
+        })
+    }
+}
+
+/// Mirror of `Board` used to deserialise it before validation
+#[cfg(feature = "serialize")]
+#[derive(Deserialize)]
+#[serde(rename = "board")]
+pub(super) struct RawBoard {
+    squares: [[Piece; 15]; 15],
+}
+
+#[cfg(feature = "serialize")]
+impl std::convert::TryFrom<RawBoard> for Board {
+    type Error = String;
+
+    fn try_from(raw: RawBoard) -> Result<Self, Self::Error> {
+        let board = Board {
+            squares: raw.squares,
+        };
+        match board.validate() {
+            Ok(()) => Ok(board),
+            Err(violations) => Err(violations
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<String>>()
+                .join("; ")),
+        }
+    }
+}
@@ -0,0 +1,443 @@
+use crate::{Action, EndState, Player, Thud, ThudError};
+#[cfg(feature = "serialize")]
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Something which tells the time for a [`Clock`](struct.Clock.html)
+pub trait TimeSource {
+    /// Get the time since some fixed point, which must never go backwards
+    fn now(&self) -> Duration;
+}
+
+/// Real time, measured from when it was made
+#[derive(Debug, Clone, Copy)]
+pub struct RealTime {
+    start: Instant,
+}
+
+impl Default for RealTime {
+    fn default() -> Self {
+        RealTime {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl TimeSource for RealTime {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// Time which only passes when told to, for tests and for replaying games
+///
+/// Clones share the same time, so one can be handed to a [`Clock`](struct.Clock.html) and the
+/// other kept to [`.advance()`](#method.advance) it.
+#[derive(Debug, Clone, Default)]
+pub struct ManualTime {
+    nanos: Arc<AtomicU64>,
+}
+
+impl ManualTime {
+    /// Make a `ManualTime` starting at zero
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Move the time forward by `by`
+    pub fn advance(&self, by: Duration) {
+        self.nanos.fetch_add(by.as_nanos() as u64, Ordering::SeqCst);
+    }
+}
+
+impl TimeSource for ManualTime {
+    fn now(&self) -> Duration {
+        Duration::from_nanos(self.nanos.load(Ordering::SeqCst))
+    }
+}
+
+/// How much time each player gets on a [`Clock`](struct.Clock.html)
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialize", serde(rename_all = "snake_case"))]
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum TimeControl {
+    /// The same amount of time for the whole game
+    SuddenDeath(Duration),
+    /// `base` to start with, and `increment` more after each turn
+    Fischer { base: Duration, increment: Duration },
+    /// `base` of main time, then `periods` periods of `period` each
+    ///
+    /// Once main time is used up, a turn finished within a period leaves it for the next turn,
+    /// while a period used up in full is lost. Time runs out when the last period is lost.
+    ByoYomi {
+        base: Duration,
+        period: Duration,
+        periods: u32,
+    },
+}
+
+/// The time one player has left between turns
+#[derive(Debug, PartialEq, Copy, Clone)]
+struct Allowance {
+    main: Duration,
+    periods: u32,
+}
+
+/// A chess-style clock counting down each player's time with a
+/// [`TimeSource`](trait.TimeSource.html)
+///
+/// Only one player's time runs at once, from [`.start()`](#method.start) to
+/// [`.stop()`](#method.stop). To have the clock follow a game, use a
+/// [`ClockedGame`](struct.ClockedGame.html).
+#[derive(Debug, Clone)]
+pub struct Clock<T> {
+    control: TimeControl,
+    dwarf: Allowance,
+    troll: Allowance,
+    // The player whose time is running and when it started
+    running: Option<(Player, Duration)>,
+    source: T,
+}
+
+impl<T: TimeSource> Clock<T> {
+    /// Make a stopped clock giving both players the time in `control`
+    pub fn new(control: TimeControl, source: T) -> Self {
+        let allowance = match control {
+            TimeControl::SuddenDeath(base) | TimeControl::Fischer { base, .. } => Allowance {
+                main: base,
+                periods: 0,
+            },
+            TimeControl::ByoYomi { base, periods, .. } => Allowance {
+                main: base,
+                periods,
+            },
+        };
+        Clock {
+            control,
+            dwarf: allowance,
+            troll: allowance,
+            running: None,
+            source,
+        }
+    }
+
+    /// Get the time control in use
+    pub fn control(&self) -> TimeControl {
+        self.control
+    }
+
+    /// Find whose time is running, if anyone's
+    pub fn running(&self) -> Option<Player> {
+        self.running.map(|(player, _)| player)
+    }
+
+    fn allowance(&mut self, player: Player) -> &mut Allowance {
+        match player {
+            Player::Dwarf => &mut self.dwarf,
+            Player::Troll => &mut self.troll,
+        }
+    }
+
+    /// Work out what is left of `allowance` after `elapsed` of a turn, along with the time left
+    /// for the turn, or `None` if time has run out
+    fn spend(&self, allowance: Allowance, elapsed: Duration) -> Option<(Allowance, Duration)> {
+        if elapsed < allowance.main {
+            let main = allowance.main - elapsed;
+            return Some((Allowance { main, ..allowance }, main));
+        }
+        match self.control {
+            TimeControl::ByoYomi { period, .. } if period > Duration::ZERO => {
+                let over = elapsed - allowance.main;
+                let lost = over.as_nanos() / period.as_nanos();
+                if lost >= u128::from(allowance.periods) {
+                    return None;
+                }
+                let left =
+                    period - Duration::from_nanos((over.as_nanos() % period.as_nanos()) as u64);
+                let periods = allowance.periods - lost as u32;
+                Some((
+                    Allowance {
+                        main: Duration::ZERO,
+                        periods,
+                    },
+                    left,
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    /// How far into their turn `player` is
+    fn elapsed(&self, player: Player) -> Duration {
+        match self.running {
+            Some((running, started)) if running == player => {
+                self.source.now().saturating_sub(started)
+            }
+            _ => Duration::ZERO,
+        }
+    }
+
+    fn current(&self, player: Player) -> Option<(Allowance, Duration)> {
+        let allowance = match player {
+            Player::Dwarf => self.dwarf,
+            Player::Troll => self.troll,
+        };
+        self.spend(allowance, self.elapsed(player))
+    }
+
+    /// Get the time `player` has left for their turn: their main time or, once that has gone,
+    /// what is left of the current byo-yomi period
+    pub fn time_left(&self, player: Player) -> Duration {
+        self.current(player)
+            .map(|(_, left)| left)
+            .unwrap_or_default()
+    }
+
+    /// Get the number of byo-yomi periods `player` has left
+    pub fn periods_left(&self, player: Player) -> u32 {
+        self.current(player)
+            .map(|(allowance, _)| allowance.periods)
+            .unwrap_or(0)
+    }
+
+    /// Find whether the player whose time is running has run out
+    pub fn flagged(&self) -> Option<Player> {
+        let player = self.running()?;
+        match self.current(player) {
+            Some(_) => None,
+            None => Some(player),
+        }
+    }
+
+    /// Start `player`'s time, stopping the other player's without ending their turn
+    pub fn start(&mut self, player: Player) {
+        self.stop(false);
+        self.running = Some((player, self.source.now()));
+    }
+
+    /// Stop whoever's time is running, taking the time they used off what they have left.
+    ///
+    /// If `turn_over`, their turn has ended, and they get any
+    /// [increment](enum.TimeControl.html#variant.Fischer) due.
+    pub fn stop(&mut self, turn_over: bool) {
+        let (player, _) = match self.running {
+            Some(running) => running,
+            None => return,
+        };
+        let elapsed = self.elapsed(player);
+        let allowance = *self.allowance(player);
+        let mut left = match self.spend(allowance, elapsed) {
+            Some((left, _)) => left,
+            None => Allowance {
+                main: Duration::ZERO,
+                periods: 0,
+            },
+        };
+        if let (true, TimeControl::Fischer { increment, .. }) = (turn_over, self.control) {
+            left.main += increment;
+        }
+        *self.allowance(player) = left;
+        self.running = None;
+    }
+}
+
+/// Find who has to act next, which is the opponent if an end has been offered
+pub(crate) fn to_act(game: &Thud) -> Option<Player> {
+    match game.end_offer() {
+        Some(player) => Some(player.opponent()),
+        None => game.turn(),
+    }
+}
+
+/// A [`Thud`](struct.Thud.html) game played against a [`Clock`](struct.Clock.html)
+///
+/// The clock runs for whoever has to act: the player whose turn it is, or their opponent while
+/// they consider an end offer. A troll move or shove and its capture are a single turn, so the
+/// clock keeps running between them and any increment is given once the capture is made.
+///
+/// Running out of time loses the game with
+/// [`EndReason::OutOfTime`](enum.EndReason.html), which is noticed by
+/// [`.check_time()`](#method.check_time) and before every action.
+///
+/// ```
+/// use std::time::Duration;
+/// use thud::{Clock, ClockedGame, EndReason, ManualTime, Player, TimeControl, Thud};
+///
+/// let time = ManualTime::new();
+/// let clock = Clock::new(TimeControl::SuddenDeath(Duration::from_secs(60)), time.clone());
+/// let mut game = ClockedGame::new(Thud::new(), clock);
+///
+/// time.advance(Duration::from_secs(20));
+/// game.apply("G1-G6".parse().unwrap()).unwrap();
+/// assert_eq!(game.clock().time_left(Player::Dwarf), Duration::from_secs(40));
+///
+/// time.advance(Duration::from_secs(60));
+/// let end = game.check_time().unwrap();
+/// assert_eq!(end.reason(), EndReason::OutOfTime);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ClockedGame<T> {
+    game: Thud,
+    clock: Clock<T>,
+}
+
+impl<T: TimeSource> ClockedGame<T> {
+    /// Play `game` against `clock`, starting the time of whoever has to act
+    pub fn new(game: Thud, mut clock: Clock<T>) -> Self {
+        match to_act(&game) {
+            Some(player) => clock.start(player),
+            None => clock.stop(false),
+        }
+        ClockedGame { game, clock }
+    }
+
+    /// Get the game being played
+    pub fn game(&self) -> &Thud {
+        &self.game
+    }
+
+    /// Get the clock
+    pub fn clock(&self) -> &Clock<T> {
+        &self.clock
+    }
+
+    /// End the game if whoever has to act has run out of time, then give the result if the game
+    /// is over
+    pub fn check_time(&mut self) -> Option<EndState> {
+        if let Some(player) = self.clock.flagged() {
+            self.clock.stop(false);
+            self.game.out_of_time(player);
+        }
+        self.game.winner()
+    }
+
+    /// Take an [`Action`](enum.Action.html) with [`Thud::apply()`](struct.Thud.html#method.apply),
+    /// then hand the clock to whoever has to act next.
+    ///
+    /// Returns [`Err(ThudError::BadAction)`](enum.ThudError.html) if the game has ended,
+    /// including by running out of time, and passes on errors from the action.
+    pub fn apply(&mut self, action: Action) -> Result<(), ThudError> {
+        if self.check_time().is_some() {
+            return Err(ThudError::BadAction);
+        }
+        let (turn, acting) = (self.game.turn(), to_act(&self.game));
+        self.game.apply(action)?;
+
+        let next = match self.game.winner() {
+            Some(_) => None,
+            None => to_act(&self.game),
+        };
+        if next != acting {
+            self.clock.stop(self.game.turn() != turn);
+            if let Some(player) = next {
+                self.clock.start(player);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EndReason;
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    fn clocked(control: TimeControl) -> (ClockedGame<ManualTime>, ManualTime) {
+        let time = ManualTime::new();
+        let clock = Clock::new(control, time.clone());
+        (ClockedGame::new(Thud::new(), clock), time)
+    }
+
+    fn act(game: &mut ClockedGame<ManualTime>, action: &str) {
+        game.apply(action.parse().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn sudden_death() {
+        let (mut game, time) = clocked(TimeControl::SuddenDeath(secs(10)));
+        assert_eq!(game.clock().running(), Some(Player::Dwarf));
+        time.advance(secs(4));
+        act(&mut game, "G1-G6");
+        assert_eq!(game.clock().running(), Some(Player::Troll));
+        assert_eq!(game.clock().time_left(Player::Dwarf), secs(6));
+
+        time.advance(secs(10));
+        assert_eq!(game.clock().time_left(Player::Troll), secs(0));
+        assert_eq!(
+            game.apply("H9-H10".parse().unwrap()),
+            Err(ThudError::BadAction)
+        );
+        assert_eq!(
+            game.check_time(),
+            Some(EndState::Won(Player::Dwarf, EndReason::OutOfTime))
+        );
+        assert_eq!(game.clock().running(), None);
+        assert_eq!(game.game().history().len(), 1);
+    }
+
+    #[test]
+    fn troll_turn_is_one_turn() {
+        let control = TimeControl::Fischer {
+            base: secs(10),
+            increment: secs(2),
+        };
+        let (mut game, time) = clocked(control);
+        time.advance(secs(1));
+        act(&mut game, "G1-G6");
+        assert_eq!(game.clock().time_left(Player::Dwarf), secs(11));
+
+        time.advance(secs(3));
+        act(&mut game, "H9-H10");
+        assert_eq!(game.clock().running(), Some(Player::Troll));
+        time.advance(secs(3));
+        act(&mut game, "H10*");
+        assert_eq!(game.clock().running(), Some(Player::Dwarf));
+        assert_eq!(game.clock().time_left(Player::Troll), secs(6));
+    }
+
+    #[test]
+    fn end_offer_runs_the_opponents_time() {
+        let (mut game, time) = clocked(TimeControl::SuddenDeath(secs(10)));
+        act(&mut game, "offer");
+        assert_eq!(game.clock().running(), Some(Player::Troll));
+        time.advance(secs(3));
+        act(&mut game, "decline");
+        assert_eq!(game.clock().running(), Some(Player::Dwarf));
+        assert_eq!(game.clock().time_left(Player::Troll), secs(7));
+        act(&mut game, "resign dwarf");
+        assert_eq!(game.clock().running(), None);
+    }
+
+    #[test]
+    fn byo_yomi() {
+        let control = TimeControl::ByoYomi {
+            base: secs(5),
+            period: secs(3),
+            periods: 2,
+        };
+        let (mut game, time) = clocked(control);
+        time.advance(secs(7));
+        assert_eq!(game.clock().time_left(Player::Dwarf), secs(1));
+        assert_eq!(game.clock().periods_left(Player::Dwarf), 2);
+        act(&mut game, "G1-G6");
+        act(&mut game, "H9-H10");
+        act(&mut game, "H10*");
+
+        // The period used last turn is whole again
+        assert_eq!(game.clock().time_left(Player::Dwarf), secs(3));
+        time.advance(secs(4));
+        assert_eq!(game.clock().periods_left(Player::Dwarf), 1);
+        assert_eq!(game.check_time(), None);
+        time.advance(secs(2));
+        assert_eq!(
+            game.check_time(),
+            Some(EndState::Won(Player::Troll, EndReason::OutOfTime))
+        );
+    }
+}
@@ -1,15 +1,41 @@
-use crate::ThudError;
+use crate::{Symmetry, ThudError};
 #[cfg(feature = "serialize")]
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Letters for the columns of the board in standard Thud notation; there is no "I"
+const COLUMNS: &[u8; 15] = b"ABCDEFGHJKLMNOP";
 
 /// Checked container for a coordinate to address into a [`Board`](enum.Board.html)
+///
+/// When deserialised with the `serialize` feature, the coordinates are bounds-checked as with
+/// [`Coord::zero_based()`](#method.zero_based).
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialize", serde(try_from = "RawCoord"))]
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub struct Coord {
     x: usize,
     y: usize,
 }
 
+/// Mirror of `Coord` used to deserialise it before bounds checking
+#[cfg(feature = "serialize")]
+#[derive(Deserialize)]
+struct RawCoord {
+    x: usize,
+    y: usize,
+}
+
+#[cfg(feature = "serialize")]
+impl std::convert::TryFrom<RawCoord> for Coord {
+    type Error = ThudError;
+
+    fn try_from(raw: RawCoord) -> Result<Self, Self::Error> {
+        Coord::zero_based(raw.x, raw.y)
+    }
+}
+
 impl Coord {
     fn check_coords(x: usize, y: usize) -> Result<(), ThudError> {
         let sum = x + y;
@@ -18,8 +44,7 @@ impl Coord {
         let invalid = (x > 14)
             || (y > 14)
             // Extreme corners
-            || (sum < 5)
-            || (sum > 23)
+            || !(5..=23).contains(&sum)
             // Outer corners
             || (9 + y < x)
             || (9 + x < y);
@@ -62,6 +87,23 @@ impl Coord {
         (self.x, self.y)
     }
 
+    /// Get the square this one is taken to by turning or flipping the board with `symmetry`
+    ///
+    /// ```
+    /// use thud::{Coord, Symmetry};
+    ///
+    /// let square: Coord = "G1".parse().unwrap();
+    /// assert_eq!(square.transform(Symmetry::Rotate90).to_string(), "P7");
+    /// ```
+    pub fn transform(self, symmetry: Symmetry) -> Self {
+        // Offsets from the centre square
+        let (x, y) = symmetry.apply((self.x as isize - 7, self.y as isize - 7));
+        Coord {
+            x: (x + 7) as usize,
+            y: (y + 7) as usize,
+        }
+    }
+
     /// Return the larger of the two coordinates.
     ///
     /// Useful for use with [`.diff()`](#method.diff) to get the orthogonal/diagonal distance between two squares:
@@ -101,18 +143,58 @@ impl Coord {
     /// assert_eq!(source.diff(destination2), (5, 0).into());
     /// ```
     pub fn diff(self, rhs: Self) -> Self {
-        let new_x = if self.x > rhs.x {
-            self.x - rhs.x
-        } else {
-            rhs.x - self.x
-        };
-        let new_y = if self.y > rhs.y {
-            self.y - rhs.y
-        } else {
-            rhs.y - self.y
-        };
+        Coord {
+            x: self.x.abs_diff(rhs.x),
+            y: self.y.abs_diff(rhs.y),
+        }
+    }
+}
 
-        Coord { x: new_x, y: new_y }
+/// Write the `Coord` in standard Thud notation, ie. a column letter from `A` to `P` (skipping
+/// `I`) for `x` followed by a row number from `1` to `15` for `y`.
+///
+/// ```
+/// use thud::Coord;
+///
+/// assert_eq!(Coord::zero_based(7, 7).unwrap().to_string(), "H8");
+/// ```
+impl fmt::Display for Coord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", COLUMNS[self.x] as char, self.y + 1)
+    }
+}
+
+/// Read a `Coord` in standard Thud notation, as written by its `Display` implementation.
+///
+/// The column letter may be upper or lower case.
+///
+/// Returns [`Err(ThudError::InvalidNotation)`](enum.ThudError.html) if the text can't be read,
+/// or [`Err(ThudError::InvalidPosition)`](enum.ThudError.html) if the square is out of bounds.
+///
+/// ```
+/// use thud::Coord;
+///
+/// let coord: Coord = "a6".parse().unwrap();
+/// assert_eq!(coord.value(), (0, 5));
+/// ```
+impl FromStr for Coord {
+    type Err = ThudError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.trim().chars();
+        let x = chars
+            .next()
+            .and_then(|column| {
+                COLUMNS
+                    .iter()
+                    .position(|letter| *letter as char == column.to_ascii_uppercase())
+            })
+            .ok_or(ThudError::InvalidNotation)?;
+        let y = match chars.as_str().parse::<usize>() {
+            Ok(row) if row >= 1 => row - 1,
+            _ => return Err(ThudError::InvalidNotation),
+        };
+        Coord::zero_based(x, y)
     }
 }
 
@@ -167,4 +249,24 @@ mod tests {
             .diff(Coord::zero_based(rhs.0, rhs.1).unwrap())
             .max()
     }
+
+    #[test_case((0, 5) => "A6")]
+    #[test_case((7, 7) => "H8")]
+    #[test_case((8, 7) => "J8")]
+    #[test_case((14, 9) => "P10")]
+    fn display(loc: (usize, usize)) -> String {
+        Coord::from(loc).to_string()
+    }
+
+    #[test_case("A6" => Ok((0, 5)))]
+    #[test_case("j8" => Ok((8, 7)))]
+    #[test_case(" P10 " => Ok((14, 9)))]
+    #[test_case("A1" => Err(ThudError::InvalidPosition(0, 0)))]
+    #[test_case("I8" => Err(ThudError::InvalidNotation))]
+    #[test_case("H0" => Err(ThudError::InvalidNotation))]
+    #[test_case("H" => Err(ThudError::InvalidNotation))]
+    #[test_case("" => Err(ThudError::InvalidNotation))]
+    fn parse(text: &str) -> Result<(usize, usize), ThudError> {
+        text.parse::<Coord>().map(|coord| coord.value())
+    }
 }
@@ -0,0 +1,666 @@
+use crate::protocol::ARENA_TERMINATIONS;
+use crate::{Action, Board, Coord, GameRecord, Outcome, Piece, Player, ThudError, ValidationError};
+use std::collections::{HashMap, HashSet};
+use std::convert::TryInto;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Name of the file holding the records, each written as its length in bytes on a line of its
+/// own and then the record
+const GAMES_FILE: &str = "games";
+/// Name of the file holding the index, a list of little-endian `(position hash: u64, game: u32,
+/// ply: u32)` entries
+const POSITIONS_FILE: &str = "positions";
+const ENTRY_SIZE: usize = 16;
+
+/// Reports a problem with a [`PositionDatabase`](struct.PositionDatabase.html)
+#[derive(Debug, Error)]
+pub enum DatabaseError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("Record {index} is invalid: {error}")]
+    Invalid {
+        index: usize,
+        error: ValidationError,
+    },
+    #[error("The database files are damaged")]
+    Corrupt,
+}
+
+/// Wins, draws and losses over a set of games
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub struct Stats {
+    pub dwarf_wins: usize,
+    pub troll_wins: usize,
+    pub draws: usize,
+    /// Games with no result recorded
+    pub unfinished: usize,
+}
+
+impl Stats {
+    /// Get the number of games counted
+    pub fn total(&self) -> usize {
+        self.dwarf_wins + self.troll_wins + self.draws + self.unfinished
+    }
+
+    fn count(&mut self, outcome: Option<Outcome>) {
+        match outcome {
+            Some(Outcome::Won(Player::Dwarf)) => self.dwarf_wins += 1,
+            Some(Outcome::Won(Player::Troll)) => self.troll_wins += 1,
+            Some(Outcome::Draw) => self.draws += 1,
+            None => self.unfinished += 1,
+        }
+    }
+}
+
+/// What was played next from the positions found by a search, and how those games went
+#[derive(Debug, PartialEq, Clone)]
+pub struct Continuation {
+    /// The action taken, or `None` if the record stops there
+    pub action: Option<Action>,
+    /// Counting each game once
+    pub stats: Stats,
+}
+
+/// The games found by a search of a [`PositionDatabase`](struct.PositionDatabase.html)
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct SearchResult {
+    /// Each game found with the first ply it matched at, by the number of actions taken to get
+    /// there, in the order the games were added
+    pub games: Vec<(usize, usize)>,
+    /// Every continuation, in the order they were first seen
+    pub continuations: Vec<Continuation>,
+    stats: Stats,
+}
+
+impl SearchResult {
+    /// Get the results of all the games found, counting each game once however many
+    /// continuations it has
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+}
+
+/// A count of pieces in a region for a [`Pattern`](struct.Pattern.html) to match
+#[derive(Debug, PartialEq, Clone)]
+pub struct Region {
+    pub piece: Piece,
+    pub squares: Vec<Coord>,
+    pub at_least: usize,
+}
+
+/// A description of positions to search for in a
+/// [`PositionDatabase`](struct.PositionDatabase.html)
+///
+/// ```
+/// use thud::{Coord, Pattern, Piece, Region};
+///
+/// // Trolls on G7 and H6 with at least 3 dwarves in the bottom left corner
+/// let pattern = Pattern {
+///     pieces: vec![((6, 6).into(), Piece::Troll), ((7, 5).into(), Piece::Troll)],
+///     regions: vec![Region {
+///         piece: Piece::Dwarf,
+///         squares: (0..7)
+///             .flat_map(|x| (0..7).filter_map(move |y| Coord::zero_based(x, y).ok()))
+///             .collect(),
+///         at_least: 3,
+///     }],
+/// };
+/// ```
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Pattern {
+    /// Pieces which have to be on these squares
+    pub pieces: Vec<(Coord, Piece)>,
+    /// Regions which have to hold enough of a piece
+    pub regions: Vec<Region>,
+}
+
+impl Pattern {
+    /// Check whether `board` matches the pattern
+    pub fn matches(&self, board: &Board) -> bool {
+        self.pieces
+            .iter()
+            .all(|&(square, piece)| board.get(square) == piece)
+            && self.regions.iter().all(|region| {
+                region
+                    .squares
+                    .iter()
+                    .filter(|&&square| board.get(square) == region.piece)
+                    .count()
+                    >= region.at_least
+            })
+    }
+}
+
+/// An index of many [`GameRecord`](struct.GameRecord.html)s by the positions they reach, kept
+/// in a directory on disk
+///
+/// Positions are compared by [`Board::position_hash()`](struct.Board.html#method.position_hash),
+/// so which player is to act isn't taken into account.
+///
+/// ```no_run
+/// use thud::{Board, GameRecord, PositionDatabase};
+///
+/// let mut database = PositionDatabase::open("games.db")?;
+/// let text = std::fs::read_to_string("games.txt")?;
+/// database.add_all(&GameRecord::read_all(&text)?)?;
+///
+/// let result = database.search(&Board::fresh());
+/// for continuation in result.continuations {
+///     println!("{:?}: {:?}", continuation.action, continuation.stats);
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct PositionDatabase {
+    dir: PathBuf,
+    records: Vec<GameRecord>,
+    positions: HashMap<u64, Vec<(u32, u32)>>,
+}
+
+impl PositionDatabase {
+    /// Open the database in the directory `dir`, creating it if it doesn't exist.
+    ///
+    /// If adding games was cut off partway through writing the files, the games which weren't
+    /// fully written are dropped from them.
+    ///
+    /// Returns [`Err(DatabaseError::Corrupt)`](enum.DatabaseError.html) if the files there can't
+    /// be read back otherwise, hold a record which doesn't pass
+    /// [`GameRecord::validate_trusting()`](struct.GameRecord.html#method.validate_trusting) with
+    /// the [`ARENA_TERMINATIONS`](protocol/constant.ARENA_TERMINATIONS.html), or a game before the
+    /// last isn't fully indexed.
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self, DatabaseError> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        let (mut records, mut ends) = read_games(&read_or_empty(&dir.join(GAMES_FILE))?)?;
+        let index = read_or_empty(&dir.join(POSITIONS_FILE))?;
+
+        // Only whole entries for the games read can have been written in full
+        let mut entries = Vec::new();
+        let mut counts = vec![0; records.len()];
+        for entry in index.chunks_exact(ENTRY_SIZE) {
+            let hash = u64::from_le_bytes(entry[..8].try_into().unwrap());
+            let game = u32::from_le_bytes(entry[8..12].try_into().unwrap());
+            let ply = u32::from_le_bytes(entry[12..].try_into().unwrap());
+            *counts
+                .get_mut(game as usize)
+                .ok_or(DatabaseError::Corrupt)? += 1;
+            entries.push((hash, game, ply));
+        }
+        // Games are indexed in order, by the position before each action and at the end
+        let indexed = counts
+            .iter()
+            .zip(records.iter())
+            .take_while(|&(&count, record)| count == record.actions.len() + 1)
+            .count();
+        if counts.iter().skip(indexed + 1).any(|&count| count > 0) {
+            return Err(DatabaseError::Corrupt);
+        }
+        let kept: usize = counts[..indexed].iter().sum();
+        if entries[..kept]
+            .iter()
+            .any(|&(_, game, _)| game as usize >= indexed)
+        {
+            return Err(DatabaseError::Corrupt);
+        }
+
+        // Drop anything after the last game written in full
+        records.truncate(indexed);
+        ends.truncate(indexed);
+        entries.truncate(kept);
+        truncate(&dir.join(GAMES_FILE), ends.last().copied().unwrap_or(0))?;
+        truncate(&dir.join(POSITIONS_FILE), (kept * ENTRY_SIZE) as u64)?;
+
+        let mut positions: HashMap<u64, Vec<(u32, u32)>> = HashMap::new();
+        for (hash, game, ply) in entries {
+            positions.entry(hash).or_default().push((game, ply));
+        }
+        Ok(Self {
+            dir,
+            records,
+            positions,
+        })
+    }
+
+    /// Get the number of games stored
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Check whether there are no games stored
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Get a game by the number it was given when it was added
+    pub fn record(&self, game: usize) -> Option<&GameRecord> {
+        self.records.get(game)
+    }
+
+    /// Add a game, giving the number it is stored under.
+    ///
+    /// Returns [`Err(DatabaseError::Invalid)`](enum.DatabaseError.html) if the record doesn't
+    /// pass [`GameRecord::validate_trusting()`](struct.GameRecord.html#method.validate_trusting)
+    /// with the [`ARENA_TERMINATIONS`](protocol/constant.ARENA_TERMINATIONS.html), so games an
+    /// engine forfeited can be stored, or wouldn't be read back the same once written.
+    pub fn add(&mut self, record: &GameRecord) -> Result<usize, DatabaseError> {
+        self.add_all(std::slice::from_ref(record))?;
+        Ok(self.records.len() - 1)
+    }
+
+    /// Add many games at once, which is quicker than adding them one at a time.
+    ///
+    /// Every record is checked before any are stored, so if one is
+    /// [`Err(DatabaseError::Invalid)`](enum.DatabaseError.html) then none are added. If writing
+    /// either file fails, both are put back as they were and none are added.
+    pub fn add_all(&mut self, records: &[GameRecord]) -> Result<(), DatabaseError> {
+        for (index, record) in records.iter().enumerate() {
+            record
+                .validate_trusting(&ARENA_TERMINATIONS)
+                .map_err(|error| DatabaseError::Invalid { index, error })?;
+            // Tags such as a name with a space in can be set but not read back
+            if record.to_string().parse().as_ref() != Ok(record) {
+                return Err(DatabaseError::Invalid {
+                    index,
+                    error: ValidationError::Unreadable(ThudError::InvalidNotation),
+                });
+            }
+        }
+
+        let mut games = Vec::new();
+        let mut index = Vec::new();
+        let mut entries = Vec::new();
+        for (offset, record) in records.iter().enumerate() {
+            let game = (self.records.len() + offset) as u32;
+            let text = record.to_string();
+            writeln!(games, "{}", text.len())?;
+            games.extend_from_slice(text.as_bytes());
+
+            for (ply, board) in positions(record).iter().enumerate() {
+                let hash = board.position_hash();
+                index.extend_from_slice(&hash.to_le_bytes());
+                index.extend_from_slice(&game.to_le_bytes());
+                index.extend_from_slice(&(ply as u32).to_le_bytes());
+                entries.push((hash, game, ply as u32));
+            }
+        }
+        let (games_path, index_path) = (self.dir.join(GAMES_FILE), self.dir.join(POSITIONS_FILE));
+        let lengths = (file_len(&games_path)?, file_len(&index_path)?);
+        let written = append(&games_path, &games).and_then(|_| append(&index_path, &index));
+        if let Err(error) = written {
+            // Should putting them back fail too, opening the database drops the extra games
+            let _ = truncate(&games_path, lengths.0);
+            let _ = truncate(&index_path, lengths.1);
+            return Err(error.into());
+        }
+
+        for (hash, game, ply) in entries {
+            self.positions.entry(hash).or_default().push((game, ply));
+        }
+        self.records.extend_from_slice(records);
+        Ok(())
+    }
+
+    /// Find every game which reaches the position on `board`.
+    pub fn search(&self, board: &Board) -> SearchResult {
+        let mut hits: Vec<(usize, usize)> = self
+            .positions
+            .get(&board.position_hash())
+            .map(|hits| {
+                hits.iter()
+                    .map(|&(game, ply)| (game as usize, ply as usize))
+                    .collect()
+            })
+            .unwrap_or_default();
+        hits.sort_unstable();
+        self.collect(hits)
+    }
+
+    /// Find every game which reaches a position matching `pattern`.
+    ///
+    /// Unlike [`.search()`](#method.search) this replays every game stored.
+    pub fn search_pattern(&self, pattern: &Pattern) -> SearchResult {
+        let mut hits = Vec::new();
+        for (game, record) in self.records.iter().enumerate() {
+            for (ply, board) in positions(record).iter().enumerate() {
+                if pattern.matches(board) {
+                    hits.push((game, ply));
+                }
+            }
+        }
+        self.collect(hits)
+    }
+
+    /// Gather the `(game, ply)` matches of a search, in order
+    fn collect(&self, hits: Vec<(usize, usize)>) -> SearchResult {
+        let mut result = SearchResult::default();
+        // A game which comes back to a match counts once for each continuation
+        let mut counted = HashSet::new();
+        for (game, ply) in hits {
+            let record = &self.records[game];
+            if result.games.last().map(|&(last, _)| last) != Some(game) {
+                result.games.push((game, ply));
+                result.stats.count(record.outcome());
+            }
+            let action = record.actions.get(ply);
+            let index = match result
+                .continuations
+                .iter()
+                .position(|continuation| continuation.action.as_ref() == action)
+            {
+                Some(index) => index,
+                None => {
+                    result.continuations.push(Continuation {
+                        action: action.cloned(),
+                        stats: Stats::default(),
+                    });
+                    result.continuations.len() - 1
+                }
+            };
+            if counted.insert((game, index)) {
+                result.continuations[index].stats.count(record.outcome());
+            }
+        }
+        result
+    }
+}
+
+/// Get the board before each action in a valid `record`, and at the end
+fn positions(record: &GameRecord) -> Vec<Board> {
+    let mut game = record.start().expect("the record is valid");
+    let mut boards = vec![game.board()];
+    for action in record.actions.iter() {
+        game.apply(action.clone()).expect("the record is valid");
+        boards.push(game.board());
+    }
+    boards
+}
+
+fn read_or_empty(path: &Path) -> io::Result<Vec<u8>> {
+    match fs::read(path) {
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        other => other,
+    }
+}
+
+fn append(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(bytes)
+}
+
+/// Get the length of the file at `path`, which is 0 if there isn't one
+fn file_len(path: &Path) -> io::Result<u64> {
+    match fs::metadata(path) {
+        Ok(metadata) => Ok(metadata.len()),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(0),
+        Err(error) => Err(error),
+    }
+}
+
+/// Cut the file at `path` down to `length` bytes, if it is longer
+fn truncate(path: &Path, length: u64) -> io::Result<()> {
+    if file_len(path)? > length {
+        OpenOptions::new().write(true).open(path)?.set_len(length)?;
+    }
+    Ok(())
+}
+
+/// Read the records in the games file, with the offset each one ends at.
+///
+/// A record cut off at the end of the file is left out.
+fn read_games(bytes: &[u8]) -> Result<(Vec<GameRecord>, Vec<u64>), DatabaseError> {
+    let mut records = Vec::new();
+    let mut ends = Vec::new();
+    let mut start = 0;
+    while start < bytes.len() {
+        let rest = &bytes[start..];
+        let newline = match rest.iter().position(|&byte| byte == b'\n') {
+            Some(newline) => newline,
+            None => break,
+        };
+        let length: usize = std::str::from_utf8(&rest[..newline])
+            .ok()
+            .and_then(|length| length.parse().ok())
+            .ok_or(DatabaseError::Corrupt)?;
+        let record = match rest[newline + 1..].get(..length) {
+            Some(record) => record,
+            None => break,
+        };
+        let record: GameRecord = std::str::from_utf8(record)
+            .ok()
+            .and_then(|record| record.parse().ok())
+            .ok_or(DatabaseError::Corrupt)?;
+        // Searches replay the records, which has to succeed
+        record
+            .validate_trusting(&ARENA_TERMINATIONS)
+            .map_err(|_| DatabaseError::Corrupt)?;
+        records.push(record);
+        start += newline + 1 + length;
+        ends.push(start as u64);
+    }
+    Ok((records, ends))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process};
+
+    /// A fresh directory for a test's database, removed when dropped
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = env::temp_dir().join(format!("thud-{}-{}", name, process::id()));
+            let _ = fs::remove_dir_all(&path);
+            Self(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn records() -> Vec<GameRecord> {
+        GameRecord::read_all(
+            "[Result \"troll\"]\n\nG1-G6 H9-H10 H10* resign dwarf\n\
+             [Result \"dwarf\"]\n\nG1-G6 H9-H10 H10* resign troll\n\
+             [Result \"draw\"]\n\nF1-F6 offer accept\n\
+             [Event \"unfinished\"]\n\nG1-G6 G7-F6\n",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn search() {
+        let dir = TempDir::new("search");
+        let mut database = PositionDatabase::open(&dir.0).unwrap();
+        database.add_all(&records()).unwrap();
+
+        // Everything is still there when opened again
+        let database = PositionDatabase::open(&dir.0).unwrap();
+        assert_eq!(database.len(), 4);
+        assert_eq!(database.record(2), Some(&records()[2]));
+
+        let result = database.search(&Board::fresh());
+        assert_eq!(result.games, vec![(0, 0), (1, 0), (2, 0), (3, 0)]);
+        assert_eq!(
+            result.stats(),
+            Stats {
+                dwarf_wins: 1,
+                troll_wins: 1,
+                draws: 1,
+                unfinished: 1,
+            }
+        );
+        assert_eq!(result.continuations.len(), 2);
+        assert_eq!(
+            result.continuations[0].action,
+            Some("G1-G6".parse().unwrap())
+        );
+        assert_eq!(result.continuations[0].stats.total(), 3);
+
+        let mut board = Board::fresh();
+        board.dwarf_move((6, 0).into(), (6, 5).into()).unwrap();
+        let result = database.search(&board);
+        assert_eq!(result.games, vec![(0, 1), (1, 1), (3, 1)]);
+        assert_eq!(
+            result.continuations[1].action,
+            Some("G7-F6".parse().unwrap())
+        );
+        assert_eq!(result.continuations[1].stats.unfinished, 1);
+    }
+
+    #[test]
+    fn search_pattern() {
+        let dir = TempDir::new("search_pattern");
+        let mut database = PositionDatabase::open(&dir.0).unwrap();
+        for record in records() {
+            database.add(&record).unwrap();
+        }
+
+        // A troll on H10 with at least one dwarf in column G, below the trolls
+        let mut pattern = Pattern {
+            pieces: vec![((7, 9).into(), Piece::Troll)],
+            regions: vec![Region {
+                piece: Piece::Dwarf,
+                squares: (0..6).map(|y| (6, y).into()).collect(),
+                at_least: 1,
+            }],
+        };
+        let result = database.search_pattern(&pattern);
+        assert_eq!(result.games, vec![(0, 2), (1, 2)]);
+        assert_eq!(result.stats().total(), result.games.len());
+        assert_eq!(
+            (result.stats().dwarf_wins, result.stats().troll_wins),
+            (1, 1)
+        );
+        // Capturing, resigning as either side, and the end of the record
+        assert_eq!(result.continuations.len(), 4);
+        assert_eq!(
+            result.continuations[0].action,
+            Some("H10*".parse().unwrap())
+        );
+        assert_eq!(result.continuations[0].stats.total(), 2);
+        assert_eq!(result.continuations[2].action, None);
+
+        pattern.regions[0].at_least = 2;
+        assert_eq!(database.search_pattern(&pattern), SearchResult::default());
+    }
+
+    #[test]
+    fn invalid() {
+        let dir = TempDir::new("invalid");
+        let mut database = PositionDatabase::open(&dir.0).unwrap();
+        let mut records = records();
+        records.push("\n\nG1-G6 G6-G7".parse().unwrap());
+        assert!(matches!(
+            database.add_all(&records),
+            Err(DatabaseError::Invalid { index: 4, .. })
+        ));
+        assert!(database.is_empty());
+
+        for (name, value) in [("My Tag", "x"), ("Event", "two\nlines")] {
+            let mut record = records[0].clone();
+            record.set_tag(name, value);
+            assert!(matches!(
+                database.add(&record),
+                Err(DatabaseError::Invalid {
+                    index: 0,
+                    error: ValidationError::Unreadable(ThudError::InvalidNotation),
+                })
+            ));
+        }
+        assert!(database.is_empty());
+
+        // An index entry for a game which isn't there
+        let mut entry = [0; ENTRY_SIZE];
+        entry[8] = 5;
+        fs::write(dir.0.join(POSITIONS_FILE), entry).unwrap();
+        assert!(matches!(
+            PositionDatabase::open(&dir.0),
+            Err(DatabaseError::Corrupt)
+        ));
+    }
+
+    #[test]
+    fn forfeits() {
+        let dir = TempDir::new("forfeits");
+        let mut database = PositionDatabase::open(&dir.0).unwrap();
+        let record: GameRecord = "[Result \"troll\"]\n[Termination \"time_forfeit\"]\n\nG1-G6\n"
+            .parse()
+            .unwrap();
+        database.add(&record).unwrap();
+
+        let database = PositionDatabase::open(&dir.0).unwrap();
+        assert_eq!(database.record(0), Some(&record));
+    }
+
+    #[test]
+    fn failed_write() {
+        let dir = TempDir::new("failed_write");
+        let mut database = PositionDatabase::open(&dir.0).unwrap();
+        // The index can't be written over a directory
+        fs::create_dir(dir.0.join(POSITIONS_FILE)).unwrap();
+        assert!(matches!(
+            database.add_all(&records()),
+            Err(DatabaseError::Io(_))
+        ));
+        assert!(database.is_empty());
+        assert_eq!(database.search(&Board::fresh()), SearchResult::default());
+
+        // The games file was put back as it was
+        fs::remove_dir(dir.0.join(POSITIONS_FILE)).unwrap();
+        let mut database = PositionDatabase::open(&dir.0).unwrap();
+        assert!(database.is_empty());
+        database.add_all(&records()).unwrap();
+        assert_eq!(PositionDatabase::open(&dir.0).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn cut_off() {
+        let dir = TempDir::new("cut_off");
+        let mut database = PositionDatabase::open(&dir.0).unwrap();
+        database.add_all(&records()[..2]).unwrap();
+        let games = fs::read(dir.0.join(GAMES_FILE)).unwrap();
+        let index = fs::read(dir.0.join(POSITIONS_FILE)).unwrap();
+        database.add_all(&records()[2..]).unwrap();
+        let all_games = fs::read(dir.0.join(GAMES_FILE)).unwrap();
+        let all_index = fs::read(dir.0.join(POSITIONS_FILE)).unwrap();
+
+        // Stop partway through the games, then partway through the index
+        let cuts = [
+            (games.len() + 1, index.len()),
+            (games.len() + 10, index.len()),
+            (all_games.len(), index.len()),
+            (all_games.len(), index.len() + 20),
+        ];
+        for (games_len, index_len) in cuts {
+            fs::write(dir.0.join(GAMES_FILE), &all_games[..games_len]).unwrap();
+            fs::write(dir.0.join(POSITIONS_FILE), &all_index[..index_len]).unwrap();
+            let mut database = PositionDatabase::open(&dir.0).unwrap();
+            assert_eq!(database.len(), 2);
+            assert_eq!(fs::read(dir.0.join(GAMES_FILE)).unwrap(), games);
+            assert_eq!(fs::read(dir.0.join(POSITIONS_FILE)).unwrap(), index);
+
+            database.add_all(&records()[2..]).unwrap();
+            assert_eq!(PositionDatabase::open(&dir.0).unwrap().len(), 4);
+        }
+    }
+
+    #[test]
+    fn invalid_on_disk() {
+        let dir = TempDir::new("invalid_on_disk");
+        fs::create_dir_all(&dir.0).unwrap();
+        let text = "\n\nG1-G6 G6-G7\n";
+        fs::write(dir.0.join(GAMES_FILE), format!("{}\n{}", text.len(), text)).unwrap();
+        assert!(matches!(
+            PositionDatabase::open(&dir.0),
+            Err(DatabaseError::Corrupt)
+        ));
+    }
+}
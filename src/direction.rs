@@ -1,10 +1,11 @@
 use crate::coord::Coord;
-use crate::ThudError;
-#[cfg(serialize)]
+use crate::{Symmetry, ThudError};
+#[cfg(feature = "serialize")]
 use serde::{Deserialize, Serialize};
 
 /// A cardinal direction on a [`Board`](struct.Board.html)
-#[cfg_attr(feature = "serialise", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialize", serde(rename_all = "snake_case"))]
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub enum Direction {
     Up,
@@ -24,6 +25,9 @@ impl Direction {
         vec![Up, UpRight, Right, DownRight, Down, DownLeft, Left, UpLeft]
     }
 
+    /// Get a `Direction` from its number, counting clockwise from `0` for `Right`.
+    ///
+    /// Returns [`Err(ThudError::InvalidDirection)`](enum.ThudError.html) if `dir` is above 7.
     pub fn from_num(dir: usize) -> Result<Self, ThudError> {
         match dir {
             0 => Ok(Direction::Right),
@@ -34,19 +38,19 @@ impl Direction {
             5 => Ok(Direction::UpLeft),
             6 => Ok(Direction::Up),
             7 => Ok(Direction::UpRight),
-            _ => Err(ThudError::MathError),
+            _ => Err(ThudError::InvalidDirection(dir)),
         }
     }
 
     /// Use two [`Coord`s](struct.Coord.html) to get a `Direction` from one to the other
     ///
-    /// Returns [`Err(ThudError::MathError)`](enum.ThudError.html) if:
+    /// Returns [`Err(ThudError::NotStraight)`](enum.ThudError.html) if:
     ///
     /// - The two [`Coord`s](enum.Coord.html) are not plottable on a straight line together
     /// - The two [`Coord`s](enum.Coord.html) are equal
     pub fn from_route(start: Coord, end: Coord) -> Result<Direction, ThudError> {
         if start == end {
-            return Err(ThudError::MathError);
+            return Err(ThudError::NotStraight(start, end));
         }
 
         let (sx, sy) = start.value();
@@ -80,7 +84,7 @@ impl Direction {
                 }
             }
         } else {
-            Err(ThudError::MathError)
+            Err(ThudError::NotStraight(start, end))
         }
     }
 
@@ -109,35 +113,31 @@ impl Direction {
 
     /// Return a [`Coord`](struct.Coord.html) equal to `loc` offset by one square in the direction given by `self`.
     ///
-    /// Returns [`Err(ThudError::MathError)`](enum.ThudError.html) if this movement would place the [`Coord`](struct.Coord.html) out of bounds.
+    /// Returns [`Err(ThudError::OffBoard)`](enum.ThudError.html) if this movement would place the [`Coord`](struct.Coord.html) out of bounds.
     pub fn modify(&self, loc: Coord) -> Result<Coord, ThudError> {
-        let modifier = self.modifier();
+        let (dx, dy) = self.modifier();
         let (x, y) = loc.value();
-
-        // Avoid overflows
-        if (x == 0 && modifier.0 == -1) || (y == 0 && modifier.1 == -1) {
-            return Err(ThudError::MathError);
+        let off_board = ThudError::OffBoard(loc, *self);
+        match (x.checked_add_signed(dx), y.checked_add_signed(dy)) {
+            (Some(x), Some(y)) => Coord::zero_based(x, y).map_err(|_| off_board),
+            _ => Err(off_board),
         }
+    }
 
-        // Workaround to using `usize`
-        let new = (
-            match modifier.0 {
-                1 => x + 1,
-                -1 => x - 1,
-                _ => x,
-            },
-            match modifier.1 {
-                1 => y + 1,
-                -1 => y - 1,
-                _ => y,
-            },
-        );
-
-        if let Ok(coord) = Coord::zero_based(new.0, new.1) {
-            Ok(coord)
-        } else {
-            Err(ThudError::MathError)
-        }
+    /// Get the `Direction` this one is taken to by turning or flipping the board with `symmetry`
+    ///
+    /// ```
+    /// use thud::{Direction, Symmetry};
+    ///
+    /// assert_eq!(Direction::Up.transform(Symmetry::Rotate90), Direction::Left);
+    /// assert_eq!(Direction::UpRight.transform(Symmetry::FlipHorizontal), Direction::UpLeft);
+    /// ```
+    pub fn transform(&self, symmetry: Symmetry) -> Self {
+        let modifier = symmetry.apply(self.modifier());
+        Direction::all()
+            .into_iter()
+            .find(|dir| dir.modifier() == modifier)
+            .expect("symmetries take directions to directions")
     }
 
     /// Get the "opposite" `Direction` to `self`.
@@ -185,4 +185,18 @@ mod tests {
     fn modify(dir: Direction, loc: (usize, usize)) -> (usize, usize) {
         dir.modify(loc.into()).expect("").value()
     }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            Down.modify((7, 0).into()),
+            Err(ThudError::OffBoard((7, 0).into(), Down))
+        );
+        assert_eq!(
+            UpRight.modify((14, 7).into()),
+            Err(ThudError::OffBoard((14, 7).into(), UpRight))
+        );
+        assert_eq!(Direction::from_num(7), Ok(UpRight));
+        assert_eq!(Direction::from_num(8), Err(ThudError::InvalidDirection(8)));
+    }
 }
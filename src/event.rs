@@ -0,0 +1,76 @@
+use crate::{Coord, EndState, Piece, Player};
+#[cfg(feature = "serialize")]
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Mutex;
+
+/// Something which happened in a [`Thud`](struct.Thud.html) game, as told to listeners added
+/// with [`Thud::listen()`](struct.Thud.html#method.listen)
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialize", serde(rename_all = "snake_case"))]
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Event {
+    /// `player` moved their `piece` from `from` to `to`, by a move, hurl or shove
+    Moved {
+        player: Player,
+        piece: Piece,
+        from: Coord,
+        to: Coord,
+    },
+    /// `player` captured the `piece` at `square`
+    Captured {
+        player: Player,
+        piece: Piece,
+        square: Coord,
+    },
+    /// It is now `player`'s turn
+    TurnChanged { player: Player },
+    /// The game is over
+    GameEnded(EndState),
+    /// The last action, or a loss on time, was taken back with
+    /// [`Thud::undo()`](struct.Thud.html#method.undo), so earlier events may no longer hold
+    Rewound,
+}
+
+type Listener = Box<dyn FnMut(&Event) + Send>;
+
+/// The listeners on a game, which are left behind when it is cloned or serialised
+// Only ever called through `&mut`, the `Mutex` is there to keep games `Sync`
+#[derive(Default)]
+pub(crate) struct Listeners(Mutex<Vec<Listener>>);
+
+impl Listeners {
+    pub(crate) fn add(&mut self, listener: Listener) {
+        self.list().push(listener);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.list().clear();
+    }
+
+    /// Tell every listener about `event`, in the order they were added
+    pub(crate) fn emit(&mut self, event: Event) {
+        for listener in self.list().iter_mut() {
+            listener(&event);
+        }
+    }
+
+    fn list(&mut self) -> &mut Vec<Listener> {
+        self.0
+            .get_mut()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Clone for Listeners {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl fmt::Debug for Listeners {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let count = self.0.lock().map(|list| list.len()).unwrap_or_default();
+        write!(f, "Listeners({})", count)
+    }
+}
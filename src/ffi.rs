@@ -0,0 +1,405 @@
+//! C API, enabled with the `ffi` feature.
+//!
+//! The header for this API is checked in as `include/thud.h`. The build script generates it
+//! afresh in `OUT_DIR`, and a test fails if the checked-in copy differs. Games are held
+//! behind an opaque `ThudGame` handle, which must be freed with [`thud_free()`](fn.thud_free.html).
+//!
+//! Squares are addressed with zero-based `x, y` pairs as with
+//! [`Coord::zero_based()`](../struct.Coord.html#method.zero_based), pieces are given as `0` for
+//! an empty square, `1` for a dwarf, `2` for a troll and `3` for the thudstone, and directions
+//! as with [`Direction::from_num()`](../enum.Direction.html#method.from_num).
+
+use crate::{Coord, Direction, EndReason, EndState, Player, Thud, ThudError};
+use std::os::raw::c_char;
+
+/// Opaque handle to a game of Thud
+pub struct ThudGame(Thud);
+
+/// Outcome of a call into the C API
+#[repr(C)]
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum ThudStatus {
+    Ok = 0,
+    InvalidPosition,
+    /// Any of the errors about a move breaking the rules, besides the two below
+    IllegalMove,
+    Obstacle,
+    LineTooShort,
+    InvalidDirection,
+    BadAction,
+    InvalidBoard,
+    InvalidNotation,
+    NullPointer,
+    InvalidRules,
+}
+
+impl From<ThudError> for ThudStatus {
+    fn from(error: ThudError) -> Self {
+        match error {
+            ThudError::InvalidPosition(..) | ThudError::OffBoard(..) => ThudStatus::InvalidPosition,
+            ThudError::WrongPiece { .. }
+            | ThudError::Immovable(..)
+            | ThudError::Occupied(..)
+            | ThudError::NotStraight(..)
+            | ThudError::TooFar(..)
+            | ThudError::NoAdjacentDwarf(..)
+            | ThudError::WrongTroll { .. }
+            | ThudError::MustCapture(..) => ThudStatus::IllegalMove,
+            ThudError::Obstacle(..) => ThudStatus::Obstacle,
+            ThudError::LineTooShort(..) => ThudStatus::LineTooShort,
+            ThudError::InvalidDirection(..) => ThudStatus::InvalidDirection,
+            ThudError::BadAction => ThudStatus::BadAction,
+            ThudError::InvalidBoard => ThudStatus::InvalidBoard,
+            ThudError::InvalidNotation => ThudStatus::InvalidNotation,
+            ThudError::InvalidRules => ThudStatus::InvalidRules,
+        }
+    }
+}
+
+impl From<Result<(), ThudError>> for ThudStatus {
+    fn from(result: Result<(), ThudError>) -> Self {
+        match result {
+            Ok(()) => ThudStatus::Ok,
+            Err(error) => error.into(),
+        }
+    }
+}
+
+/// Whose turn it is, or who won
+#[repr(C)]
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum ThudPlayer {
+    /// No player, ie. the game has ended or it is a draw
+    None = 0,
+    Dwarf,
+    Troll,
+}
+
+impl From<Player> for ThudPlayer {
+    fn from(player: Player) -> Self {
+        match player {
+            Player::Dwarf => ThudPlayer::Dwarf,
+            Player::Troll => ThudPlayer::Troll,
+        }
+    }
+}
+
+/// Why a game ended, mirroring [`EndReason`](../enum.EndReason.html)
+#[repr(C)]
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum ThudEndReason {
+    NoMoves = 0,
+    Agreement,
+    Resignation,
+    Repetition,
+    MoveLimit,
+    OutOfTime,
+}
+
+impl From<EndReason> for ThudEndReason {
+    fn from(reason: EndReason) -> Self {
+        match reason {
+            EndReason::NoMoves => ThudEndReason::NoMoves,
+            EndReason::Agreement => ThudEndReason::Agreement,
+            EndReason::Resignation => ThudEndReason::Resignation,
+            EndReason::Repetition => ThudEndReason::Repetition,
+            EndReason::MoveLimit => ThudEndReason::MoveLimit,
+            EndReason::OutOfTime => ThudEndReason::OutOfTime,
+        }
+    }
+}
+
+/// The result of a finished game; `winner` is `None` for a draw
+#[repr(C)]
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct ThudOutcome {
+    pub winner: ThudPlayer,
+    pub reason: ThudEndReason,
+}
+
+fn coord(x: usize, y: usize) -> Result<Coord, ThudError> {
+    Coord::zero_based(x, y)
+}
+
+/// Start a new game, which must later be freed with [`thud_free()`](fn.thud_free.html).
+#[no_mangle]
+pub extern "C" fn thud_new() -> *mut ThudGame {
+    Box::into_raw(Box::new(ThudGame(Thud::new())))
+}
+
+/// Free a game made with [`thud_new()`](fn.thud_new.html).
+///
+/// # Safety
+///
+/// `game` must be a handle returned by [`thud_new()`](fn.thud_new.html) which has not yet been
+/// freed, or null.
+#[no_mangle]
+pub unsafe extern "C" fn thud_free(game: *mut ThudGame) {
+    if !game.is_null() {
+        drop(Box::from_raw(game));
+    }
+}
+
+/// Wrapper for [`Thud::move_piece()`](../struct.Thud.html#method.move_piece)
+///
+/// # Safety
+///
+/// `game` must be a live handle from [`thud_new()`](fn.thud_new.html), or null.
+#[no_mangle]
+pub unsafe extern "C" fn thud_move(
+    game: *mut ThudGame,
+    src_x: usize,
+    src_y: usize,
+    dest_x: usize,
+    dest_y: usize,
+) -> ThudStatus {
+    match game.as_mut() {
+        Some(ThudGame(game)) => {
+            (|| game.move_piece(coord(src_x, src_y)?, coord(dest_x, dest_y)?))().into()
+        }
+        None => ThudStatus::NullPointer,
+    }
+}
+
+/// Wrapper for [`Thud::attack()`](../struct.Thud.html#method.attack), which will hurl a dwarf
+/// or shove a troll depending on whose turn it is
+///
+/// # Safety
+///
+/// `game` must be a live handle from [`thud_new()`](fn.thud_new.html), or null.
+#[no_mangle]
+pub unsafe extern "C" fn thud_attack(
+    game: *mut ThudGame,
+    src_x: usize,
+    src_y: usize,
+    dest_x: usize,
+    dest_y: usize,
+) -> ThudStatus {
+    match game.as_mut() {
+        Some(ThudGame(game)) => {
+            (|| game.attack(coord(src_x, src_y)?, coord(dest_x, dest_y)?))().into()
+        }
+        None => ThudStatus::NullPointer,
+    }
+}
+
+/// Wrapper for [`Thud::troll_cap()`](../struct.Thud.html#method.troll_cap)
+///
+/// `directions` should point to `len` direction numbers; it may be null if `len` is 0.
+///
+/// # Safety
+///
+/// `game` must be a live handle from [`thud_new()`](fn.thud_new.html), or null, and
+/// `directions` must be valid for reads of `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn thud_capture(
+    game: *mut ThudGame,
+    troll_x: usize,
+    troll_y: usize,
+    directions: *const u8,
+    len: usize,
+) -> ThudStatus {
+    let game = match game.as_mut() {
+        Some(ThudGame(game)) => game,
+        None => return ThudStatus::NullPointer,
+    };
+    let directions: &[u8] = if len == 0 {
+        &[]
+    } else if directions.is_null() {
+        return ThudStatus::NullPointer;
+    } else {
+        std::slice::from_raw_parts(directions, len)
+    };
+
+    (|| {
+        let targets = directions
+            .iter()
+            .map(|dir| Direction::from_num(*dir as usize))
+            .collect::<Result<Vec<Direction>, ThudError>>()?;
+        game.troll_cap(coord(troll_x, troll_y)?, targets)
+    })()
+    .into()
+}
+
+/// Find whose turn it is, or [`ThudPlayer::None`](enum.ThudPlayer.html) if the game is over
+///
+/// # Safety
+///
+/// `game` must be a live handle from [`thud_new()`](fn.thud_new.html), or null.
+#[no_mangle]
+pub unsafe extern "C" fn thud_turn(game: *const ThudGame) -> ThudPlayer {
+    match game.as_ref().and_then(|ThudGame(game)| game.turn()) {
+        Some(player) => player.into(),
+        None => ThudPlayer::None,
+    }
+}
+
+/// Copy the board into `out`, a 225-byte array indexed by `x * 15 + y`
+///
+/// # Safety
+///
+/// `game` must be a live handle from [`thud_new()`](fn.thud_new.html), or null, and `out` must
+/// be valid for writes of 225 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn thud_board(game: *const ThudGame, out: *mut u8) -> ThudStatus {
+    let game = match game.as_ref() {
+        Some(ThudGame(game)) => game,
+        None => return ThudStatus::NullPointer,
+    };
+    if out.is_null() {
+        return ThudStatus::NullPointer;
+    }
+
+    let out = std::slice::from_raw_parts_mut(out, 15 * 15);
+    for (x, column) in game.board().full_raw().iter().enumerate() {
+        for (y, piece) in column.iter().enumerate() {
+            out[x * 15 + y] = piece.code();
+        }
+    }
+    ThudStatus::Ok
+}
+
+/// Wrapper for [`Thud::score()`](../struct.Thud.html#method.score)
+///
+/// # Safety
+///
+/// `game` must be a live handle from [`thud_new()`](fn.thud_new.html), or null, and `dwarf`
+/// and `troll` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn thud_score(
+    game: *const ThudGame,
+    dwarf: *mut usize,
+    troll: *mut usize,
+) -> ThudStatus {
+    match (game.as_ref(), dwarf.as_mut(), troll.as_mut()) {
+        (Some(ThudGame(game)), Some(dwarf), Some(troll)) => {
+            let score = game.score();
+            *dwarf = score.0;
+            *troll = score.1;
+            ThudStatus::Ok
+        }
+        _ => ThudStatus::NullPointer,
+    }
+}
+
+/// Wrapper for [`Thud::winner()`](../struct.Thud.html#method.winner)
+///
+/// Returns `true` and fills in `out` if the game has ended, otherwise returns `false`.
+///
+/// # Safety
+///
+/// `game` must be a live handle from [`thud_new()`](fn.thud_new.html), or null, and `out` must
+/// be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn thud_winner(game: *mut ThudGame, out: *mut ThudOutcome) -> bool {
+    let (game, out) = match (game.as_mut(), out.as_mut()) {
+        (Some(ThudGame(game)), Some(out)) => (game, out),
+        _ => return false,
+    };
+
+    match game.winner() {
+        Some(EndState::Won(player, reason)) => {
+            *out = ThudOutcome {
+                winner: player.into(),
+                reason: reason.into(),
+            };
+            true
+        }
+        Some(EndState::Draw(reason)) => {
+            *out = ThudOutcome {
+                winner: ThudPlayer::None,
+                reason: reason.into(),
+            };
+            true
+        }
+        None => false,
+    }
+}
+
+/// Get a human-readable, static, NUL-terminated description of a status
+#[no_mangle]
+pub extern "C" fn thud_status_message(status: ThudStatus) -> *const c_char {
+    let message: &'static [u8] = match status {
+        ThudStatus::Ok => b"Ok\0",
+        ThudStatus::InvalidPosition => b"Position is out of bounds\0",
+        ThudStatus::IllegalMove => b"Requested move not allowed\0",
+        ThudStatus::Obstacle => b"A piece is blocking that move\0",
+        ThudStatus::LineTooShort => b"Line of pieces is too short to make that move\0",
+        ThudStatus::InvalidDirection => b"Direction is not a number from 0 to 7\0",
+        ThudStatus::BadAction => b"Action not allowed at this point in the game\0",
+        ThudStatus::InvalidBoard => b"Board is not a valid Thud position\0",
+        ThudStatus::InvalidNotation => b"Could not read notation\0",
+        ThudStatus::NullPointer => b"A required pointer was null\0",
+        ThudStatus::InvalidRules => {
+            b"A repetition limit has to be at least 2 and a move limit at least 1\0"
+        }
+    };
+    message.as_ptr() as *const c_char
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+
+    #[test]
+    fn header_is_current() {
+        let generated = include_str!(concat!(env!("OUT_DIR"), "/thud.h"));
+        assert!(
+            include_str!("../include/thud.h") == generated,
+            "include/thud.h is out of date, copy it from {}",
+            concat!(env!("OUT_DIR"), "/thud.h")
+        );
+    }
+
+    #[test]
+    fn play() {
+        unsafe {
+            let game = thud_new();
+            assert_eq!(thud_turn(game), ThudPlayer::Dwarf);
+            assert_eq!(thud_move(game, 6, 0, 6, 5), ThudStatus::Ok);
+            assert_eq!(thud_move(game, 6, 0, 6, 5), ThudStatus::IllegalMove);
+            assert_eq!(thud_attack(game, 8, 7, 0, 0), ThudStatus::InvalidPosition);
+            assert_eq!(thud_move(game, 8, 7, 9, 7), ThudStatus::Ok);
+            assert_eq!(
+                thud_capture(game, 9, 7, std::ptr::null(), 0),
+                ThudStatus::Ok
+            );
+            assert_eq!(thud_turn(game), ThudPlayer::Dwarf);
+
+            let mut board = [0; 225];
+            assert_eq!(thud_board(game, board.as_mut_ptr()), ThudStatus::Ok);
+            assert_eq!(board[7 * 15 + 7], 3);
+            assert_eq!(board[6 * 15 + 5], 1);
+
+            let (mut dwarf, mut troll) = (0, 0);
+            assert_eq!(thud_score(game, &mut dwarf, &mut troll), ThudStatus::Ok);
+            assert_eq!((dwarf, troll), (32, 32));
+
+            let mut outcome = ThudOutcome {
+                winner: ThudPlayer::None,
+                reason: ThudEndReason::NoMoves,
+            };
+            assert!(!thud_winner(game, &mut outcome));
+            thud_free(game);
+        }
+    }
+
+    #[test]
+    fn null() {
+        unsafe {
+            assert_eq!(
+                thud_move(std::ptr::null_mut(), 6, 0, 6, 5),
+                ThudStatus::NullPointer
+            );
+            assert_eq!(thud_turn(std::ptr::null()), ThudPlayer::None);
+            thud_free(std::ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn message() {
+        let message = unsafe { CStr::from_ptr(thud_status_message(ThudStatus::BadAction)) };
+        assert_eq!(message.to_str().unwrap(), ThudError::BadAction.to_string());
+    }
+}
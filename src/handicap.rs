@@ -0,0 +1,75 @@
+use crate::{Board, Piece};
+#[cfg(feature = "serialize")]
+use serde::{Deserialize, Serialize};
+
+/// A preset starting position that gives one player an advantage
+///
+/// Use with [`Thud::from_board()`](struct.Thud.html#method.from_board).
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialize", serde(rename_all = "snake_case"))]
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Handicap {
+    /// Two trolls are removed from opposite corners of the trolls' square, in favour of the dwarves
+    TwoTrollsRemoved,
+    /// All four trolls on the corners of the trolls' square are removed, in favour of the dwarves
+    FourTrollsRemoved,
+    /// Four dwarves are added at the middle of each edge of the board, in favour of the dwarves
+    ExtraDwarves,
+    /// The eight dwarves on the edges beside the middle of each edge are removed, in favour of
+    /// the trolls
+    FewerDwarves,
+}
+
+impl Handicap {
+    /// Get a `Vec` containing all the different `Handicap`s
+    pub fn all() -> Vec<Self> {
+        use Handicap::*;
+        vec![
+            TwoTrollsRemoved,
+            FourTrollsRemoved,
+            ExtraDwarves,
+            FewerDwarves,
+        ]
+    }
+
+    /// Get the starting [`Board`](struct.Board.html) for this handicap
+    pub fn board(&self) -> Board {
+        let mut board = Board::fresh();
+        let (squares, piece): (&[(usize, usize)], Piece) = match self {
+            Handicap::TwoTrollsRemoved => (&[(6, 8), (8, 6)], Piece::Empty),
+            Handicap::FourTrollsRemoved => (&[(6, 6), (6, 8), (8, 6), (8, 8)], Piece::Empty),
+            Handicap::ExtraDwarves => (&[(0, 7), (14, 7), (7, 0), (7, 14)], Piece::Dwarf),
+            Handicap::FewerDwarves => (
+                &[
+                    (0, 6),
+                    (0, 8),
+                    (14, 6),
+                    (14, 8),
+                    (6, 0),
+                    (8, 0),
+                    (6, 14),
+                    (8, 14),
+                ],
+                Piece::Empty,
+            ),
+        };
+        for square in squares {
+            board.place((*square).into(), piece);
+        }
+        board
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(Handicap::TwoTrollsRemoved => (32, 24))]
+    #[test_case(Handicap::FourTrollsRemoved => (32, 16))]
+    #[test_case(Handicap::ExtraDwarves => (36, 32))]
+    #[test_case(Handicap::FewerDwarves => (24, 32))]
+    fn score(handicap: Handicap) -> (usize, usize) {
+        handicap.board().score()
+    }
+}
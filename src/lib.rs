@@ -4,58 +4,246 @@
 //! ## Getting Started
 //!
 //! To get started, take a look at the [`Thud`](struct.Thud.html) `struct`.
+//! [`Phase`](enum.Phase.html) wraps it so that taking an action out of turn won't compile.
+//!
+//! To write a computer player, implement [`Agent`](trait.Agent.html); it can be run as an engine
+//! speaking the text protocol in the [`protocol`](protocol/index.html) module.
+//!
+//! To play against the clock, wrap a game in a [`ClockedGame`](struct.ClockedGame.html).
+//!
+//! To look up how positions have gone in past games, add their records to a
+//! [`PositionDatabase`](struct.PositionDatabase.html).
 //!
 //! ### `serialize` feature
 //!
 //! The library supports serialising and deserialising all types using
 //! [`serde`](https://serde.rs/) when this feature is enabled.
+//!
+//! The wire format is stable between releases; in self-describing formats such as JSON it looks
+//! like this:
+//!
+//! - Enum variants are written in `snake_case`, eg. [`Piece::Thudstone`](enum.Piece.html) is
+//!   `"thudstone"` and [`Direction::UpLeft`](enum.Direction.html) is `"up_left"`
+//! - Variants carrying data are written as a single-entry map, eg.
+//!   [`EndState::Won(Player::Troll, EndReason::NoMoves)`](enum.EndState.html) is
+//!   `{"won": ["troll", "no_moves"]}`
+//! - [`Coord`](struct.Coord.html) is `{"x": 7, "y": 7}`, zero-based, and is bounds-checked when
+//!   deserialised
+//! - [`Board`](struct.Board.html) is `{"squares": [[...], ...]}`, a 15x15 array of pieces
+//!   indexed by `x` then `y`, and is [validated](struct.Board.html#method.validate) when
+//!   deserialised
+//! - [`Thud`](struct.Thud.html) is a map holding the `board`, the `rules`, any `end_offer`, the
+//!   `history` and starting position, some bookkeeping for the rules, and the `state`, which is
+//!   one of `{"to_move": <player>}`, `{"troll_capture": {"shoved": <bool>,
+//!   "troll": <coord>}}` while the troll player is yet to capture, or `{"ended": <end state>}`
+//!
+//! For saving games to be loaded later, use a [`Snapshot`](struct.Snapshot.html); unlike
+//! [`Thud`](struct.Thud.html), its format is versioned and older snapshots are upgraded when
+//! loaded.
+//!
+//! ### `ffi` feature
+//!
+//! Builds a C API, found in the [`ffi`](ffi/index.html) module. Its header is checked in at
+//! `include/thud.h`; the build script generates a fresh one in `OUT_DIR`, and a test checks
+//! the two match.
+//!
+//! ### `wasm` feature
+//!
+//! Builds JavaScript bindings with [`wasm-bindgen`](https://rustwasm.github.io/wasm-bindgen/),
+//! found in the [`wasm`](wasm/index.html) module; enables `serialize`.
+//!
+//! ### `python` feature
+//!
+//! Builds a Python module with [`pyo3`](https://pyo3.rs/), found in the
+//! [`python`](python/index.html) module, which exports boards as NumPy arrays.
+//!
+//! ### `server` feature
+//!
+//! Builds an HTTP and WebSocket server for hosting many games at once, found in the
+//! [`server`](server/index.html) module, and the `thud-server` binary; enables `serialize`.
 
+mod action;
+mod agent;
 mod board;
+mod clock;
 mod coord;
+mod database;
 mod direction;
+mod event;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod handicap;
+mod phase;
 mod piece;
+pub mod protocol;
+#[cfg(feature = "python")]
+pub mod python;
+mod record;
+mod rules;
+#[cfg(feature = "server")]
+pub mod server;
+mod session;
+#[cfg(feature = "serialize")]
+mod snapshot;
 mod state;
+mod symmetry;
+mod tree;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 use thiserror::Error;
 
-pub use board::Board;
+pub use action::Action;
+pub use agent::{Agent, GreedyAgent, RandomAgent};
+pub use board::{Board, BoardViolation, CaptureOptions, CaptureSubsets};
+pub use clock::{Clock, ClockedGame, ManualTime, RealTime, TimeControl, TimeSource};
 pub use coord::Coord;
+pub use database::{
+    Continuation, DatabaseError, Pattern, PositionDatabase, Region, SearchResult, Stats,
+};
 pub use direction::Direction;
+pub use event::Event;
+pub use handicap::Handicap;
+pub use phase::{DwarfTurn, GameOver, Phase, Rejected, TrollCapturePending, TrollTurn};
 pub use piece::Piece;
+pub use record::{GameRecord, Outcome, ValidationError};
+pub use rules::Rules;
 #[cfg(feature = "serialize")]
 use serde::{Deserialize, Serialize};
-pub use state::Thud;
+pub use session::{SeatToken, Session, SessionError};
+#[cfg(feature = "serialize")]
+pub use snapshot::{Snapshot, SnapshotError};
+pub use state::{DecodeError, Thud};
+pub use symmetry::Symmetry;
+pub use tree::{GameTree, Mark, NodeId};
 
 /// One of the two Thud players
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialize", serde(rename_all = "snake_case"))]
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub enum Player {
     Dwarf,
     Troll,
 }
 
+impl Player {
+    /// Get the other player
+    pub fn opponent(&self) -> Self {
+        match self {
+            Player::Dwarf => Player::Troll,
+            Player::Troll => Player::Dwarf,
+        }
+    }
+}
+
 /// What victory condition a [`Thud`](struct.Thud.html) game is in once it has ended
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialize", serde(rename_all = "snake_case"))]
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub enum EndState {
-    Won(Player),
-    Draw,
+    Won(Player, EndReason),
+    Draw(EndReason),
+}
+
+impl EndState {
+    /// Decide the result from a score in the format given by
+    /// [`Board::score()`](struct.Board.html#method.score)
+    pub(crate) fn from_score(
+        (dwarf_score, troll_score): (usize, usize),
+        reason: EndReason,
+    ) -> Self {
+        if dwarf_score > troll_score {
+            EndState::Won(Player::Dwarf, reason)
+        } else if troll_score > dwarf_score {
+            EndState::Won(Player::Troll, reason)
+        } else {
+            EndState::Draw(reason)
+        }
+    }
+
+    /// Get the reason the game ended
+    pub fn reason(&self) -> EndReason {
+        match self {
+            EndState::Won(_, reason) | EndState::Draw(reason) => *reason,
+        }
+    }
+}
+
+/// Why a [`Thud`](struct.Thud.html) game ended
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialize", serde(rename_all = "snake_case"))]
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum EndReason {
+    /// One of the players had no moves left
+    NoMoves,
+    /// Both players agreed that no further captures were worthwhile
+    Agreement,
+    /// One of the players conceded
+    Resignation,
+    /// The same position came up too many times, see [`Rules`](struct.Rules.html)
+    Repetition,
+    /// Too many turns were taken without a capture, see [`Rules`](struct.Rules.html)
+    MoveLimit,
+    /// One of the players ran out of time on their [`Clock`](struct.Clock.html)
+    OutOfTime,
+}
+
+/// Describes the reason in a few words, such as `out of time`
+impl std::fmt::Display for EndReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let words = match self {
+            EndReason::NoMoves => "no moves left",
+            EndReason::Agreement => "agreement",
+            EndReason::Resignation => "resignation",
+            EndReason::Repetition => "repetition",
+            EndReason::MoveLimit => "move limit",
+            EndReason::OutOfTime => "out of time",
+        };
+        f.write_str(words)
+    }
 }
 
 /// Reports invalid action
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialize", serde(rename_all = "snake_case"))]
 #[derive(Debug, PartialEq, Copy, Clone, Error)]
 pub enum ThudError {
     #[error("({0},{1}) is out of bounds")]
     InvalidPosition(usize, usize),
-    #[error("Requested move not allowed")]
-    IllegalMove,
-    #[error("A piece at ({0},{1}) is blocking that move")]
-    Obstacle(usize, usize),
+    #[error("Expected a {expected:?} at {square} but found {found:?}")]
+    WrongPiece {
+        square: Coord,
+        expected: Piece,
+        found: Piece,
+    },
+    #[error("The {1:?} at {0} can't be moved")]
+    Immovable(Coord, Piece),
+    #[error("There is already a {1:?} at {0}")]
+    Occupied(Coord, Piece),
+    #[error("There is no straight line from {0} to {1}")]
+    NotStraight(Coord, Coord),
+    #[error("A troll can only move one square, not from {0} to {1}")]
+    TooFar(Coord, Coord),
+    #[error("The {1:?} at {0} is blocking that move")]
+    Obstacle(Coord, Piece),
     #[error("You need {0} pieces behind you to make that move but you only have {1}")]
     LineTooShort(usize, usize),
-    #[error("Arithmetic Error")]
-    MathError,
+    #[error("A troll can only be shoved next to a dwarf, and there is none by {0}")]
+    NoAdjacentDwarf(Coord),
+    #[error("Only the troll which just moved, at {moved}, can capture, not one at {chosen}")]
+    WrongTroll { moved: Coord, chosen: Coord },
+    #[error("The troll at {0} was shoved, so it has to capture at least one dwarf")]
+    MustCapture(Coord),
+    #[error("Moving {1:?} from {0} leaves the board")]
+    OffBoard(Coord, Direction),
+    #[error("{0} is not a direction")]
+    InvalidDirection(usize),
     #[error("Action not allowed at this point in the game")]
     BadAction,
+    #[error("Board is not a valid Thud position")]
+    InvalidBoard,
+    #[error("Could not read notation")]
+    InvalidNotation,
+    #[error("A repetition limit has to be at least 2 and a move limit at least 1")]
+    InvalidRules,
 }
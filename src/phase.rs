@@ -0,0 +1,311 @@
+use crate::{CaptureOptions, Coord, Direction, EndState, Player, Thud, ThudError};
+use std::fmt;
+
+/// A [`Thud`](struct.Thud.html) game at one of the points in a turn, each its own type so that
+/// only the actions allowed there can be taken
+///
+/// Every action takes the phase by value and gives back the next one, so taking an action out
+/// of turn won't compile:
+///
+/// ```compile_fail
+/// use thud::{Phase, Thud};
+///
+/// if let Ok(Phase::Dwarf(turn)) = Phase::new(Thud::new()) {
+///     // Dwarves can't capture
+///     turn.capture(vec![]);
+/// }
+/// ```
+///
+/// ```
+/// use thud::{Direction, Phase, Player, Thud};
+///
+/// let dwarf = match Phase::new(Thud::new()).unwrap() {
+///     Phase::Dwarf(turn) => turn,
+///     _ => unreachable!(),
+/// };
+/// let troll = match dwarf.move_piece((6, 0).into(), (6, 5).into()).unwrap() {
+///     Phase::Troll(turn) => turn,
+///     _ => unreachable!(),
+/// };
+/// let pending = troll.move_piece((7, 6).into(), (7, 5).into()).unwrap();
+/// match pending.capture(vec![Direction::Left]).unwrap() {
+///     Phase::Dwarf(turn) => assert_eq!(turn.game().score(), (31, 32)),
+///     _ => unreachable!(),
+/// }
+/// ```
+///
+/// Offering an end to the game isn't covered; use the game itself for that.
+#[derive(Debug, Clone)]
+pub enum Phase {
+    /// The dwarf player is to move or hurl
+    Dwarf(DwarfTurn),
+    /// The troll player is to move or shove
+    Troll(TrollTurn),
+    /// The troll player has moved or shoved and is yet to capture
+    Capture(TrollCapturePending),
+    /// The game is over
+    Ended(GameOver),
+}
+
+impl Phase {
+    /// Find which phase `game` is at.
+    ///
+    /// Returns [`Err(ThudError::BadAction)`](enum.ThudError.html) if an end to the game has
+    /// been offered and is yet to be answered.
+    pub fn new(game: Thud) -> Result<Self, ThudError> {
+        if game.end_offer().is_some() {
+            return Err(ThudError::BadAction);
+        }
+        Ok(Self::after(Box::new(game)))
+    }
+
+    /// Find the phase of a game with no end offer pending
+    fn after(mut game: Box<Thud>) -> Self {
+        if let Some(end) = game.winner() {
+            return Phase::Ended(GameOver { game, end });
+        }
+        match (game.pending_capture(), game.turn()) {
+            (Some(troll), _) => Phase::Capture(TrollCapturePending { game, troll }),
+            (None, Some(Player::Dwarf)) => Phase::Dwarf(DwarfTurn(game)),
+            (None, _) => Phase::Troll(TrollTurn(game)),
+        }
+    }
+
+    /// Get the game
+    pub fn game(&self) -> &Thud {
+        match self {
+            Phase::Dwarf(DwarfTurn(game))
+            | Phase::Troll(TrollTurn(game))
+            | Phase::Capture(TrollCapturePending { game, .. })
+            | Phase::Ended(GameOver { game, .. }) => game,
+        }
+    }
+
+    /// Get the game back to use directly
+    pub fn into_game(self) -> Thud {
+        match self {
+            Phase::Dwarf(DwarfTurn(game))
+            | Phase::Troll(TrollTurn(game))
+            | Phase::Capture(TrollCapturePending { game, .. })
+            | Phase::Ended(GameOver { game, .. }) => *game,
+        }
+    }
+}
+
+/// An action a [`Phase`](enum.Phase.html) turned down, giving the phase back unchanged
+#[derive(Debug)]
+pub struct Rejected<T> {
+    /// The phase the action was tried in
+    pub phase: T,
+    /// Why the action was turned down
+    pub error: ThudError,
+}
+
+impl<T> fmt::Display for Rejected<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl<T: fmt::Debug> std::error::Error for Rejected<T> {}
+
+/// Shared by every phase of a game which is still in progress
+macro_rules! in_progress {
+    ($phase:ident, $game:tt) => {
+        impl $phase {
+            /// Get the game
+            pub fn game(&self) -> &Thud {
+                &self.$game
+            }
+
+            /// Get the game back to use directly
+            pub fn into_game(self) -> Thud {
+                *self.$game
+            }
+
+            /// Concede the game on behalf of `player`, see
+            /// [`Thud::resign()`](struct.Thud.html#method.resign)
+            pub fn resign(self, player: Player) -> GameOver {
+                let mut game = self.$game;
+                let end = game
+                    .resign(player)
+                    .expect("games in progress can be resigned");
+                GameOver { game, end }
+            }
+        }
+    };
+}
+
+/// The dwarf player's turn, see [`Phase`](enum.Phase.html)
+#[derive(Debug, Clone)]
+pub struct DwarfTurn(Box<Thud>);
+
+impl DwarfTurn {
+    /// Move a dwarf, see [`Thud::move_piece()`](struct.Thud.html#method.move_piece)
+    pub fn move_piece(mut self, src: Coord, target: Coord) -> Result<Phase, Rejected<Self>> {
+        match self.0.move_piece(src, target) {
+            Ok(()) => Ok(Phase::after(self.0)),
+            Err(error) => Err(Rejected { phase: self, error }),
+        }
+    }
+
+    /// Hurl a dwarf at a troll, see [`Thud::attack()`](struct.Thud.html#method.attack)
+    pub fn attack(mut self, src: Coord, target: Coord) -> Result<Phase, Rejected<Self>> {
+        match self.0.attack(src, target) {
+            Ok(()) => Ok(Phase::after(self.0)),
+            Err(error) => Err(Rejected { phase: self, error }),
+        }
+    }
+}
+
+in_progress!(DwarfTurn, 0);
+
+/// The start of the troll player's turn, see [`Phase`](enum.Phase.html)
+#[derive(Debug, Clone)]
+pub struct TrollTurn(Box<Thud>);
+
+impl TrollTurn {
+    /// Move a troll, see [`Thud::move_piece()`](struct.Thud.html#method.move_piece)
+    pub fn move_piece(
+        mut self,
+        src: Coord,
+        target: Coord,
+    ) -> Result<TrollCapturePending, Rejected<Self>> {
+        match self.0.move_piece(src, target) {
+            Ok(()) => Ok(TrollCapturePending {
+                game: self.0,
+                troll: target,
+            }),
+            Err(error) => Err(Rejected { phase: self, error }),
+        }
+    }
+
+    /// Shove a troll, see [`Thud::attack()`](struct.Thud.html#method.attack)
+    pub fn attack(
+        mut self,
+        src: Coord,
+        target: Coord,
+    ) -> Result<TrollCapturePending, Rejected<Self>> {
+        match self.0.attack(src, target) {
+            Ok(()) => Ok(TrollCapturePending {
+                game: self.0,
+                troll: target,
+            }),
+            Err(error) => Err(Rejected { phase: self, error }),
+        }
+    }
+}
+
+in_progress!(TrollTurn, 0);
+
+/// The end of the troll player's turn, after a move or shove, see [`Phase`](enum.Phase.html)
+#[derive(Debug, Clone)]
+pub struct TrollCapturePending {
+    game: Box<Thud>,
+    troll: Coord,
+}
+
+impl TrollCapturePending {
+    /// Get the square of the troll which has to make the capture
+    pub fn troll(&self) -> Coord {
+        self.troll
+    }
+
+    /// Find which dwarves the troll could capture, see
+    /// [`Thud::capture_options()`](struct.Thud.html#method.capture_options)
+    pub fn capture_options(&self) -> CaptureOptions {
+        self.game
+            .capture_options(self.troll)
+            .expect("the pending troll can capture")
+    }
+
+    /// Capture dwarves with the troll to finish the turn, see
+    /// [`Thud::troll_cap()`](struct.Thud.html#method.troll_cap)
+    pub fn capture(mut self, targets: Vec<Direction>) -> Result<Phase, Rejected<Self>> {
+        match self.game.troll_cap(self.troll, targets) {
+            Ok(()) => Ok(Phase::after(self.game)),
+            Err(error) => Err(Rejected { phase: self, error }),
+        }
+    }
+}
+
+in_progress!(TrollCapturePending, game);
+
+/// A game which has ended, see [`Phase`](enum.Phase.html)
+#[derive(Debug, Clone)]
+pub struct GameOver {
+    game: Box<Thud>,
+    end: EndState,
+}
+
+impl GameOver {
+    /// Get the game
+    pub fn game(&self) -> &Thud {
+        &self.game
+    }
+
+    /// Get the game back to use directly
+    pub fn into_game(self) -> Thud {
+        *self.game
+    }
+
+    /// Get the result
+    pub fn end(&self) -> EndState {
+        self.end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EndReason, Piece};
+
+    fn dwarf_turn(phase: Phase) -> DwarfTurn {
+        match phase {
+            Phase::Dwarf(turn) => turn,
+            other => panic!("expected a dwarf turn, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejected() {
+        let turn = dwarf_turn(Phase::new(Thud::new()).unwrap());
+        let rejected = turn.move_piece((6, 0).into(), (7, 7).into()).unwrap_err();
+        assert_eq!(
+            rejected.error,
+            ThudError::Occupied((7, 7).into(), Piece::Thudstone)
+        );
+
+        let troll = match rejected.phase.move_piece((6, 0).into(), (6, 5).into()) {
+            Ok(Phase::Troll(turn)) => turn,
+            other => panic!("expected a troll turn, got {:?}", other),
+        };
+        let pending = troll.attack((7, 6).into(), (7, 5).into()).unwrap();
+        assert_eq!(pending.troll(), (7, 5).into());
+        assert!(pending.capture_options().must_capture());
+
+        // A shove has to capture something
+        let rejected = pending.capture(vec![]).unwrap_err();
+        assert_eq!(rejected.error, ThudError::MustCapture((7, 5).into()));
+        let turn = dwarf_turn(rejected.phase.capture(vec![Direction::Left]).unwrap());
+        assert_eq!(turn.game().history().len(), 3);
+    }
+
+    #[test]
+    fn ended() {
+        let mut game = Thud::new();
+        game.offer_end().unwrap();
+        assert!(Phase::new(game.clone()).is_err());
+        game.decline_end(Player::Troll).unwrap();
+
+        let over = dwarf_turn(Phase::new(game).unwrap()).resign(Player::Dwarf);
+        assert_eq!(
+            over.end(),
+            EndState::Won(Player::Troll, EndReason::Resignation)
+        );
+        match Phase::new(over.into_game()) {
+            Ok(Phase::Ended(over)) => assert_eq!(over.end().reason(), EndReason::Resignation),
+            other => panic!("expected the game to be over, got {:?}", other),
+        }
+    }
+}
@@ -6,27 +6,19 @@ use serde::{Deserialize, Serialize};
 /// **Note**: Empty squares are modelled as `Piece`s too, to avoid the horror of `Option<Piece>`
 /// everywhere.
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
-#[derive(Debug, PartialEq, Copy, Clone)]
+#[cfg_attr(feature = "serialize", serde(rename_all = "snake_case"))]
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
 pub enum Piece {
-    #[cfg_attr(feature = "serialize", serde(rename = "dwarf"))]
     Dwarf,
-    #[cfg_attr(feature = "serialize", serde(rename = "troll"))]
     Troll,
-    #[cfg_attr(feature = "serialize", serde(rename = "thudstone"))]
     Thudstone,
-    #[cfg_attr(feature = "serialize", serde(rename = "empty"))]
+    #[default]
     Empty,
 }
 
-impl Default for Piece {
-    fn default() -> Self {
-        Self::Empty
-    }
-}
-
-#[cfg(feature = "ffi")]
 impl Piece {
-    pub fn into_int(&self) -> usize {
+    /// 2-bit code for the piece, used for hashing and binary encodings
+    pub(crate) fn code(&self) -> u8 {
         match self {
             Piece::Empty => 0,
             Piece::Dwarf => 1,
@@ -34,4 +26,14 @@ impl Piece {
             Piece::Thudstone => 3,
         }
     }
+
+    /// Inverse of `.code()`, ignoring all but the lowest 2 bits
+    pub(crate) fn from_code(code: u8) -> Self {
+        match code & 0b11 {
+            0 => Piece::Empty,
+            1 => Piece::Dwarf,
+            2 => Piece::Troll,
+            _ => Piece::Thudstone,
+        }
+    }
 }
@@ -0,0 +1,690 @@
+//! Line-based protocol for playing Thud engines, in the spirit of chess's UCI.
+//!
+//! A driver, such as an arena or a GUI, talks to an engine over a pair of streams, usually the
+//! engine's stdin and stdout, one message per line. The driver sends [`Command`s](enum.Command.html):
+//!
+//! - `newgame` to forget the previous game
+//! - `position startpos [<start>] [moves <action> ...]` or
+//!   `position fen <fen> [<start>] [moves <action> ...]` to set up the game, where `<fen>` is as
+//!   written by [`Thud::fen()`](../struct.Thud.html#method.fen) and each `<action>` is in the
+//!   notation of [`Action`](../enum.Action.html). `<start>` describes a game which didn't start
+//!   at the beginning of a turn or uses optional [`Rules`](../struct.Rules.html), with
+//!   `capture <square> [shoved]` if the troll on `<square>` has just moved or been shoved and
+//!   still has to capture, `offer` if the player to move has offered to end the game, and
+//!   `repetition <count>` and `movelimit <turns>` for the rules
+//! - `go [depth <plies>] [movetime <milliseconds>]` to ask for a turn for whoever has to act
+//! - `quit`
+//!
+//! The engine answers `go` with any number of `info <text>` lines, followed by a
+//! [`Reply::BestMove`](enum.Reply.html) of one line:
+//!
+//! - `bestmove <action> [<capture>]`, where a troll move or shove is followed by its capture so
+//!   the whole turn is given at once
+//! - `bestmove resign` to concede
+//! - `bestmove (none)` if there is nothing to be done
+//!
+//! Engines can be built around an [`Agent`](../trait.Agent.html) with [`Engine`](struct.Engine.html),
+//! and played with [`Driver`](struct.Driver.html). Agents have no way to be limited, so an
+//! `Engine` answers a `go` with limits by saying so in an `info string` line before playing
+//! without them.
+//!
+//! ```
+//! use thud::protocol::{Command, Engine, Reply};
+//! use thud::RandomAgent;
+//!
+//! let mut engine = Engine::new(RandomAgent::new(0));
+//! engine.handle("position startpos moves G1-G6".parse().unwrap());
+//! let replies = engine.handle("go".parse().unwrap());
+//! match &replies[..] {
+//!     [Reply::BestMove(turn)] => assert_eq!(turn.len(), 2),
+//!     _ => panic!("expected a best move"),
+//! }
+//! ```
+
+use crate::state::GameState;
+use crate::{Action, Agent, Coord, Rules, Thud, ThudError};
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// Reports a problem talking to an engine
+#[derive(Debug, Error)]
+pub enum ProtocolError {
+    #[error("Could not read message {0:?}")]
+    Malformed(String),
+    #[error("The other side closed the connection")]
+    Closed,
+    #[error("Ran out of time waiting for a reply")]
+    Timeout,
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// The `Termination` tags an arena gives games decided away from the board: an engine forfeiting
+/// by running out of time, sending something unreadable, crashing, taking an illegal action or
+/// stopping partway through its turn, and a draw after too many plies.
+///
+/// These can't be checked by replaying the game, so pass them to
+/// [`GameRecord::validate_trusting()`](../struct.GameRecord.html#method.validate_trusting).
+pub const ARENA_TERMINATIONS: [&str; 6] = [
+    "time_forfeit",
+    "malformed_reply",
+    "crash",
+    "illegal_action",
+    "incomplete_turn",
+    "max_plies",
+];
+
+/// How long an engine may think for, given with `go`
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+pub struct Limits {
+    /// Number of plies to search
+    pub depth: Option<u32>,
+    /// Time to think for
+    pub movetime: Option<Duration>,
+}
+
+/// A message from a driver to an engine
+#[derive(Debug, PartialEq, Clone)]
+pub enum Command {
+    NewGame,
+    /// Set up the game from a position written with [`Thud::fen()`](../struct.Thud.html#method.fen),
+    /// or the standard starting position if `fen` is `None`, then take `moves`
+    Position {
+        fen: Option<String>,
+        /// The troll which still has to capture to finish the first turn, and whether it was
+        /// shoved
+        capture: Option<(Coord, bool)>,
+        /// Whether the player to move has offered to end the game before the first action
+        offer: bool,
+        rules: Rules,
+        moves: Vec<Action>,
+    },
+    Go(Limits),
+    Quit,
+}
+
+/// A message from an engine to a driver
+#[derive(Debug, PartialEq, Clone)]
+pub enum Reply {
+    /// Free-form information about the search
+    Info(String),
+    /// The engine's turn, which is empty if there is nothing to be done
+    BestMove(Vec<Action>),
+    /// The engine concedes the game
+    Resign,
+}
+
+fn malformed(line: &str) -> ProtocolError {
+    ProtocolError::Malformed(line.to_string())
+}
+
+fn parse_actions<'a>(
+    line: &str,
+    actions: impl Iterator<Item = &'a str>,
+) -> Result<Vec<Action>, ProtocolError> {
+    actions
+        .map(|action| action.parse().map_err(|_| malformed(line)))
+        .collect()
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Command::NewGame => write!(f, "newgame"),
+            Command::Position {
+                fen,
+                capture,
+                offer,
+                rules,
+                moves,
+            } => {
+                match fen {
+                    Some(fen) => write!(f, "position fen {}", fen)?,
+                    None => write!(f, "position startpos")?,
+                }
+                if let Some((troll, shoved)) = capture {
+                    write!(f, " capture {}", troll)?;
+                    if *shoved {
+                        write!(f, " shoved")?;
+                    }
+                }
+                if *offer {
+                    write!(f, " offer")?;
+                }
+                if let Some(repetition) = rules.repetition {
+                    write!(f, " repetition {}", repetition)?;
+                }
+                if let Some(move_limit) = rules.move_limit {
+                    write!(f, " movelimit {}", move_limit)?;
+                }
+                if !moves.is_empty() {
+                    write!(f, " moves")?;
+                    for action in moves {
+                        write!(f, " {}", action)?;
+                    }
+                }
+                Ok(())
+            }
+            Command::Go(limits) => {
+                write!(f, "go")?;
+                if let Some(depth) = limits.depth {
+                    write!(f, " depth {}", depth)?;
+                }
+                if let Some(movetime) = limits.movetime {
+                    write!(f, " movetime {}", movetime.as_millis())?;
+                }
+                Ok(())
+            }
+            Command::Quit => write!(f, "quit"),
+        }
+    }
+}
+
+impl FromStr for Command {
+    type Err = ProtocolError;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words[..] {
+            ["newgame"] => Ok(Command::NewGame),
+            ["quit"] => Ok(Command::Quit),
+            ["go", ref options @ ..] => {
+                let mut limits = Limits::default();
+                for pair in options.chunks(2) {
+                    match *pair {
+                        ["depth", depth] => {
+                            limits.depth = Some(depth.parse().map_err(|_| malformed(line))?)
+                        }
+                        ["movetime", millis] => {
+                            let millis = millis.parse().map_err(|_| malformed(line))?;
+                            limits.movetime = Some(Duration::from_millis(millis))
+                        }
+                        _ => return Err(malformed(line)),
+                    }
+                }
+                Ok(Command::Go(limits))
+            }
+            ["position", ref rest @ ..] => {
+                let (fen, mut rest) = match rest {
+                    ["startpos", rest @ ..] => (None, rest),
+                    ["fen", rows, side, rest @ ..] => (Some(format!("{} {}", rows, side)), rest),
+                    _ => return Err(malformed(line)),
+                };
+                let mut capture = None;
+                let mut offer = false;
+                let mut rules = Rules::default();
+                let number = |value: &str| value.parse().map_err(|_| malformed(line));
+                let moves = loop {
+                    match rest {
+                        [] => break Vec::new(),
+                        ["moves", moves @ ..] => break parse_actions(line, moves.iter().copied())?,
+                        ["capture", troll, tail @ ..] => {
+                            let troll = troll.parse().map_err(|_| malformed(line))?;
+                            let (shoved, tail) = match tail {
+                                ["shoved", tail @ ..] => (true, tail),
+                                _ => (false, tail),
+                            };
+                            capture = Some((troll, shoved));
+                            rest = tail;
+                        }
+                        ["offer", tail @ ..] => {
+                            offer = true;
+                            rest = tail;
+                        }
+                        ["repetition", count, tail @ ..] => {
+                            rules.repetition = Some(number(count)?);
+                            rest = tail;
+                        }
+                        ["movelimit", turns, tail @ ..] => {
+                            rules.move_limit = Some(number(turns)?);
+                            rest = tail;
+                        }
+                        _ => return Err(malformed(line)),
+                    }
+                };
+                Ok(Command::Position {
+                    fen,
+                    capture,
+                    offer,
+                    rules,
+                    moves,
+                })
+            }
+            _ => Err(malformed(line)),
+        }
+    }
+}
+
+impl fmt::Display for Reply {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reply::Info(text) => write!(f, "info {}", text),
+            Reply::BestMove(actions) if actions.is_empty() => write!(f, "bestmove (none)"),
+            Reply::BestMove(actions) => {
+                write!(f, "bestmove")?;
+                for action in actions {
+                    write!(f, " {}", action)?;
+                }
+                Ok(())
+            }
+            Reply::Resign => write!(f, "bestmove resign"),
+        }
+    }
+}
+
+impl FromStr for Reply {
+    type Err = ProtocolError;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let line = line.trim();
+        if let Some(text) = line.strip_prefix("info ") {
+            return Ok(Reply::Info(text.trim().to_string()));
+        }
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words[..] {
+            ["bestmove", "(none)"] => Ok(Reply::BestMove(Vec::new())),
+            ["bestmove", "resign"] => Ok(Reply::Resign),
+            ["bestmove", ref actions @ ..] if !actions.is_empty() && actions.len() <= 2 => Ok(
+                Reply::BestMove(parse_actions(line, actions.iter().copied())?),
+            ),
+            _ => Err(malformed(line)),
+        }
+    }
+}
+
+/// Set up the game a `position` command starts from
+fn start(
+    fen: Option<&str>,
+    capture: Option<(Coord, bool)>,
+    offer: bool,
+    rules: Rules,
+) -> Result<Thud, ThudError> {
+    let (board, to_move) = match fen {
+        Some(fen) => Thud::from_fen(fen)?.start(),
+        None => Thud::new().start(),
+    };
+    let mut game = Thud::from_board_with_rules(board, to_move, rules)?;
+    let state = match capture {
+        Some((troll, shoved)) => GameState::PostTrollMove { shoved, troll },
+        None => GameState::Nominal(to_move),
+    };
+    game.set_start_phase(state, if offer { Some(to_move) } else { None })?;
+    Ok(game)
+}
+
+/// Engine side of the protocol, choosing turns with an [`Agent`](../trait.Agent.html)
+///
+/// The agent is given the whole game, and asked again for a capture after a troll move or
+/// shove. Agents can't be given search limits, so a `go` with a depth or move time is answered
+/// with an `info string` saying they aren't supported, then played as if they weren't given.
+pub struct Engine<A> {
+    agent: A,
+    game: Thud,
+}
+
+impl<A: Agent> Engine<A> {
+    /// Make an engine which plays with `agent`
+    pub fn new(agent: A) -> Self {
+        Engine {
+            agent,
+            game: Thud::new(),
+        }
+    }
+
+    /// Respond to a command, giving the replies to send back.
+    ///
+    /// Problems setting up a position are reported as `info string` replies.
+    pub fn handle(&mut self, command: Command) -> Vec<Reply> {
+        match command {
+            Command::NewGame => {
+                self.game = Thud::new();
+                Vec::new()
+            }
+            Command::Position {
+                fen,
+                capture,
+                offer,
+                rules,
+                moves,
+            } => {
+                let mut game = match start(fen.as_deref(), capture, offer, rules) {
+                    Ok(game) => game,
+                    Err(error) => return vec![Reply::Info(format!("string {}", error))],
+                };
+                for (ply, action) in moves.into_iter().enumerate() {
+                    if let Err(error) = game.apply(action) {
+                        return vec![Reply::Info(format!("string move {}: {}", ply + 1, error))];
+                    }
+                }
+                self.game = game;
+                Vec::new()
+            }
+            Command::Go(limits) => {
+                let mut replies = Vec::new();
+                if limits != Limits::default() {
+                    replies.push(Reply::Info(
+                        "string search limits are not supported, ignoring them".to_string(),
+                    ));
+                }
+                let mut game = self.game.clone();
+                let mut turn = Vec::new();
+                while let Some(action) = self.agent.choose(&game) {
+                    if let Action::Resign(_) = action {
+                        replies.push(Reply::Resign);
+                        return replies;
+                    }
+                    if game.apply(action.clone()).is_err() {
+                        break;
+                    }
+                    turn.push(action);
+                    if game.pending_capture().is_none() {
+                        break;
+                    }
+                }
+                replies.push(Reply::BestMove(turn));
+                replies
+            }
+            Command::Quit => Vec::new(),
+        }
+    }
+
+    /// Answer commands from `input` on `output` until `quit` is received or `input` runs out.
+    pub fn run(&mut self, input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let replies = match line.parse() {
+                Ok(Command::Quit) => return Ok(()),
+                Ok(command) => self.handle(command),
+                Err(error) => vec![Reply::Info(format!("string {}", error))],
+            };
+            for reply in replies {
+                writeln!(output, "{}", reply)?;
+            }
+            output.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// Driver side of the protocol, for playing an engine
+///
+/// Replies are read on a background thread so that [`.go()`](#method.go) can give up on an
+/// engine which takes too long.
+pub struct Driver<W> {
+    writer: W,
+    lines: Receiver<io::Result<String>>,
+    info: Vec<String>,
+}
+
+impl<W: Write> Driver<W> {
+    /// Talk to an engine which reads commands from `writer` and replies on `reader`
+    pub fn new<R: BufRead + Send + 'static>(reader: R, writer: W) -> Self {
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in reader.lines() {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Driver {
+            writer,
+            lines,
+            info: Vec::new(),
+        }
+    }
+
+    /// Send any command to the engine
+    pub fn send(&mut self, command: &Command) -> Result<(), ProtocolError> {
+        writeln!(self.writer, "{}", command)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Tell the engine a new game is starting
+    pub fn new_game(&mut self) -> Result<(), ProtocolError> {
+        self.send(&Command::NewGame)
+    }
+
+    /// Tell the engine about `game`, as how it started, with its rules, and its
+    /// [history](../struct.Thud.html#method.history)
+    pub fn position(&mut self, game: &Thud) -> Result<(), ProtocolError> {
+        let (board, to_move) = game.start();
+        let fen = Thud::from_board(board, to_move)
+            .map(|start| start.fen())
+            .map_err(|error| ProtocolError::Malformed(error.to_string()))?;
+        let (state, offer) = game.start_phase();
+        let capture = match state {
+            GameState::PostTrollMove { shoved, troll } => Some((troll, shoved)),
+            _ => None,
+        };
+        self.send(&Command::Position {
+            fen: Some(fen),
+            capture,
+            offer: offer.is_some(),
+            rules: game.rules(),
+            moves: game.history().to_vec(),
+        })
+    }
+
+    /// Ask the engine for a turn, waiting up to `timeout` for its
+    /// [`BestMove`](enum.Reply.html) or [`Resign`](enum.Reply.html).
+    ///
+    /// The text of any `info` lines sent beforehand is kept in [`.info()`](#method.info).
+    pub fn go(
+        &mut self,
+        limits: Limits,
+        timeout: Option<Duration>,
+    ) -> Result<Reply, ProtocolError> {
+        self.send(&Command::Go(limits))?;
+        self.info.clear();
+
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            let line = match deadline {
+                Some(deadline) => self
+                    .lines
+                    .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                    .map_err(|error| match error {
+                        RecvTimeoutError::Timeout => ProtocolError::Timeout,
+                        RecvTimeoutError::Disconnected => ProtocolError::Closed,
+                    })?,
+                None => self.lines.recv().map_err(|_| ProtocolError::Closed)?,
+            }?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match line.parse()? {
+                Reply::Info(text) => self.info.push(text),
+                reply => return Ok(reply),
+            }
+        }
+    }
+
+    /// Get the text of the `info` lines sent during the last [`.go()`](#method.go)
+    pub fn info(&self) -> &[String] {
+        &self.info
+    }
+
+    /// Tell the engine to quit
+    pub fn quit(mut self) -> Result<(), ProtocolError> {
+        self.send(&Command::Quit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Player, RandomAgent};
+    use test_case::test_case;
+
+    #[test_case("newgame")]
+    #[test_case("quit")]
+    #[test_case("go")]
+    #[test_case("go depth 3 movetime 250")]
+    #[test_case("position startpos")]
+    #[test_case("position startpos moves G1-G6 H9-H10 H10*")]
+    #[test_case("position fen 15/15/15/15/15/15/15/7O7/15/15/15/15/15/15/15 t moves offer")]
+    #[test_case("position startpos offer repetition 3 movelimit 40 moves accept")]
+    #[test_case("position fen 15/15/15/15/15/15/15/6TO7/15/15/15/15/15/15/15 t capture G8 shoved")]
+    fn command_round_trip(line: &str) {
+        let command: Command = line.parse().unwrap();
+        assert_eq!(command.to_string(), line);
+    }
+
+    #[test_case("info depth 2 score 4" => Reply::Info("depth 2 score 4".to_string()))]
+    #[test_case("bestmove (none)" => Reply::BestMove(vec![]))]
+    #[test_case("bestmove resign" => Reply::Resign)]
+    #[test_case("bestmove H9-H10 H10*" => Reply::BestMove(vec![
+        Action::Move((7, 8).into(), (7, 9).into()),
+        Action::Capture((7, 9).into(), vec![]),
+    ]))]
+    fn reply(line: &str) -> Reply {
+        let reply: Reply = line.parse().unwrap();
+        assert_eq!(reply.to_string(), line);
+        reply
+    }
+
+    #[test_case("go depth"; "missing value")]
+    #[test_case("go nodes 4"; "unknown limit")]
+    #[test_case("position startpos G1-G6"; "missing moves")]
+    #[test_case("position startpos moves G1"; "bad action")]
+    #[test_case("position startpos capture"; "missing square")]
+    #[test_case("position startpos repetition x"; "bad rule")]
+    #[test_case("bestmove"; "missing move")]
+    fn malformed_lines(line: &str) {
+        assert!(matches!(
+            line.parse::<Command>(),
+            Err(ProtocolError::Malformed(_))
+        ));
+        assert!(matches!(
+            line.parse::<Reply>(),
+            Err(ProtocolError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn engine_reports_bad_position() {
+        let mut engine = Engine::new(RandomAgent::new(0));
+        let replies = engine.handle("position startpos moves H9-H10".parse().unwrap());
+        assert_eq!(
+            replies,
+            vec![Reply::Info(
+                "string move 1: Expected a Dwarf at H9 but found Troll".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn engine_reports_limits() {
+        let mut engine = Engine::new(RandomAgent::new(0));
+        let replies = engine.handle("go depth 3 movetime 250".parse().unwrap());
+        match &replies[..] {
+            [Reply::Info(text), Reply::BestMove(turn)] => {
+                assert_eq!(
+                    text,
+                    "string search limits are not supported, ignoring them"
+                );
+                assert_eq!(turn.len(), 1);
+            }
+            other => panic!("unexpected replies {:?}", other),
+        }
+    }
+
+    #[test]
+    fn driver_plays_engine() {
+        let (engine_input, driver_output) = io::pipe().unwrap();
+        let (driver_input, engine_output) = io::pipe().unwrap();
+        let engine = thread::spawn(move || {
+            Engine::new(RandomAgent::new(5))
+                .run(io::BufReader::new(engine_input), engine_output)
+                .unwrap()
+        });
+
+        let mut driver = Driver::new(io::BufReader::new(driver_input), driver_output);
+        let mut game = Thud::new();
+        driver.new_game().unwrap();
+        for _ in 0..6 {
+            driver.position(&game).unwrap();
+            let player = game.turn();
+            match driver.go(Limits::default(), Some(Duration::from_secs(10))) {
+                Ok(Reply::BestMove(turn)) => {
+                    for action in turn {
+                        game.apply(action).unwrap();
+                    }
+                }
+                other => panic!("unexpected reply {:?}", other),
+            }
+            assert_ne!(game.turn(), player);
+        }
+        driver.quit().unwrap();
+        engine.join().unwrap();
+        assert_eq!(game.turn(), Some(Player::Dwarf));
+    }
+
+    #[test]
+    fn driver_sends_start() {
+        let mut shoved = Thud::from_board_with_rules(
+            crate::Board::fresh(),
+            Player::Troll,
+            Rules {
+                repetition: Some(3),
+                move_limit: Some(40),
+            },
+        )
+        .unwrap();
+        shoved
+            .set_start_phase(
+                GameState::PostTrollMove {
+                    shoved: true,
+                    troll: (7, 8).into(),
+                },
+                None,
+            )
+            .unwrap();
+        let mut offered = Thud::new();
+        offered
+            .set_start_phase(GameState::Nominal(Player::Dwarf), Some(Player::Dwarf))
+            .unwrap();
+        offered.decline_end(Player::Troll).unwrap();
+
+        for game in [shoved, offered] {
+            let (engine_input, driver_output) = io::pipe().unwrap();
+            let (driver_input, _engine_output) = io::pipe().unwrap();
+            let mut driver = Driver::new(io::BufReader::new(driver_input), driver_output);
+            driver.position(&game).unwrap();
+            let mut line = String::new();
+            io::BufReader::new(engine_input)
+                .read_line(&mut line)
+                .unwrap();
+
+            let mut engine = Engine::new(RandomAgent::new(0));
+            assert_eq!(engine.handle(line.parse().unwrap()), vec![]);
+            assert_eq!(engine.game.start(), game.start());
+            assert_eq!(engine.game.start_phase(), game.start_phase());
+            assert_eq!(engine.game.rules(), game.rules());
+            assert_eq!(engine.game.history(), game.history());
+            assert_eq!(engine.game.pending_capture(), game.pending_capture());
+            assert_eq!(engine.game.end_offer(), game.end_offer());
+        }
+    }
+
+    #[test]
+    fn driver_times_out() {
+        let (_engine_input, driver_output) = io::pipe().unwrap();
+        let (driver_input, _engine_output) = io::pipe().unwrap();
+        let mut driver = Driver::new(io::BufReader::new(driver_input), driver_output);
+        assert!(matches!(
+            driver.go(Limits::default(), Some(Duration::from_millis(50))),
+            Err(ProtocolError::Timeout)
+        ));
+    }
+}
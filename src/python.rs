@@ -0,0 +1,346 @@
+//! Python bindings, enabled with the `python` feature.
+//!
+//! Build the `thud` Python module with [`maturin`](https://www.maturin.rs/), eg. `maturin develop
+//! --release`, using the settings in `pyproject.toml`.
+//!
+//! Squares are given as zero-based `(x, y)` tuples as with
+//! [`Coord::zero_based()`](../struct.Coord.html#method.zero_based). Players, end reasons and
+//! directions are given as lowercase strings, eg. `"troll"` or `"up_left"`. Boards are exported
+//! to NumPy as `uint8` arrays of piece codes indexed by `[x, y]`, where `0` is empty, `1` a
+//! dwarf, `2` a troll and `3` the thudstone.
+//!
+//! Actions are tuples naming the [`Action`](../enum.Action.html) variant followed by its
+//! fields:
+//!
+//! ```python
+//! ("move", (6, 0), (6, 5))
+//! ("attack", (6, 0), (6, 5))
+//! ("capture", (9, 7), ["up", "down_left"])
+//! ("resign", "dwarf")
+//! ("offer_end",)
+//! ("accept_end",)
+//! ("decline_end",)
+//! ```
+//!
+//! To amortise the cost of calling into Rust, [`step_batch()`](fn.step_batch.html) takes an
+//! action in each of many games and [`boards()`](fn.boards.html) exports many boards as one
+//! array.
+
+use crate::record::reason_name;
+use crate::{Action, Board, Coord, Direction, EndState, Player, Thud};
+use numpy::{PyArray1, PyArrayMethods};
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyTuple;
+
+create_exception!(
+    thud,
+    ThudError,
+    PyException,
+    "An error from the thud library"
+);
+
+impl From<crate::ThudError> for PyErr {
+    fn from(error: crate::ThudError) -> Self {
+        ThudError::new_err(error.to_string())
+    }
+}
+
+/// Name of a [`Player`](../enum.Player.html)
+fn player_name(player: Player) -> &'static str {
+    match player {
+        Player::Dwarf => "dwarf",
+        Player::Troll => "troll",
+    }
+}
+
+fn player_from_name(name: &str) -> PyResult<Player> {
+    match name {
+        "dwarf" => Ok(Player::Dwarf),
+        "troll" => Ok(Player::Troll),
+        _ => Err(PyValueError::new_err(format!("Unknown player {:?}", name))),
+    }
+}
+
+fn direction_name(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Up => "up",
+        Direction::UpRight => "up_right",
+        Direction::Right => "right",
+        Direction::DownRight => "down_right",
+        Direction::Down => "down",
+        Direction::DownLeft => "down_left",
+        Direction::Left => "left",
+        Direction::UpLeft => "up_left",
+    }
+}
+
+fn direction_from_name(name: &str) -> PyResult<Direction> {
+    Direction::all()
+        .into_iter()
+        .find(|&direction| direction_name(direction) == name)
+        .ok_or_else(|| PyValueError::new_err(format!("Unknown direction {:?}", name)))
+}
+
+/// Outcome of a game as `(winner, reason)`, where `winner` is `None` for a draw
+fn outcome(end: EndState) -> (Option<&'static str>, &'static str) {
+    match end {
+        EndState::Won(player, reason) => (Some(player_name(player)), reason_name(reason)),
+        EndState::Draw(reason) => (None, reason_name(reason)),
+    }
+}
+
+fn square((x, y): (usize, usize)) -> PyResult<Coord> {
+    Ok(Coord::zero_based(x, y)?)
+}
+
+/// Piece codes of every square, indexed by `x * 15 + y`
+fn piece_codes(board: &Board) -> Vec<u8> {
+    board
+        .full_raw()
+        .iter()
+        .flat_map(|column| column.iter().map(|piece| piece.code()))
+        .collect()
+}
+
+fn action_from_py(action: &Bound<'_, PyAny>) -> PyResult<Action> {
+    let item = |index| action.get_item(index);
+    let kind: String = item(0)?.extract()?;
+    Ok(match kind.as_str() {
+        "move" => Action::Move(square(item(1)?.extract()?)?, square(item(2)?.extract()?)?),
+        "attack" => Action::Attack(square(item(1)?.extract()?)?, square(item(2)?.extract()?)?),
+        "capture" => Action::Capture(
+            square(item(1)?.extract()?)?,
+            item(2)?
+                .extract::<Vec<String>>()?
+                .iter()
+                .map(|name| direction_from_name(name))
+                .collect::<PyResult<_>>()?,
+        ),
+        "resign" => Action::Resign(player_from_name(&item(1)?.extract::<String>()?)?),
+        "offer_end" => Action::OfferEnd,
+        "accept_end" => Action::AcceptEnd,
+        "decline_end" => Action::DeclineEnd,
+        _ => return Err(PyValueError::new_err(format!("Unknown action {:?}", kind))),
+    })
+}
+
+fn action_into_py<'py>(py: Python<'py>, action: &Action) -> PyResult<Bound<'py, PyTuple>> {
+    let value = |coord: &Coord| coord.value();
+    match action {
+        Action::Move(src, target) => ("move", value(src), value(target)).into_pyobject(py),
+        Action::Attack(src, target) => ("attack", value(src), value(target)).into_pyobject(py),
+        Action::Capture(troll, targets) => (
+            "capture",
+            value(troll),
+            targets
+                .iter()
+                .map(|&direction| direction_name(direction))
+                .collect::<Vec<_>>(),
+        )
+            .into_pyobject(py),
+        Action::Resign(player) => ("resign", player_name(*player)).into_pyobject(py),
+        Action::OfferEnd => ("offer_end",).into_pyobject(py),
+        Action::AcceptEnd => ("accept_end",).into_pyobject(py),
+        Action::DeclineEnd => ("decline_end",).into_pyobject(py),
+    }
+}
+
+/// A configuration of pieces on the board, see [`Board`](../struct.Board.html)
+#[pyclass(name = "Board", module = "thud", frozen)]
+pub struct PyBoard(Board);
+
+#[pymethods]
+impl PyBoard {
+    /// Get a board with the pieces in their starting positions
+    #[staticmethod]
+    fn fresh() -> Self {
+        PyBoard(Board::fresh())
+    }
+
+    /// Get the squares the piece at a square can move to or attack
+    fn available_moves(&self, loc: (usize, usize)) -> PyResult<Vec<(usize, usize)>> {
+        Ok(self
+            .0
+            .available_moves(square(loc)?)
+            .iter()
+            .map(Coord::value)
+            .collect())
+    }
+
+    /// Get the scores as `(dwarf, troll)`
+    fn score(&self) -> (usize, usize) {
+        self.0.score()
+    }
+
+    /// Find the outcome if neither player can move, as `(winner, reason)`
+    fn winner(&self) -> Option<(Option<&'static str>, &'static str)> {
+        self.0.winner().map(outcome)
+    }
+
+    /// Get the board as a 15x15 NumPy array of piece codes
+    fn to_numpy<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        Ok(PyArray1::from_vec(py, piece_codes(&self.0))
+            .reshape([15, 15])?
+            .into_any())
+    }
+}
+
+/// A game of Thud, see [`Thud`](../struct.Thud.html)
+#[pyclass(name = "Thud", module = "thud")]
+#[derive(Clone)]
+pub struct PyThud(Thud);
+
+#[pymethods]
+impl PyThud {
+    /// Start a new game
+    #[new]
+    fn new() -> Self {
+        PyThud(Thud::new())
+    }
+
+    /// Get an independent copy of the game
+    fn copy(&self) -> Self {
+        self.clone()
+    }
+
+    /// Find whose turn it is, or `None` if the game is over
+    fn turn(&self) -> Option<&'static str> {
+        self.0.turn().map(player_name)
+    }
+
+    /// Get a copy of the current board
+    fn board(&self) -> PyBoard {
+        PyBoard(self.0.board())
+    }
+
+    /// Get the scores as `(dwarf, troll)`
+    fn score(&self) -> (usize, usize) {
+        self.0.score()
+    }
+
+    /// Find the outcome of the game as `(winner, reason)`, or `None` if it is still going
+    fn winner(&mut self) -> Option<(Option<&'static str>, &'static str)> {
+        self.0.winner().map(outcome)
+    }
+
+    /// Move a piece of the player whose turn it is
+    fn move_piece(&mut self, src: (usize, usize), target: (usize, usize)) -> PyResult<()> {
+        Ok(self.0.move_piece(square(src)?, square(target)?)?)
+    }
+
+    /// Hurl a dwarf or shove a troll, depending on whose turn it is
+    fn attack(&mut self, src: (usize, usize), target: (usize, usize)) -> PyResult<()> {
+        Ok(self.0.attack(square(src)?, square(target)?)?)
+    }
+
+    /// Capture dwarves in a list of directions with the troll that just moved
+    fn troll_cap(&mut self, troll: (usize, usize), targets: Vec<String>) -> PyResult<()> {
+        let targets = targets
+            .iter()
+            .map(|name| direction_from_name(name))
+            .collect::<PyResult<_>>()?;
+        Ok(self.0.troll_cap(square(troll)?, targets)?)
+    }
+
+    /// Take an action given as a tuple
+    fn apply(&mut self, action: &Bound<'_, PyAny>) -> PyResult<()> {
+        Ok(self.0.apply(action_from_py(action)?)?)
+    }
+
+    /// Get every action that can be taken, as tuples
+    fn legal_actions<'py>(&self, py: Python<'py>) -> PyResult<Vec<Bound<'py, PyTuple>>> {
+        self.0
+            .legal_actions()
+            .iter()
+            .map(|action| action_into_py(py, action))
+            .collect()
+    }
+
+    /// Get the actions taken so far, oldest first, as tuples
+    fn history<'py>(&self, py: Python<'py>) -> PyResult<Vec<Bound<'py, PyTuple>>> {
+        self.0
+            .history()
+            .iter()
+            .map(|action| action_into_py(py, action))
+            .collect()
+    }
+}
+
+/// Take one action in each of a list of games.
+///
+/// Returns a list holding `None` for each action that was taken, or the error message if it
+/// couldn't be, so one bad action doesn't stop the rest of the batch.
+#[pyfunction]
+pub fn step_batch(
+    mut games: Vec<PyRefMut<'_, PyThud>>,
+    actions: Vec<Bound<'_, PyAny>>,
+) -> PyResult<Vec<Option<String>>> {
+    if games.len() != actions.len() {
+        return Err(PyValueError::new_err(format!(
+            "Got {} games but {} actions",
+            games.len(),
+            actions.len()
+        )));
+    }
+
+    Ok(games
+        .iter_mut()
+        .zip(&actions)
+        .map(|(game, action)| {
+            action_from_py(action)
+                .and_then(|action| Ok(game.0.apply(action)?))
+                .err()
+                .map(|error| error.to_string())
+        })
+        .collect())
+}
+
+/// Get the boards of a list of games as one `(len(games), 15, 15)` NumPy array of piece codes
+#[pyfunction]
+pub fn boards<'py>(py: Python<'py>, games: Vec<PyRef<'py, PyThud>>) -> PyResult<Bound<'py, PyAny>> {
+    let codes = games
+        .iter()
+        .flat_map(|game| piece_codes(&game.0.board()))
+        .collect();
+    Ok(PyArray1::from_vec(py, codes)
+        .reshape([games.len(), 15, 15])?
+        .into_any())
+}
+
+#[pymodule]
+#[pyo3(name = "thud")]
+fn thud_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("ThudError", m.py().get_type::<ThudError>())?;
+    m.add_class::<PyBoard>()?;
+    m.add_class::<PyThud>()?;
+    m.add_function(wrap_pyfunction!(step_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(boards, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn piece_codes_layout() {
+        let codes = piece_codes(&Board::fresh());
+        assert_eq!(codes.len(), 225);
+        assert_eq!(codes[7 * 15 + 7], 3);
+        assert_eq!(codes[6 * 15 + 6], 2);
+        assert_eq!(codes[6 * 15], 1);
+        assert_eq!(codes[0], 0);
+    }
+
+    #[test]
+    fn direction_names() {
+        for direction in Direction::all() {
+            assert_eq!(
+                direction_from_name(direction_name(direction)).unwrap(),
+                direction
+            );
+        }
+    }
+}
@@ -0,0 +1,669 @@
+use crate::clock::to_act;
+use crate::{Action, EndReason, EndState, Mark, Player, Rules, Thud, ThudError};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// How a recorded game finished, as given by its `Result` tag
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Outcome {
+    Won(Player),
+    Draw,
+}
+
+impl From<EndState> for Outcome {
+    fn from(end: EndState) -> Self {
+        match end {
+            EndState::Won(player, _) => Outcome::Won(player),
+            EndState::Draw(_) => Outcome::Draw,
+        }
+    }
+}
+
+/// Get the name of `reason` as written in a `Termination` tag
+pub(crate) fn reason_name(reason: EndReason) -> &'static str {
+    match reason {
+        EndReason::NoMoves => "no_moves",
+        EndReason::Agreement => "agreement",
+        EndReason::Resignation => "resignation",
+        EndReason::Repetition => "repetition",
+        EndReason::MoveLimit => "move_limit",
+        EndReason::OutOfTime => "out_of_time",
+    }
+}
+
+/// The reasons a game can end which can be seen by replaying it
+const REPLAYABLE_ENDS: [EndReason; 5] = [
+    EndReason::NoMoves,
+    EndReason::Agreement,
+    EndReason::Resignation,
+    EndReason::Repetition,
+    EndReason::MoveLimit,
+];
+
+/// Reports a problem found by [`GameRecord::validate()`](struct.GameRecord.html#method.validate)
+#[derive(Debug, PartialEq, Clone, Error)]
+pub enum ValidationError {
+    #[error("A tag could not be read: {0}")]
+    Unreadable(ThudError),
+    #[error("Action {ply} ({action}) is illegal: {error}")]
+    Illegal {
+        ply: usize,
+        action: Action,
+        error: ThudError,
+    },
+    #[error("The record claims a result of {claimed:?} but replaying it gives {actual:?}")]
+    ResultMismatch {
+        claimed: Option<Outcome>,
+        actual: Option<EndState>,
+    },
+    #[error("The record claims the game ended by {claimed} but replaying it gives {actual:?}")]
+    TerminationMismatch {
+        claimed: String,
+        actual: Option<EndReason>,
+    },
+}
+
+/// A game written down as text, for keeping and sharing
+///
+/// The format is modelled on chess's PGN. A record starts with tags, one per line, in the form
+/// `[Name "value"]`, with `\"` and `\\` escaping quotes and backslashes in the value. After a
+/// blank line come the [actions](enum.Action.html) in their notation, separated by whitespace.
+///
+/// Records may also hold the annotations and variations of a [`GameTree`](struct.GameTree.html);
+/// these are skipped when reading a `GameRecord`, which keeps only the main line.
+///
+/// These tags have a meaning:
+///
+/// - `FEN`: the starting position as written by [`Thud::fen()`](struct.Thud.html#method.fen),
+///   if it isn't the standard one
+/// - `Repetition` and `MoveLimit`: the optional [`Rules`](struct.Rules.html) in play
+/// - `Result`: `dwarf` or `troll` for the winner, `draw`, or `*` if the game is unfinished
+/// - `Termination`: why the game ended
+///
+/// ```
+/// use thud::{GameRecord, Outcome, Player};
+///
+/// let record: GameRecord = "[Dwarf \"me\"]\n[Result \"troll\"]\n\nG1-G6 H9-H10 H10* resign dwarf\n"
+///     .parse()
+///     .unwrap();
+/// assert_eq!(record.tag("Dwarf"), Some("me"));
+/// assert_eq!(record.outcome(), Some(Outcome::Won(Player::Troll)));
+/// assert_eq!(record.replay().unwrap().history().len(), 4);
+/// ```
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct GameRecord {
+    /// Tag names and values, in the order they are written
+    pub tags: Vec<(String, String)>,
+    /// Every action taken in the game, oldest first
+    pub actions: Vec<Action>,
+}
+
+impl GameRecord {
+    /// Make an empty record
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record `game` so far, with its starting position, rules and any result
+    pub fn from_game(game: &Thud) -> Self {
+        let mut record = Self::new();
+        let (board, to_move) = game.start();
+        if (board, to_move) != Thud::new().start() {
+            if let Ok(start) = Thud::from_board(board, to_move) {
+                record.set_tag("FEN", start.fen());
+            }
+        }
+        let rules = game.rules();
+        if let Some(repetition) = rules.repetition {
+            record.set_tag("Repetition", repetition.to_string());
+        }
+        if let Some(move_limit) = rules.move_limit {
+            record.set_tag("MoveLimit", move_limit.to_string());
+        }
+
+        match game.clone().winner() {
+            Some(end) => {
+                record.set_outcome(Some(end.into()));
+                record.set_tag("Termination", reason_name(end.reason()));
+            }
+            None => record.set_outcome(None),
+        }
+        record.actions = game.history().to_vec();
+        record
+    }
+
+    /// Get the value of the tag called `name`
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(other, _)| other == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Set the tag called `name`, replacing any value it had
+    pub fn set_tag(&mut self, name: &str, value: impl Into<String>) {
+        let value = value.into();
+        match self.tags.iter_mut().find(|(other, _)| other == name) {
+            Some((_, old)) => *old = value,
+            None => self.tags.push((name.to_string(), value)),
+        }
+    }
+
+    /// Get the result given in the `Result` tag, or `None` if it is missing or the game is
+    /// unfinished
+    pub fn outcome(&self) -> Option<Outcome> {
+        match self.tag("Result")? {
+            "dwarf" => Some(Outcome::Won(Player::Dwarf)),
+            "troll" => Some(Outcome::Won(Player::Troll)),
+            "draw" => Some(Outcome::Draw),
+            _ => None,
+        }
+    }
+
+    /// Set the `Result` tag, with `None` for an unfinished game
+    pub fn set_outcome(&mut self, outcome: Option<Outcome>) {
+        let value = match outcome {
+            Some(Outcome::Won(Player::Dwarf)) => "dwarf",
+            Some(Outcome::Won(Player::Troll)) => "troll",
+            Some(Outcome::Draw) => "draw",
+            None => "*",
+        };
+        self.set_tag("Result", value);
+    }
+
+    /// Set up the game as it was before any actions, from the `FEN`, `Repetition` and
+    /// `MoveLimit` tags.
+    ///
+    /// Returns [`Err(ThudError::InvalidNotation)`](enum.ThudError.html) if a tag can't be read,
+    /// or the errors of [`Thud::from_fen()`](struct.Thud.html#method.from_fen).
+    pub fn start(&self) -> Result<Thud, ThudError> {
+        let number = |name| {
+            self.tag(name)
+                .map(|value| value.parse().map_err(|_| ThudError::InvalidNotation))
+                .transpose()
+        };
+        let rules = Rules {
+            repetition: number("Repetition")?,
+            move_limit: number("MoveLimit")?,
+        };
+        match self.tag("FEN") {
+            Some(fen) => {
+                let (board, to_move) = Thud::from_fen(fen)?.start();
+                Thud::from_board_with_rules(board, to_move, rules)
+            }
+            None => Thud::with_rules(rules),
+        }
+    }
+
+    /// Play the recorded actions from the [start](#method.start), passing on the first error.
+    ///
+    /// If the `Termination` tag is `out_of_time` and the actions leave the game unfinished, the
+    /// player who was to act loses on time.
+    pub fn replay(&self) -> Result<Thud, ThudError> {
+        let mut game = self.start()?;
+        for action in &self.actions {
+            game.apply(action.clone())?;
+        }
+        if self.tag("Termination") == Some("out_of_time") {
+            if let Some(player) = to_act(&game) {
+                game.out_of_time(player);
+            }
+        }
+        Ok(game)
+    }
+
+    /// Replay the record, checking every action and any claimed result, and giving the game
+    /// reached if all is well.
+    ///
+    /// The `Result` tag, if there is one, has to match how the replayed game ended, and so does
+    /// the `Termination` tag. A result can also be claimed for a game its actions leave
+    /// unfinished if the `Termination` tag is `out_of_time`, when the loser has to be the player
+    /// who was to act. Any other reason from away from the board, such as a forfeit, is rejected;
+    /// see [`.validate_trusting()`](#method.validate_trusting) to accept some.
+    ///
+    /// Returns:
+    ///
+    /// - [`Err(ValidationError::Unreadable)`](enum.ValidationError.html) with the errors of
+    ///   [`.start()`](#method.start), or if the `Result` tag can't be read
+    /// - [`Err(ValidationError::Illegal)`](enum.ValidationError.html) for the first action which
+    ///   can't be taken, with its index in [`actions`](#structfield.actions) and the error
+    /// - [`Err(ValidationError::ResultMismatch)`](enum.ValidationError.html) or
+    ///   [`Err(ValidationError::TerminationMismatch)`](enum.ValidationError.html) if the tags
+    ///   don't match the game, or the `Termination` tag isn't known
+    ///
+    /// ```
+    /// use thud::{GameRecord, ValidationError};
+    ///
+    /// let record: GameRecord = "[Result \"dwarf\"]\n\nG1-G6 H9-H10 H10* resign dwarf"
+    ///     .parse()
+    ///     .unwrap();
+    /// assert!(matches!(
+    ///     record.validate(),
+    ///     Err(ValidationError::ResultMismatch { .. })
+    /// ));
+    /// ```
+    pub fn validate(&self) -> Result<Thud, ValidationError> {
+        self.validate_trusting(&[])
+    }
+
+    /// Check the record as with [`.validate()`](#method.validate), also taking on trust a result
+    /// claimed for an unfinished game with any of the `trusted` terminations.
+    ///
+    /// This is for records from a source which adds its own terminations, such as an arena
+    /// recording an engine's forfeit.
+    ///
+    /// ```
+    /// use thud::{GameRecord, ValidationError};
+    ///
+    /// let record: GameRecord = "[Result \"troll\"]\n[Termination \"crash\"]\n\nG1-G6"
+    ///     .parse()
+    ///     .unwrap();
+    /// assert!(matches!(
+    ///     record.validate(),
+    ///     Err(ValidationError::TerminationMismatch { .. })
+    /// ));
+    /// assert!(record.validate_trusting(&["crash", "time_forfeit"]).is_ok());
+    /// ```
+    pub fn validate_trusting(&self, trusted: &[&str]) -> Result<Thud, ValidationError> {
+        let mut game = self.start().map_err(ValidationError::Unreadable)?;
+        for (ply, action) in self.actions.iter().enumerate() {
+            game.apply(action.clone())
+                .map_err(|error| ValidationError::Illegal {
+                    ply,
+                    action: action.clone(),
+                    error,
+                })?;
+        }
+
+        let claimed = match self.tag("Result") {
+            None => return Ok(game),
+            Some("*") => None,
+            Some(_) => Some(
+                self.outcome()
+                    .ok_or(ValidationError::Unreadable(ThudError::InvalidNotation))?,
+            ),
+        };
+        let actual = game.winner();
+        let mismatch = ValidationError::ResultMismatch { claimed, actual };
+        let termination = self.tag("Termination");
+        match (actual, claimed) {
+            (Some(end), _) => {
+                if claimed != Some(end.into()) {
+                    return Err(mismatch);
+                }
+                match termination {
+                    Some(name) if name != reason_name(end.reason()) => {
+                        Err(ValidationError::TerminationMismatch {
+                            claimed: name.to_string(),
+                            actual: Some(end.reason()),
+                        })
+                    }
+                    _ => Ok(game),
+                }
+            }
+            (None, None) => Ok(game),
+            (None, Some(outcome)) => match termination {
+                Some("out_of_time") => {
+                    let to_act = to_act(&game).expect("the game is unfinished");
+                    if outcome == Outcome::Won(to_act.opponent()) {
+                        game.out_of_time(to_act);
+                        Ok(game)
+                    } else {
+                        Err(mismatch)
+                    }
+                }
+                Some(name) if REPLAYABLE_ENDS.iter().any(|&end| reason_name(end) == name) => {
+                    Err(mismatch)
+                }
+                Some(name) if trusted.contains(&name) => Ok(game),
+                Some(name) => Err(ValidationError::TerminationMismatch {
+                    claimed: name.to_string(),
+                    actual: None,
+                }),
+                None => Err(mismatch),
+            },
+        }
+    }
+
+    /// Read every record in `text`, such as a file of several games one after another.
+    ///
+    /// Returns [`Err(ThudError::InvalidNotation)`](enum.ThudError.html) if any can't be read.
+    pub fn read_all(text: &str) -> Result<Vec<Self>, ThudError> {
+        let mut records = Vec::new();
+        let mut current = String::new();
+        let mut in_tags = true;
+        for line in text.lines() {
+            let is_tag = line.trim_start().starts_with('[');
+            if is_tag && !in_tags {
+                records.push(current.parse()?);
+                current.clear();
+            }
+            in_tags = is_tag;
+            current.push_str(line);
+            current.push('\n');
+        }
+        if !current.trim().is_empty() {
+            records.push(current.parse()?);
+        }
+        Ok(records)
+    }
+}
+
+/// Read a `[Name "value"]` tag line
+fn parse_tag(line: &str) -> Result<(String, String), ThudError> {
+    let inner = line
+        .strip_prefix('[')
+        .and_then(|line| line.strip_suffix(']'))
+        .ok_or(ThudError::InvalidNotation)?;
+    let (name, quoted) = inner.split_once(' ').ok_or(ThudError::InvalidNotation)?;
+    let quoted = quoted
+        .trim()
+        .strip_prefix('"')
+        .and_then(|quoted| quoted.strip_suffix('"'))
+        .ok_or(ThudError::InvalidNotation)?;
+
+    let mut value = String::new();
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => value.push(chars.next().ok_or(ThudError::InvalidNotation)?),
+            '"' => return Err(ThudError::InvalidNotation),
+            c => value.push(c),
+        }
+    }
+    if name.is_empty() {
+        return Err(ThudError::InvalidNotation);
+    }
+    Ok((name.to_string(), value))
+}
+
+/// Read the tags at the start of a record, giving them along with the movetext after them
+pub(crate) fn parse_header(text: &str) -> Result<(Vec<(String, String)>, String), ThudError> {
+    let mut tags = Vec::new();
+    let mut movetext = String::new();
+    for line in text.lines() {
+        // Movetext is kept as it is, as comments can run over several lines
+        let tag = line.trim();
+        if tag.starts_with('[') && movetext.trim().is_empty() {
+            tags.push(parse_tag(tag)?);
+        } else {
+            movetext.push_str(line);
+            movetext.push('\n');
+        }
+    }
+    Ok((tags, movetext))
+}
+
+/// A piece of movetext
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) enum Token {
+    /// The notation of an action, with any mark written after it
+    Action(String, Option<Mark>),
+    /// The text between `{` and `}`, still escaped
+    Comment(String),
+    /// `(`, starting a variation
+    Open,
+    /// `)`, ending a variation
+    Close,
+}
+
+/// Split movetext into [`Token`]s, keeping `resign <player>` together
+pub(crate) fn tokenize(movetext: &str) -> Result<Vec<Token>, ThudError> {
+    let mut tokens = Vec::new();
+    let mut chars = movetext.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            '{' => {
+                let mut comment = String::new();
+                loop {
+                    match chars.next().ok_or(ThudError::InvalidNotation)? {
+                        '\\' => {
+                            comment.push('\\');
+                            comment.push(chars.next().ok_or(ThudError::InvalidNotation)?);
+                        }
+                        '}' => break,
+                        c => comment.push(c),
+                    }
+                }
+                tokens.push(Token::Comment(comment));
+            }
+            '}' => return Err(ThudError::InvalidNotation),
+            c if c.is_whitespace() => (),
+            c => {
+                let mut word = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "(){}".contains(c) {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                let notation = word.trim_end_matches(['!', '?']);
+                let mark = match &word[notation.len()..] {
+                    "" => None,
+                    mark => Some(mark.parse()?),
+                };
+                match tokens.last_mut() {
+                    // The mark on a resignation comes after the player
+                    Some(Token::Action(last, last_mark @ None))
+                        if last.eq_ignore_ascii_case("resign") =>
+                    {
+                        last.push(' ');
+                        last.push_str(notation);
+                        *last_mark = mark;
+                    }
+                    _ => tokens.push(Token::Action(notation.to_string(), mark)),
+                }
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Write `[Name "value"]` tag lines and the blank line after them
+pub(crate) fn write_tags(f: &mut fmt::Formatter<'_>, tags: &[(String, String)]) -> fmt::Result {
+    for (name, value) in tags {
+        let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+        writeln!(f, "[{} \"{}\"]", name, value)?;
+    }
+    writeln!(f)
+}
+
+/// Write `words` separated by spaces, starting a new line rather than going past 80 columns
+pub(crate) fn write_wrapped(f: &mut fmt::Formatter<'_>, words: &[String]) -> fmt::Result {
+    let mut width = 0;
+    for word in words {
+        if width > 0 && width + word.len() + 1 > 80 {
+            writeln!(f)?;
+            width = 0;
+        }
+        if width > 0 {
+            write!(f, " ")?;
+            width += 1;
+        }
+        write!(f, "{}", word)?;
+        width += word.len();
+    }
+    writeln!(f)
+}
+
+impl fmt::Display for GameRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_tags(f, &self.tags)?;
+        let actions: Vec<String> = self.actions.iter().map(Action::to_string).collect();
+        write_wrapped(f, &actions)
+    }
+}
+
+impl FromStr for GameRecord {
+    type Err = ThudError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (tags, movetext) = parse_header(s)?;
+        let mut record = GameRecord {
+            tags,
+            actions: Vec::new(),
+        };
+        // Only actions outside of any variation are on the main line
+        let mut depth = 0usize;
+        for token in tokenize(&movetext)? {
+            match token {
+                Token::Action(action, _) if depth == 0 => record.actions.push(action.parse()?),
+                Token::Open => depth += 1,
+                Token::Close => depth = depth.checked_sub(1).ok_or(ThudError::InvalidNotation)?,
+                _ => (),
+            }
+        }
+        if depth > 0 {
+            return Err(ThudError::InvalidNotation);
+        }
+        Ok(record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RandomAgent;
+    use crate::{Agent, Board, Piece};
+    use test_case::test_case;
+
+    #[test]
+    fn round_trip() {
+        let mut game = Thud::with_rules(Rules {
+            repetition: Some(3),
+            move_limit: None,
+        })
+        .unwrap();
+        let mut agent = RandomAgent::new(4);
+        for _ in 0..60 {
+            let action = agent.choose(&game).unwrap();
+            game.apply(action).unwrap();
+        }
+        game.resign(Player::Troll).unwrap();
+
+        let mut record = GameRecord::from_game(&game);
+        record.set_tag("Event", "A \"quoted\" \\ name");
+        assert_eq!(record.outcome(), Some(Outcome::Won(Player::Dwarf)));
+        assert_eq!(record.tag("Termination"), Some("resignation"));
+        assert_eq!(record.tag("FEN"), None);
+
+        let text = record.to_string();
+        assert!(text.lines().all(|line| line.len() <= 80));
+        let read: GameRecord = text.parse().unwrap();
+        assert_eq!(read, record);
+        let replayed = read.replay().unwrap();
+        assert_eq!(replayed.board(), game.board());
+        assert_eq!(replayed.rules(), game.rules());
+    }
+
+    #[test]
+    fn custom_start() {
+        let mut board = Board::default();
+        board.place((7, 7).into(), Piece::Thudstone);
+        board.place((5, 5).into(), Piece::Troll);
+        board.place((5, 7).into(), Piece::Dwarf);
+        let mut game = Thud::from_board(board, Player::Troll).unwrap();
+        game.move_piece((5, 5).into(), (5, 6).into()).unwrap();
+
+        let record = GameRecord::from_game(&game);
+        assert_eq!(record.outcome(), None);
+        assert_eq!(record.tag("Result"), Some("*"));
+        let replayed = record.to_string().parse::<GameRecord>().unwrap().replay();
+        assert_eq!(replayed.unwrap().board(), game.board());
+    }
+
+    #[test]
+    fn out_of_time() {
+        let mut game = Thud::new();
+        game.move_piece((6, 0).into(), (6, 5).into()).unwrap();
+        game.out_of_time(Player::Troll);
+
+        let record = GameRecord::from_game(&game);
+        assert_eq!(record.tag("Termination"), Some("out_of_time"));
+        let read: GameRecord = record.to_string().parse().unwrap();
+        let won = Some(EndState::Won(Player::Dwarf, EndReason::OutOfTime));
+        assert_eq!(read.replay().unwrap().winner(), won);
+        assert_eq!(read.validate().unwrap().winner(), won);
+    }
+
+    #[test]
+    fn read_all() {
+        let text = "[Round \"1\"]\n\nG1-G6\n\n[Round \"2\"]\n[Result \"draw\"]\n\n\n[Round \"3\"]\nG1-G6 H9-H10\n";
+        let records = GameRecord::read_all(text).unwrap();
+        let rounds: Vec<_> = records.iter().map(|record| record.tag("Round")).collect();
+        assert_eq!(rounds, vec![Some("1"), Some("2"), Some("3")]);
+        assert_eq!(records[1].outcome(), Some(Outcome::Draw));
+        assert_eq!(records[2].actions.len(), 2);
+    }
+
+    #[test_case("[Result \"troll\"]\n\nG1-G6 H9-H10 H10* resign dwarf" => Ok(()); "resigned")]
+    #[test_case("[Result \"*\"]\n\nG1-G6" => Ok(()); "unfinished")]
+    #[test_case("[Result \"dwarf\"]\n[Termination \"out_of_time\"]\n\nG1-G6" => Ok(()); "flagged")]
+    #[test_case("[Result \"1-0\"]\n\n" => Err(ValidationError::Unreadable(ThudError::InvalidNotation)); "unreadable result")]
+    #[test_case("\n\nG1-G6 G6-G7" => Err(ValidationError::Illegal {
+        ply: 1,
+        action: Action::Move((6, 5).into(), (6, 6).into()),
+        error: ThudError::WrongPiece {
+            square: (6, 5).into(),
+            expected: Piece::Troll,
+            found: Piece::Dwarf,
+        },
+    }); "illegal")]
+    #[test_case("[Result \"dwarf\"]\n\nG1-G6 resign dwarf" => Err(ValidationError::ResultMismatch {
+        claimed: Some(Outcome::Won(Player::Dwarf)),
+        actual: Some(EndState::Won(Player::Troll, EndReason::Resignation)),
+    }); "wrong winner")]
+    #[test_case("[Result \"*\"]\n\nG1-G6 resign dwarf" => Err(ValidationError::ResultMismatch {
+        claimed: None,
+        actual: Some(EndState::Won(Player::Troll, EndReason::Resignation)),
+    }); "finished")]
+    #[test_case("[Result \"dwarf\"]\n[Termination \"resignation\"]\n\nG1-G6" => Err(ValidationError::ResultMismatch {
+        claimed: Some(Outcome::Won(Player::Dwarf)),
+        actual: None,
+    }); "not finished")]
+    #[test_case("[Result \"troll\"]\n[Termination \"out_of_time\"]\n\nG1-G6" => Err(ValidationError::ResultMismatch {
+        claimed: Some(Outcome::Won(Player::Troll)),
+        actual: None,
+    }); "wrong player flagged")]
+    #[test_case("[Result \"troll\"]\n[Termination \"agreement\"]\n\nG1-G6 resign dwarf" => Err(ValidationError::TerminationMismatch {
+        claimed: "agreement".to_string(),
+        actual: Some(EndReason::Resignation),
+    }); "wrong termination")]
+    #[test_case("[Result \"troll\"]\n[Termination \"crash\"]\n\nG1-G6" => Err(ValidationError::TerminationMismatch {
+        claimed: "crash".to_string(),
+        actual: None,
+    }); "unknown termination")]
+    fn validate(text: &str) -> Result<(), ValidationError> {
+        text.parse::<GameRecord>().unwrap().validate().map(|_| ())
+    }
+
+    #[test_case("[Result \"troll\"]\n[Termination \"crash\"]\n\nG1-G6" => Ok(()); "trusted")]
+    #[test_case("[Result \"troll\"]\n[Termination \"forfeit\"]\n\nG1-G6" => Err(ValidationError::TerminationMismatch {
+        claimed: "forfeit".to_string(),
+        actual: None,
+    }); "untrusted")]
+    #[test_case("[Result \"dwarf\"]\n[Termination \"crash\"]\n\nG1-G6 resign dwarf" => Err(ValidationError::ResultMismatch {
+        claimed: Some(Outcome::Won(Player::Dwarf)),
+        actual: Some(EndState::Won(Player::Troll, EndReason::Resignation)),
+    }); "finished game")]
+    fn validate_trusting(text: &str) -> Result<(), ValidationError> {
+        let record = text.parse::<GameRecord>().unwrap();
+        record.validate_trusting(&["crash"]).map(|_| ())
+    }
+
+    #[test_case("[Event \"x]\n\nG1-G6"; "unclosed quote")]
+    #[test_case("[Event]\n\nG1-G6"; "no value")]
+    #[test_case("[Event \"x\"]\n\nG1-Z6"; "bad action")]
+    #[test_case("[MoveLimit \"lots\"]\n\n"; "bad rules")]
+    #[test_case("[Event \"x\"]\n\nG1-G6 (G1-G5"; "unclosed variation")]
+    #[test_case("[Event \"x\"]\n\nG1-G6 {unclosed"; "unclosed comment")]
+    #[test_case("[Event \"x\"]\n\nG1-G6!!!"; "bad mark")]
+    fn invalid(text: &str) {
+        let record = text.parse::<GameRecord>().and_then(|record| record.start());
+        assert_eq!(record.err(), Some(ThudError::InvalidNotation));
+    }
+}
@@ -0,0 +1,40 @@
+use crate::ThudError;
+#[cfg(feature = "serialize")]
+use serde::{Deserialize, Serialize};
+
+/// Optional rules to apply on top of the standard Thud rules
+///
+/// The default is to apply none of them:
+///
+/// ```
+/// use thud::{Rules, Thud};
+///
+/// let game = Thud::with_rules(Rules {
+///     repetition: Some(3),
+///     ..Rules::default()
+/// })
+/// .unwrap();
+/// ```
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+pub struct Rules {
+    /// End the game as a draw once the same position, with the same player to move, has come up
+    /// this many times, which has to be at least 2
+    pub repetition: Option<usize>,
+    /// End the game as a draw once this many turns have been taken in a row without a capture,
+    /// which has to be at least 1
+    pub move_limit: Option<usize>,
+}
+
+impl Rules {
+    /// Check the limits can be played to.
+    ///
+    /// Returns [`Err(ThudError::InvalidRules)`](enum.ThudError.html) if the repetition limit is
+    /// below 2, as every position has already come up once, or the move limit is 0.
+    pub fn validate(&self) -> Result<(), ThudError> {
+        match (self.repetition, self.move_limit) {
+            (Some(0..=1), _) | (_, Some(0)) => Err(ThudError::InvalidRules),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,772 @@
+//! Host many games of Thud at once over HTTP, pushing what happens in them over WebSockets.
+//!
+//! Every request and response body is JSON. Errors are sent with a 4xx status and a body of
+//! `{"error": <message>}`. The endpoints are:
+//!
+//! - `POST /games` starts a game, optionally with a body giving the [`Rules`](../struct.Rules.html)
+//!   in their serialised form, and responds with the game
+//! - `GET /games` lists the games
+//! - `GET /games/<id>` gets a game
+//! - `POST /games/<id>/join` with `{"side": "dwarf"}` or `{"side": "troll"}` takes a free seat,
+//!   responding with `{"side": <side>, "token": <token>, "game": <game>}`
+//! - `POST /games/<id>/actions` with `{"token": <token>, "action": <notation>}` takes an
+//!   [`Action`](../enum.Action.html), written in its notation, for the seat holding the token
+//! - `GET /games/<id>/ws` opens a WebSocket which is sent the game as it stands, then an
+//!   event for every seat taken and every action
+//!
+//! A game is sent as:
+//!
+//! ```json
+//! {
+//!   "id": 1,
+//!   "fen": "5dd1dd5/4d5d4/...",
+//!   "board": {"squares": [[...], ...]},
+//!   "turn": "dwarf",
+//!   "score": [32, 32],
+//!   "end": null,
+//!   "end_offer": null,
+//!   "pending_capture": null,
+//!   "history": ["G1-G6"],
+//!   "seats": {"dwarf": true, "troll": false}
+//! }
+//! ```
+//!
+//! Events on the WebSocket are text messages like `{"event": "state", "game": <game>}`,
+//! `{"event": "joined", "side": "troll", "game": <game>}` and
+//! `{"event": "action", "action": "H9-H10", "game": <game>}`.
+//!
+//! Seats and actions go through a [`Session`](../struct.Session.html), so clients can only act
+//! for their own seat and in turn, and every action is checked by [`Thud`](../struct.Thud.html),
+//! so they can't break the rules. An unknown token gets a 401 status, resigning for the
+//! opponent a 403, acting out of turn or joining a taken seat a 409, and an illegal action a
+//! 422.
+//!
+//! Clients have 10 seconds to send the whole of a request. Up to 1024 connections are served at
+//! once, and any more are turned away with a 503. A game is dropped once nobody has joined it or
+//! acted in it for an hour, or five minutes after its last action if it has ended.
+//!
+//! ```no_run
+//! use thud::server::Server;
+//!
+//! let server = Server::bind("127.0.0.1:8080").unwrap();
+//! server.run().unwrap();
+//! ```
+
+use crate::{Action, Board, Coord, EndState, Player, Rules, Session, SessionError, Thud};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+use tungstenite::handshake::derive_accept_key;
+use tungstenite::protocol::Role;
+use tungstenite::{Message, WebSocket};
+
+/// Largest request, head and body together, which will be read
+const MAX_REQUEST: usize = 64 * 1024;
+
+/// How often a WebSocket checks for messages from its client
+const POLL: Duration = Duration::from_millis(50);
+
+/// How long a client has to send its whole request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Most connections served at once
+const MAX_CONNECTIONS: usize = 1024;
+
+/// How long to wait before accepting again after failing to accept a connection
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// How long a game is kept without a seat being taken or an action
+const IDLE_ROOM: Duration = Duration::from_secs(60 * 60);
+
+/// How long a game which has ended is kept after its last action
+const FINISHED_ROOM: Duration = Duration::from_secs(5 * 60);
+
+/// Which seats in a game have been taken
+#[derive(Debug, Serialize, Clone, Copy)]
+struct Seats {
+    dwarf: bool,
+    troll: bool,
+}
+
+/// A hosted game and the WebSockets watching it
+struct Room {
+    session: Session,
+    watchers: Vec<Sender<String>>,
+    // When the game was started, or last joined or acted in
+    touched: Instant,
+}
+
+impl Room {
+    /// Check whether the room should still be kept at `now`
+    fn is_live(&self, now: Instant) -> bool {
+        let idle = now.saturating_duration_since(self.touched);
+        let finished = self.session.game().turn().is_none();
+        idle < IDLE_ROOM && !(finished && idle >= FINISHED_ROOM)
+    }
+
+    /// Send an event to every watcher, forgetting those which have gone
+    fn broadcast(&mut self, event: &Event) {
+        let text = serde_json::to_string(event).expect("events can be serialised");
+        self.watchers
+            .retain(|watcher| watcher.send(text.clone()).is_ok());
+    }
+}
+
+/// What is sent about a game
+#[derive(Debug, Serialize)]
+struct View {
+    id: u64,
+    fen: String,
+    board: Board,
+    turn: Option<Player>,
+    score: (usize, usize),
+    end: Option<EndState>,
+    end_offer: Option<Player>,
+    pending_capture: Option<Coord>,
+    history: Vec<String>,
+    seats: Seats,
+}
+
+impl View {
+    fn new(id: u64, room: &Room) -> Self {
+        let mut game = room.session.game().clone();
+        View {
+            id,
+            fen: game.fen(),
+            board: game.board(),
+            turn: game.turn(),
+            score: game.score(),
+            end: game.winner(),
+            end_offer: game.end_offer(),
+            pending_capture: game.pending_capture(),
+            history: game.history().iter().map(Action::to_string).collect(),
+            seats: Seats {
+                dwarf: room.session.is_taken(Player::Dwarf),
+                troll: room.session.is_taken(Player::Troll),
+            },
+        }
+    }
+}
+
+/// A message sent on a game's WebSockets
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Event {
+    State { game: View },
+    Joined { side: Player, game: View },
+    Action { action: String, game: View },
+}
+
+#[derive(Debug, Deserialize)]
+struct JoinRequest {
+    side: Player,
+}
+
+#[derive(Debug, Deserialize)]
+struct ActionRequest {
+    token: String,
+    action: String,
+}
+
+/// The games being hosted
+#[derive(Default)]
+struct Hub {
+    rooms: BTreeMap<u64, Room>,
+    next_id: u64,
+}
+
+impl Hub {
+    /// Drop the games which have been idle too long at `now`, hanging up their WebSockets
+    fn evict(&mut self, now: Instant) {
+        self.rooms.retain(|_, room| room.is_live(now));
+    }
+}
+
+/// A failed request, sent back as a status and message
+#[derive(Debug)]
+struct Failure(u16, String);
+
+impl Failure {
+    fn not_found() -> Self {
+        Failure(404, "Not found".to_string())
+    }
+}
+
+impl From<SessionError> for Failure {
+    fn from(error: SessionError) -> Self {
+        let status = match error {
+            SessionError::UnknownToken => 401,
+            SessionError::WrongSeat(_) => 403,
+            SessionError::SeatTaken(_) | SessionError::NotYourTurn(_) => 409,
+            SessionError::Game(_) => 422,
+        };
+        Failure(status, error.to_string())
+    }
+}
+
+/// An HTTP request
+#[derive(Debug)]
+struct Request {
+    method: String,
+    path: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(other, _)| other.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn json<'a, T: Deserialize<'a>>(&'a self) -> Result<T, Failure> {
+        serde_json::from_slice(&self.body).map_err(|error| Failure(400, error.to_string()))
+    }
+}
+
+/// Read from `stream` into `chunk`, giving up at `deadline`
+fn read_until(stream: &mut TcpStream, chunk: &mut [u8], deadline: Instant) -> io::Result<usize> {
+    let left = deadline.saturating_duration_since(Instant::now());
+    if left.is_zero() {
+        return Err(io::Error::new(ErrorKind::TimedOut, "request timed out"));
+    }
+    stream.set_read_timeout(Some(left))?;
+    stream.read(chunk)
+}
+
+/// Read a request from `stream` which has to arrive by `deadline`, or `None` if it closes first
+fn read_request(stream: &mut TcpStream, deadline: Instant) -> io::Result<Option<Request>> {
+    let invalid = |message: &str| io::Error::new(ErrorKind::InvalidData, message.to_string());
+    let mut buffer = Vec::new();
+    let mut chunk = [0; 4096];
+    loop {
+        let read = read_until(stream, &mut chunk, deadline)?;
+        if read == 0 {
+            return Ok(None);
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+
+        let mut headers = [httparse::EMPTY_HEADER; 32];
+        let mut parsed = httparse::Request::new(&mut headers);
+        let head = match parsed.parse(&buffer) {
+            Ok(httparse::Status::Complete(head)) => head,
+            Ok(httparse::Status::Partial) if buffer.len() < MAX_REQUEST => continue,
+            Ok(httparse::Status::Partial) => return Err(invalid("request too large")),
+            Err(error) => return Err(invalid(&error.to_string())),
+        };
+
+        let mut request = Request {
+            method: parsed.method.unwrap_or_default().to_string(),
+            path: parsed.path.unwrap_or_default().to_string(),
+            headers: parsed
+                .headers
+                .iter()
+                .map(|header| {
+                    let value = String::from_utf8_lossy(header.value).into_owned();
+                    (header.name.to_string(), value)
+                })
+                .collect(),
+            body: buffer[head..].to_vec(),
+        };
+        let length = match request.header("Content-Length") {
+            Some(length) => length.parse().map_err(|_| invalid("bad content length"))?,
+            None => 0,
+        };
+        match head.checked_add(length) {
+            Some(total) if total <= MAX_REQUEST => (),
+            _ => return Err(invalid("request too large")),
+        }
+        while request.body.len() < length {
+            let read = read_until(stream, &mut chunk, deadline)?;
+            if read == 0 {
+                return Ok(None);
+            }
+            request.body.extend_from_slice(&chunk[..read]);
+        }
+        request.body.truncate(length);
+        return Ok(Some(request));
+    }
+}
+
+/// Send a JSON response and close the connection
+fn respond(stream: &mut TcpStream, status: u16, body: &impl Serialize) -> io::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        409 => "Conflict",
+        422 => "Unprocessable Entity",
+        503 => "Service Unavailable",
+        _ => "",
+    };
+    let body = serde_json::to_vec(body).expect("responses can be serialised");
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        reason,
+        body.len()
+    )?;
+    stream.write_all(&body)?;
+    stream.flush()
+}
+
+fn to_json(value: impl Serialize) -> serde_json::Value {
+    serde_json::to_value(value).expect("views can be serialised")
+}
+
+/// An HTTP and WebSocket server hosting games of Thud, see the [module docs](index.html)
+pub struct Server {
+    listener: TcpListener,
+    hub: Arc<Mutex<Hub>>,
+    connections: Arc<AtomicUsize>,
+    max_connections: usize,
+}
+
+/// A connection being served, counted until it is dropped
+struct Slot(Arc<AtomicUsize>);
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Server {
+    /// Listen on `address`, such as `"127.0.0.1:8080"`, or port 0 to have one picked
+    pub fn bind(address: impl ToSocketAddrs) -> io::Result<Self> {
+        Ok(Server {
+            listener: TcpListener::bind(address)?,
+            hub: Arc::default(),
+            connections: Arc::default(),
+            max_connections: MAX_CONNECTIONS,
+        })
+    }
+
+    /// Get the address the server is listening on
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Serve requests forever, each connection on its own thread.
+    ///
+    /// A connection which can't be accepted is reported on stderr, and the server carries on.
+    pub fn run(self) -> io::Result<()> {
+        for stream in self.listener.incoming() {
+            let mut stream = match stream {
+                Ok(stream) => stream,
+                Err(error) => {
+                    // Such as running out of file descriptors, which may pass
+                    eprintln!("Could not accept a connection: {}", error);
+                    thread::sleep(ACCEPT_BACKOFF);
+                    continue;
+                }
+            };
+            let slot = Slot(Arc::clone(&self.connections));
+            if self.connections.fetch_add(1, Ordering::SeqCst) >= self.max_connections {
+                let body = serde_json::json!({ "error": "too many connections" });
+                let _ = respond(&mut stream, 503, &body);
+                continue;
+            }
+            let hub = Arc::clone(&self.hub);
+            thread::spawn(move || {
+                let _slot = slot;
+                let _ = Connection { stream, hub }.serve();
+            });
+        }
+        Ok(())
+    }
+}
+
+/// A client connected to the server
+struct Connection {
+    stream: TcpStream,
+    hub: Arc<Mutex<Hub>>,
+}
+
+impl Connection {
+    fn hub(&self) -> MutexGuard<'_, Hub> {
+        self.hub
+            .lock()
+            .expect("no request panics while holding the games")
+    }
+
+    fn serve(mut self) -> io::Result<()> {
+        let deadline = Instant::now() + REQUEST_TIMEOUT;
+        let request = match read_request(&mut self.stream, deadline) {
+            Ok(Some(request)) => request,
+            Ok(None) => return Ok(()),
+            Err(error) => {
+                let body = serde_json::json!({ "error": error.to_string() });
+                return respond(&mut self.stream, 400, &body);
+            }
+        };
+
+        let path = request.path.split('?').next().unwrap_or_default();
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        if let ("GET", ["games", id, "ws"]) = (request.method.as_str(), &segments[..]) {
+            return match id.parse() {
+                Ok(id) => self.watch(id, &request),
+                Err(_) => self.fail(Failure::not_found()),
+            };
+        }
+
+        match self.route(&request, &segments) {
+            Ok((status, body)) => respond(&mut self.stream, status, &body),
+            Err(failure) => self.fail(failure),
+        }
+    }
+
+    fn fail(&mut self, Failure(status, message): Failure) -> io::Result<()> {
+        respond(
+            &mut self.stream,
+            status,
+            &serde_json::json!({ "error": message }),
+        )
+    }
+
+    /// Answer a REST request, giving the status and body
+    fn route(
+        &self,
+        request: &Request,
+        segments: &[&str],
+    ) -> Result<(u16, serde_json::Value), Failure> {
+        let id = |segment: &str| segment.parse::<u64>().map_err(|_| Failure::not_found());
+        let mut hub = self.hub();
+        hub.evict(Instant::now());
+        match (request.method.as_str(), segments) {
+            ("GET", ["games"]) => {
+                let views: Vec<View> = hub
+                    .rooms
+                    .iter()
+                    .map(|(&id, room)| View::new(id, room))
+                    .collect();
+                Ok((200, to_json(views)))
+            }
+            ("POST", ["games"]) => {
+                let rules = if request.body.iter().all(u8::is_ascii_whitespace) {
+                    Rules::default()
+                } else {
+                    request.json()?
+                };
+                let game =
+                    Thud::with_rules(rules).map_err(|error| Failure(400, error.to_string()))?;
+                hub.next_id += 1;
+                let id = hub.next_id;
+                let room = Room {
+                    session: Session::new(game),
+                    watchers: Vec::new(),
+                    touched: Instant::now(),
+                };
+                let view = View::new(id, &room);
+                hub.rooms.insert(id, room);
+                Ok((201, to_json(view)))
+            }
+            ("GET", ["games", game]) => {
+                let id = id(game)?;
+                let room = hub.rooms.get(&id).ok_or_else(Failure::not_found)?;
+                Ok((200, to_json(View::new(id, room))))
+            }
+            ("POST", ["games", game, "join"]) => {
+                let id = id(game)?;
+                let room = hub.rooms.get_mut(&id).ok_or_else(Failure::not_found)?;
+                let JoinRequest { side } = request.json()?;
+                let token = room.session.join(side).map_err(Failure::from)?;
+                room.touched = Instant::now();
+                room.broadcast(&Event::Joined {
+                    side,
+                    game: View::new(id, room),
+                });
+                let joined = serde_json::json!({
+                    "side": side,
+                    "token": token.as_str(),
+                    "game": View::new(id, room),
+                });
+                Ok((200, joined))
+            }
+            ("POST", ["games", game, "actions"]) => {
+                let id = id(game)?;
+                let room = hub.rooms.get_mut(&id).ok_or_else(Failure::not_found)?;
+                let ActionRequest { token, action } = request.json()?;
+                let action: Action = action
+                    .parse()
+                    .map_err(|error: crate::ThudError| Failure(400, error.to_string()))?;
+                room.session
+                    .act(&token, action.clone())
+                    .map_err(Failure::from)?;
+                room.touched = Instant::now();
+                let view = View::new(id, room);
+                room.broadcast(&Event::Action {
+                    action: action.to_string(),
+                    game: View::new(id, room),
+                });
+                Ok((200, to_json(view)))
+            }
+            _ => Err(Failure::not_found()),
+        }
+    }
+
+    /// Upgrade to a WebSocket and send it the events of game `id` until it closes
+    fn watch(mut self, id: u64, request: &Request) -> io::Result<()> {
+        let key = match request.header("Sec-WebSocket-Key") {
+            Some(key) => key,
+            None => return self.fail(Failure(400, "Expected a WebSocket".to_string())),
+        };
+        let (sender, events) = mpsc::channel();
+        let state = {
+            let mut hub = self.hub();
+            let room = match hub.rooms.get_mut(&id) {
+                Some(room) => room,
+                None => {
+                    drop(hub);
+                    return self.fail(Failure::not_found());
+                }
+            };
+            room.watchers.push(sender);
+            Event::State {
+                game: View::new(id, room),
+            }
+        };
+
+        write!(
+            self.stream,
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+            derive_accept_key(key.as_bytes())
+        )?;
+        self.stream.set_read_timeout(Some(POLL))?;
+        let mut socket = WebSocket::from_raw_socket(self.stream, Role::Server, None);
+        let closed = |error| io::Error::new(ErrorKind::ConnectionAborted, error);
+
+        let state = serde_json::to_string(&state).expect("events can be serialised");
+        socket.send(Message::Text(state)).map_err(closed)?;
+        loop {
+            match events.recv_timeout(POLL) {
+                Ok(event) => socket.send(Message::Text(event)).map_err(closed)?,
+                Err(RecvTimeoutError::Timeout) => (),
+                Err(RecvTimeoutError::Disconnected) => return Ok(()),
+            }
+            // Answer pings and notice the client leaving; anything else it sends is ignored
+            match socket.read() {
+                Ok(Message::Close(_)) => return Ok(()),
+                Ok(_) => (),
+                Err(tungstenite::Error::Io(error))
+                    if matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+                Err(error) => return Err(closed(error)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn start() -> SocketAddr {
+        let server = Server::bind("127.0.0.1:0").unwrap();
+        let address = server.local_addr().unwrap();
+        thread::spawn(move || server.run());
+        address
+    }
+
+    fn request(address: SocketAddr, method: &str, path: &str, body: Option<Value>) -> (u16, Value) {
+        let body = body.map(|body| body.to_string()).unwrap_or_default();
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            path,
+            body.len(),
+            body
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+        (status, serde_json::from_str(body).unwrap())
+    }
+
+    #[test]
+    fn rest() {
+        let address = start();
+        let (status, game) = request(address, "POST", "/games", None);
+        assert_eq!(status, 201);
+        assert_eq!(game["turn"], "dwarf");
+        let id = game["id"].as_u64().unwrap();
+        let path = format!("/games/{}", id);
+
+        let join = |side| {
+            let body = Some(json!({ "side": side }));
+            request(address, "POST", &format!("{}/join", path), body)
+        };
+        let (status, troll) = join("troll");
+        assert_eq!(
+            (status, &troll["game"]["seats"]),
+            (200, &json!({"dwarf": false, "troll": true}))
+        );
+        assert_eq!(join("troll").0, 409);
+        let (_, dwarf) = join("dwarf");
+        let (dwarf, troll) = (&dwarf["token"], &troll["token"]);
+
+        let act = |token: &Value, action: &str| {
+            let body = Some(json!({"token": token, "action": action}));
+            request(address, "POST", &format!("{}/actions", path), body)
+        };
+        assert_eq!(act(troll, "G1-G6").0, 409);
+        assert_eq!(act(&json!("spoofed"), "G1-G6").0, 401);
+        let (status, game) = act(dwarf, "G1-G6");
+        assert_eq!((status, &game["turn"]), (200, &json!("troll")));
+        let (status, error) = act(troll, "G6-G7");
+        assert_eq!(status, 422);
+        assert!(error["error"].is_string());
+        assert_eq!(act(troll, "Z1-G7").0, 400);
+        assert_eq!(act(troll, "resign dwarf").0, 403);
+
+        let (status, game) = request(address, "GET", &path, None);
+        assert_eq!((status, &game["history"]), (200, &json!(["G1-G6"])));
+        let (status, games) = request(address, "GET", "/games", None);
+        assert_eq!((status, games.as_array().unwrap().len()), (200, 1));
+        let (status, _) = request(address, "GET", "/games/99", None);
+        assert_eq!(status, 404);
+
+        let rules = json!({"repetition": 1, "move_limit": null});
+        let (status, error) = request(address, "POST", "/games", Some(rules));
+        assert_eq!(status, 400);
+        assert!(error["error"].is_string());
+    }
+
+    #[test]
+    fn huge_content_length() {
+        let address = start();
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(
+            stream,
+            "POST /games HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            usize::MAX
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 400 "));
+        assert!(response.contains("request too large"));
+    }
+
+    #[test]
+    fn slow_request() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (mut stream, _) = listener.accept().unwrap();
+        // Each byte arrives well within a read timeout, but the request is never finished
+        let trickle = thread::spawn(move || {
+            for _ in 0..20 {
+                if client.write_all(b"G").is_err() {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(20));
+            }
+        });
+        let deadline = Instant::now() + Duration::from_millis(100);
+        let error = read_request(&mut stream, deadline).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::TimedOut);
+        drop(stream);
+        trickle.join().unwrap();
+    }
+
+    #[test]
+    fn too_many_connections() {
+        let mut server = Server::bind("127.0.0.1:0").unwrap();
+        server.max_connections = 1;
+        let address = server.local_addr().unwrap();
+        thread::spawn(move || server.run());
+
+        // Holds the only connection while it waits for a request
+        let held = TcpStream::connect(address).unwrap();
+        thread::sleep(Duration::from_millis(100));
+        let mut stream = TcpStream::connect(address).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 503 "));
+
+        drop(held);
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(request(address, "GET", "/games", None).0, 200);
+    }
+
+    #[test]
+    fn evict() {
+        let now = Instant::now();
+        let room = |game| Room {
+            session: Session::new(game),
+            watchers: Vec::new(),
+            touched: now,
+        };
+        let mut ended = Thud::new();
+        ended.resign(Player::Dwarf).unwrap();
+        let mut hub = Hub::default();
+        hub.rooms.insert(1, room(Thud::new()));
+        hub.rooms.insert(2, room(ended));
+
+        hub.evict(now + FINISHED_ROOM / 2);
+        assert_eq!(hub.rooms.len(), 2);
+        hub.evict(now + FINISHED_ROOM);
+        assert_eq!(hub.rooms.keys().collect::<Vec<_>>(), vec![&1]);
+        hub.evict(now + IDLE_ROOM);
+        assert!(hub.rooms.is_empty());
+    }
+
+    #[test]
+    fn rules() {
+        let address = start();
+        let rules = json!({"repetition": 3, "move_limit": null});
+        let (status, _) = request(address, "POST", "/games", Some(rules));
+        assert_eq!(status, 201);
+        let (status, _) = request(address, "POST", "/games", Some(json!({"repetition": "x"})));
+        assert_eq!(status, 400);
+    }
+
+    #[test]
+    fn websocket() {
+        let address = start();
+        let (_, game) = request(address, "POST", "/games", None);
+        let id = game["id"].as_u64().unwrap();
+
+        let stream = TcpStream::connect(address).unwrap();
+        let url = format!("ws://{}/games/{}/ws", address, id);
+        let (mut socket, _) = tungstenite::client(url, stream).unwrap();
+        let mut next = || match socket.read().unwrap() {
+            Message::Text(text) => serde_json::from_str::<Value>(&text).unwrap(),
+            message => panic!("unexpected message {:?}", message),
+        };
+        assert_eq!(next()["event"], "state");
+
+        let path = format!("/games/{}", id);
+        let body = Some(json!({"side": "dwarf"}));
+        let (_, joined) = request(address, "POST", &format!("{}/join", path), body);
+        let token = &joined["token"];
+        let joined = next();
+        assert_eq!(
+            (&joined["event"], &joined["side"]),
+            (&json!("joined"), &json!("dwarf"))
+        );
+
+        let body = Some(json!({"token": token, "action": "G1-G6"}));
+        request(address, "POST", &format!("{}/actions", path), body);
+        let action = next();
+        assert_eq!(action["event"], "action");
+        assert_eq!(action["action"], "G1-G6");
+        assert_eq!(action["game"]["turn"], "troll");
+    }
+}
@@ -0,0 +1,240 @@
+use crate::{Action, Player, Thud, ThudError};
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+/// Reports a request a [`Session`](struct.Session.html) turned down
+#[derive(Debug, PartialEq, Clone, Copy, Error)]
+pub enum SessionError {
+    #[error("The {0:?} seat is taken")]
+    SeatTaken(Player),
+    #[error("No seat is held with that token")]
+    UnknownToken,
+    #[error("The {0:?} player can't act for their opponent")]
+    WrongSeat(Player),
+    #[error("It is not the {0:?} player's turn")]
+    NotYourTurn(Player),
+    #[error(transparent)]
+    Game(#[from] ThudError),
+}
+
+/// A secret handed to whoever takes a seat in a [`Session`](struct.Session.html), which they
+/// show to act for that seat
+///
+/// Tokens are 32 hex digits from the standard library's randomly keyed hasher: enough that
+/// clients can't guess each other's, though they should still be kept off unencrypted
+/// connections.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct SeatToken(String);
+
+impl SeatToken {
+    fn generate() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_nanos())
+            .unwrap_or_default();
+        let halves: Vec<u64> = (0..2u8)
+            .map(|half| {
+                let mut hasher = RandomState::new().build_hasher();
+                hasher.write_u128(nanos);
+                hasher.write_u8(half);
+                hasher.finish()
+            })
+            .collect();
+        SeatToken(format!("{:016x}{:016x}", halves[0], halves[1]))
+    }
+
+    /// Get the token as text, to be sent to its holder
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for SeatToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// A [`Thud`](struct.Thud.html) game played by clients who each hold a seat
+///
+/// Taking a seat with [`.join()`](#method.join) gives a [`SeatToken`](struct.SeatToken.html),
+/// and every action must come with the token of the player who has to act; hosts need only pass
+/// on what clients send.
+///
+/// ```
+/// use thud::{Action, Player, Session, SessionError, Thud};
+///
+/// let mut session = Session::new(Thud::new());
+/// let dwarf = session.join(Player::Dwarf).unwrap();
+/// let troll = session.join(Player::Troll).unwrap();
+///
+/// let first: Action = "G1-G6".parse().unwrap();
+/// assert_eq!(
+///     session.act(troll.as_str(), first.clone()),
+///     Err(SessionError::NotYourTurn(Player::Troll))
+/// );
+/// assert_eq!(
+///     session.act("made up", first.clone()),
+///     Err(SessionError::UnknownToken)
+/// );
+/// session.act(dwarf.as_str(), first).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Session {
+    game: Thud,
+    dwarf: Option<SeatToken>,
+    troll: Option<SeatToken>,
+}
+
+impl Session {
+    /// Host `game`, with both seats free
+    pub fn new(game: Thud) -> Self {
+        Session {
+            game,
+            dwarf: None,
+            troll: None,
+        }
+    }
+
+    /// Get the game being played
+    pub fn game(&self) -> &Thud {
+        &self.game
+    }
+
+    fn token(&self, side: Player) -> &Option<SeatToken> {
+        match side {
+            Player::Dwarf => &self.dwarf,
+            Player::Troll => &self.troll,
+        }
+    }
+
+    /// Find whether someone has taken the seat for `side`
+    pub fn is_taken(&self, side: Player) -> bool {
+        self.token(side).is_some()
+    }
+
+    /// Take the seat for `side`, getting the token to act for it with.
+    ///
+    /// Returns [`Err(SessionError::SeatTaken)`](enum.SessionError.html) if someone already has.
+    pub fn join(&mut self, side: Player) -> Result<SeatToken, SessionError> {
+        let seat = match side {
+            Player::Dwarf => &mut self.dwarf,
+            Player::Troll => &mut self.troll,
+        };
+        if seat.is_some() {
+            return Err(SessionError::SeatTaken(side));
+        }
+        let token = SeatToken::generate();
+        *seat = Some(token.clone());
+        Ok(token)
+    }
+
+    /// Find which seat `token` is for, if any
+    pub fn seat(&self, token: &str) -> Option<Player> {
+        [Player::Dwarf, Player::Troll]
+            .iter()
+            .copied()
+            .find(|&side| self.token(side).as_ref().map(SeatToken::as_str) == Some(token))
+    }
+
+    /// Take `action` on behalf of the holder of `token`.
+    ///
+    /// Seated players may [resign](enum.Action.html#variant.Resign) for themselves at any time;
+    /// anything else has to wait until they are the one to act, which is their opponent when
+    /// [an end has been offered](struct.Thud.html#method.end_offer) and otherwise whoever has
+    /// the [turn](struct.Thud.html#method.turn).
+    ///
+    /// Returns:
+    ///
+    /// - [`Err(SessionError::UnknownToken)`](enum.SessionError.html) if no seat has `token`
+    /// - [`Err(SessionError::WrongSeat)`](enum.SessionError.html) if resigning for the opponent
+    /// - [`Err(SessionError::NotYourTurn)`](enum.SessionError.html) if the player isn't the one
+    ///   to act
+    /// - [`Err(SessionError::Game)`](enum.SessionError.html) with any error from
+    ///   [`Thud::apply()`](struct.Thud.html#method.apply)
+    pub fn act(&mut self, token: &str, action: Action) -> Result<(), SessionError> {
+        let side = self.seat(token).ok_or(SessionError::UnknownToken)?;
+        match action {
+            Action::Resign(player) if player != side => return Err(SessionError::WrongSeat(side)),
+            Action::Resign(_) => (),
+            _ => {
+                let to_act = match self.game.end_offer() {
+                    Some(offered_by) => Some(offered_by.opponent()),
+                    None => self.game.turn(),
+                };
+                if to_act != Some(side) {
+                    return Err(SessionError::NotYourTurn(side));
+                }
+            }
+        }
+        self.game.apply(action)?;
+        self.game.winner();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Piece;
+
+    #[test]
+    fn seats() {
+        let mut session = Session::new(Thud::new());
+        assert!(!session.is_taken(Player::Troll));
+        let troll = session.join(Player::Troll).unwrap();
+        assert!(session.is_taken(Player::Troll));
+        assert_eq!(
+            session.join(Player::Troll),
+            Err(SessionError::SeatTaken(Player::Troll))
+        );
+        let dwarf = session.join(Player::Dwarf).unwrap();
+
+        assert_ne!(dwarf, troll);
+        assert_eq!(dwarf.as_str().len(), 32);
+        assert_eq!(session.seat(dwarf.as_str()), Some(Player::Dwarf));
+        assert_eq!(session.seat(troll.as_str()), Some(Player::Troll));
+        assert_eq!(session.seat(""), None);
+    }
+
+    #[test]
+    fn turns() {
+        let mut session = Session::new(Thud::new());
+        let dwarf = session.join(Player::Dwarf).unwrap();
+        let troll = session.join(Player::Troll).unwrap();
+        let (dwarf, troll) = (dwarf.as_str(), troll.as_str());
+
+        session.act(dwarf, "G1-G6".parse().unwrap()).unwrap();
+        assert_eq!(
+            session.act(dwarf, "G6-G7".parse().unwrap()),
+            Err(SessionError::NotYourTurn(Player::Dwarf))
+        );
+        assert_eq!(
+            session.act(troll, "G6-G7".parse().unwrap()),
+            Err(SessionError::Game(ThudError::WrongPiece {
+                square: (6, 5).into(),
+                expected: Piece::Troll,
+                found: Piece::Dwarf,
+            }))
+        );
+        session.act(troll, "H9-H10".parse().unwrap()).unwrap();
+        session.act(troll, "H10*".parse().unwrap()).unwrap();
+
+        session.act(dwarf, Action::OfferEnd).unwrap();
+        assert_eq!(
+            session.act(dwarf, Action::AcceptEnd),
+            Err(SessionError::NotYourTurn(Player::Dwarf))
+        );
+        session.act(troll, Action::DeclineEnd).unwrap();
+
+        assert_eq!(
+            session.act(troll, Action::Resign(Player::Dwarf)),
+            Err(SessionError::WrongSeat(Player::Troll))
+        );
+        session.act(troll, Action::Resign(Player::Troll)).unwrap();
+        assert_eq!(session.game().turn(), None);
+    }
+}
@@ -0,0 +1,328 @@
+use crate::state::GameState;
+use crate::{Action, Board, Player, Rules, Thud, ThudError};
+use serde::de::IgnoredAny;
+use serde::{Deserialize, Deserializer, Serialize};
+use thiserror::Error;
+
+/// A saved game of Thud, which will keep loading across versions of this library
+///
+/// Rather than storing the internals of a [`Thud`](struct.Thud.html), a `Snapshot` stores the
+/// starting position, [`Rules`](struct.Rules.html) and [history](struct.Thud.html#method.history)
+/// of the game, which are replayed to restore it. Snapshots saved by older versions of the
+/// library are upgraded when they are deserialised.
+///
+/// In human-readable formats such as JSON, a [`Thud`](struct.Thud.html) serialised directly by
+/// version 0.1 of the library, before snapshots existed, can be loaded as a `Snapshot` too. It
+/// has no history, so it restores to a game starting from its position, and one saved partway
+/// through a troll player's turn carries on at the dwarf player's turn.
+///
+/// ```
+/// use thud::{Snapshot, Thud};
+///
+/// let mut game = Thud::new();
+/// game.move_piece((6, 0).into(), (6, 5).into()).unwrap();
+///
+/// let saved = serde_json::to_string(&Snapshot::new(&game)).unwrap();
+/// let loaded: Snapshot = serde_json::from_str(&saved).unwrap();
+/// let game = loaded.restore().unwrap();
+///
+/// assert_eq!(game.history().len(), 1);
+/// ```
+#[derive(Serialize, Debug, PartialEq, Clone)]
+#[serde(into = "Envelope")]
+pub struct Snapshot {
+    rules: Rules,
+    start: Board,
+    start_to_move: Player,
+    start_state: GameState,
+    start_offer: Option<Player>,
+    history: Vec<Action>,
+    flagged: Option<Player>,
+    board: Board,
+}
+
+/// Reports a problem restoring a [`Snapshot`](struct.Snapshot.html)
+#[derive(Debug, PartialEq, Clone, Error)]
+pub enum SnapshotError {
+    #[error("Starting position is invalid")]
+    InvalidStart,
+    #[error("Action {ply} could not be replayed: {error}")]
+    Replay { ply: usize, error: ThudError },
+    #[error("Replaying the history does not reach the saved position")]
+    Mismatch,
+}
+
+impl Snapshot {
+    /// The format version written by this version of the library
+    pub const VERSION: u32 = 1;
+
+    /// Take a snapshot of a game.
+    pub fn new(game: &Thud) -> Self {
+        let (start, start_to_move) = game.start();
+        let (start_state, start_offer) = game.start_phase();
+        Snapshot {
+            rules: game.rules(),
+            start,
+            start_to_move,
+            start_state,
+            start_offer,
+            history: game.history().to_vec(),
+            flagged: game.flagged(),
+            board: game.board(),
+        }
+    }
+
+    /// Get the [`Rules`](struct.Rules.html) the game is played under
+    pub fn rules(&self) -> Rules {
+        self.rules
+    }
+
+    /// Get the [`Action`s](enum.Action.html) taken in the game, oldest first
+    pub fn history(&self) -> &[Action] {
+        &self.history
+    }
+
+    /// Get the position the game had reached when the snapshot was taken
+    pub fn board(&self) -> Board {
+        self.board
+    }
+
+    /// Rebuild the game by replaying its history.
+    ///
+    /// Returns:
+    ///
+    /// - [`Err(SnapshotError::InvalidStart)`](enum.SnapshotError.html) if the starting position,
+    ///   or how far through the first turn it is, is not valid
+    /// - [`Err(SnapshotError::Replay)`](enum.SnapshotError.html) with the index of the first
+    ///   action in the history that can't be taken
+    /// - [`Err(SnapshotError::Mismatch)`](enum.SnapshotError.html) if the history doesn't lead to
+    ///   the saved position
+    pub fn restore(&self) -> Result<Thud, SnapshotError> {
+        let mut game = Thud::from_board_with_rules(self.start, self.start_to_move, self.rules)
+            .map_err(|_| SnapshotError::InvalidStart)?;
+        game.set_start_phase(self.start_state, self.start_offer)
+            .map_err(|_| SnapshotError::InvalidStart)?;
+        for (ply, action) in self.history.iter().enumerate() {
+            game.apply(action.clone())
+                .map_err(|error| SnapshotError::Replay { ply, error })?;
+        }
+        if let Some(player) = self.flagged {
+            game.out_of_time(player);
+        }
+
+        if game.board() != self.board {
+            return Err(SnapshotError::Mismatch);
+        }
+        Ok(game)
+    }
+}
+
+impl<'de> Deserialize<'de> for Snapshot {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // Only self-describing formats can tell a legacy save from an envelope
+        if deserializer.is_human_readable() {
+            Saved::deserialize(deserializer).map(Snapshot::from)
+        } else {
+            Envelope::deserialize(deserializer).map(Snapshot::from)
+        }
+    }
+}
+
+/// Anything a `Snapshot` can be loaded from in a human-readable format
+// Only lives while loading, so its size doesn't matter
+#[allow(clippy::large_enum_variant)]
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Saved {
+    Envelope(Envelope),
+    Legacy(LegacyThud),
+}
+
+/// A `Thud` as serialised by version 0.1, before snapshots existed
+#[derive(Deserialize)]
+struct LegacyThud {
+    board: Board,
+    state: LegacyState,
+}
+
+#[derive(Deserialize)]
+enum LegacyState {
+    Nominal(LegacyPlayer),
+    PostTrollMove(IgnoredAny),
+    // Games could only end by running out of moves, which replaying will find again
+    GameEnded(IgnoredAny),
+}
+
+#[derive(Deserialize)]
+enum LegacyPlayer {
+    Dwarf,
+    Troll,
+}
+
+impl From<Saved> for Snapshot {
+    fn from(saved: Saved) -> Self {
+        match saved {
+            Saved::Envelope(envelope) => envelope.into(),
+            Saved::Legacy(legacy) => {
+                let to_move = match legacy.state {
+                    LegacyState::Nominal(LegacyPlayer::Troll) => Player::Troll,
+                    // Where the troll is that should capture wasn't saved, so the troll player's
+                    // turn ends there
+                    _ => Player::Dwarf,
+                };
+                Snapshot {
+                    rules: Rules::default(),
+                    start: legacy.board,
+                    start_to_move: to_move,
+                    start_state: GameState::Nominal(to_move),
+                    start_offer: None,
+                    history: Vec::new(),
+                    flagged: None,
+                    board: legacy.board,
+                }
+            }
+        }
+    }
+}
+
+/// Every format version of `Snapshot`, oldest first
+///
+/// Add a new variant when the format changes, and upgrade the older ones to it in
+/// `From<Envelope> for Snapshot`.
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "snapshot", rename_all = "snake_case")]
+enum Envelope {
+    V1(SnapshotV1),
+}
+
+#[derive(Serialize, Deserialize)]
+struct SnapshotV1 {
+    rules: Rules,
+    start: Board,
+    start_to_move: Player,
+    start_state: GameState,
+    start_offer: Option<Player>,
+    history: Vec<Action>,
+    flagged: Option<Player>,
+    board: Board,
+}
+
+impl From<Snapshot> for Envelope {
+    fn from(snapshot: Snapshot) -> Self {
+        Envelope::V1(SnapshotV1 {
+            rules: snapshot.rules,
+            start: snapshot.start,
+            start_to_move: snapshot.start_to_move,
+            start_state: snapshot.start_state,
+            start_offer: snapshot.start_offer,
+            history: snapshot.history,
+            flagged: snapshot.flagged,
+            board: snapshot.board,
+        })
+    }
+}
+
+impl From<Envelope> for Snapshot {
+    fn from(envelope: Envelope) -> Self {
+        match envelope {
+            Envelope::V1(v1) => Snapshot {
+                rules: v1.rules,
+                start: v1.start,
+                start_to_move: v1.start_to_move,
+                start_state: v1.start_state,
+                start_offer: v1.start_offer,
+                history: v1.history,
+                flagged: v1.flagged,
+                board: v1.board,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EndReason, EndState, Piece};
+
+    fn played() -> Thud {
+        let mut game = Thud::new();
+        game.move_piece((6, 0).into(), (6, 5).into()).unwrap();
+        game.move_piece((8, 7).into(), (9, 7).into()).unwrap();
+        game.troll_cap((9, 7).into(), vec![]).unwrap();
+        game
+    }
+
+    #[test]
+    fn round_trip() {
+        let snapshot = Snapshot::new(&played());
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert!(json.starts_with(&format!(r#"{{"v{}":"#, Snapshot::VERSION)));
+        let from_json: Snapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(from_json, snapshot);
+
+        let binary = bincode::serialize(&snapshot).unwrap();
+        let from_binary: Snapshot = bincode::deserialize(&binary).unwrap();
+        assert_eq!(from_binary, snapshot);
+
+        let game = from_json.restore().unwrap();
+        assert_eq!(game.history(), played().history());
+        assert_eq!(game.turn(), Some(Player::Dwarf));
+    }
+
+    #[test]
+    fn bad_history() {
+        let mut snapshot = Snapshot::new(&played());
+        snapshot
+            .history
+            .insert(1, Action::Move((6, 0).into(), (6, 5).into()));
+        assert_eq!(
+            snapshot.restore().unwrap_err(),
+            SnapshotError::Replay {
+                ply: 1,
+                error: ThudError::WrongPiece {
+                    square: (6, 0).into(),
+                    expected: Piece::Troll,
+                    found: Piece::Empty,
+                }
+            }
+        );
+
+        let mut snapshot = Snapshot::new(&played());
+        snapshot.board = Board::fresh();
+        assert_eq!(snapshot.restore().unwrap_err(), SnapshotError::Mismatch);
+    }
+
+    #[test]
+    fn out_of_time() {
+        let mut game = played();
+        game.out_of_time(Player::Dwarf);
+
+        let json = serde_json::to_string(&Snapshot::new(&game)).unwrap();
+        let mut restored = serde_json::from_str::<Snapshot>(&json)
+            .unwrap()
+            .restore()
+            .unwrap();
+        assert_eq!(
+            restored.winner(),
+            Some(EndState::Won(Player::Troll, EndReason::OutOfTime))
+        );
+    }
+
+    #[test]
+    fn resumed_partway_through_a_turn() {
+        let mut game = Thud::new();
+        game.move_piece((6, 0).into(), (6, 5).into()).unwrap();
+        game.move_piece((7, 8).into(), (7, 9).into()).unwrap();
+        let mut game = Thud::decode_position(&game.encode_position()).unwrap();
+        game.troll_cap((7, 9).into(), vec![]).unwrap();
+
+        let json = serde_json::to_string(&Snapshot::new(&game)).unwrap();
+        let restored = serde_json::from_str::<Snapshot>(&json)
+            .unwrap()
+            .restore()
+            .unwrap();
+        assert_eq!(restored.history(), game.history());
+        assert_eq!(restored.turn(), Some(Player::Dwarf));
+    }
+}
@@ -1,31 +1,129 @@
+use crate::event::Listeners;
 use crate::*;
 #[cfg(feature = "serialize")]
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+mod encoding;
+mod fen;
+
+pub use encoding::DecodeError;
 
 /// Stores the current state of a game of Thud
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
 pub struct Thud {
     board: Board,
     state: GameState,
+    end_offer: Option<Player>,
+    rules: Rules,
+    // Number of times each position has been seen since the last capture, by its hash, keeping
+    // the positions themselves in case two hash the same
+    positions: BTreeMap<u64, Vec<(Board, Player, usize)>>,
+    // Number of turns since the last capture
+    quiet_turns: usize,
+    start: Board,
+    start_to_move: Player,
+    // How far through the first turn the game started, for games set up partway through one
+    start_state: GameState,
+    start_offer: Option<Player>,
+    history: Vec<Action>,
+    // The player who lost on time, which isn't an action in the history
+    flagged: Option<Player>,
+    #[cfg_attr(feature = "serialize", serde(skip))]
+    listeners: Listeners,
 }
 
-#[derive(PartialEq)]
+#[derive(Debug, PartialEq, Copy, Clone)]
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
-enum GameState {
+pub(crate) enum GameState {
+    #[cfg_attr(feature = "serialize", serde(rename = "to_move"))]
     Nominal(Player),
-    PostTrollMove(bool),
+    #[cfg_attr(feature = "serialize", serde(rename = "troll_capture"))]
+    PostTrollMove { shoved: bool, troll: Coord },
+    #[cfg_attr(feature = "serialize", serde(rename = "ended"))]
     GameEnded(EndState),
 }
 
+impl Default for Thud {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Thud {
     /// Get a `Thud` ready to be played!
     pub fn new() -> Self {
+        Self::build(Board::fresh(), Player::Dwarf, Rules::default())
+    }
+
+    /// Get a `Thud` ready to be played, with some optional [`Rules`](struct.Rules.html) applied.
+    ///
+    /// Returns [`Err(ThudError::InvalidRules)`](enum.ThudError.html) if the rules fail
+    /// [`Rules::validate()`](struct.Rules.html#method.validate).
+    pub fn with_rules(rules: Rules) -> Result<Self, ThudError> {
+        rules.validate()?;
+        Ok(Self::build(Board::fresh(), Player::Dwarf, rules))
+    }
+
+    /// Start a game from a custom position, with `to_move` taking the first turn.
+    ///
+    /// Useful for puzzles and handicap games, see also [`Handicap`](enum.Handicap.html):
+    ///
+    /// ```
+    /// use thud::{Handicap, Player, Thud};
+    ///
+    /// let game = Thud::from_board(Handicap::TwoTrollsRemoved.board(), Player::Dwarf).unwrap();
+    /// assert_eq!(game.score(), (32, 24));
+    /// ```
+    ///
+    /// Returns [`Err(ThudError::InvalidBoard)`](enum.ThudError.html) if the board fails
+    /// [`Board::validate()`](struct.Board.html#method.validate).
+    pub fn from_board(board: Board, to_move: Player) -> Result<Self, ThudError> {
+        Self::from_board_with_rules(board, to_move, Rules::default())
+    }
+
+    fn build(board: Board, to_move: Player, rules: Rules) -> Self {
+        let mut positions = BTreeMap::new();
+        positions.insert(position_key(&board, to_move), vec![(board, to_move, 1)]);
+
         Thud {
-            board: Board::fresh(),
-            state: GameState::Nominal(Player::Dwarf),
+            board,
+            state: GameState::Nominal(to_move),
+            end_offer: None,
+            rules,
+            positions,
+            quiet_turns: 0,
+            start: board,
+            start_to_move: to_move,
+            start_state: GameState::Nominal(to_move),
+            start_offer: None,
+            history: Vec::new(),
+            flagged: None,
+            listeners: Listeners::default(),
         }
     }
 
+    /// Start a game from a custom position as with [`.from_board()`](#method.from_board), with
+    /// some optional [`Rules`](struct.Rules.html) applied.
+    ///
+    /// Also returns [`Err(ThudError::InvalidRules)`](enum.ThudError.html) if the rules fail
+    /// [`Rules::validate()`](struct.Rules.html#method.validate).
+    pub fn from_board_with_rules(
+        board: Board,
+        to_move: Player,
+        rules: Rules,
+    ) -> Result<Self, ThudError> {
+        board.validate().map_err(|_| ThudError::InvalidBoard)?;
+        rules.validate()?;
+        Ok(Self::build(board, to_move, rules))
+    }
+
+    /// Get the optional [`Rules`](struct.Rules.html) in play
+    pub fn rules(&self) -> Rules {
+        self.rules
+    }
+
     /// Find which player's turn it is.
     ///
     /// Will return `Some(Player)` if the game is still in progress, or if the game is ended
@@ -33,7 +131,7 @@ impl Thud {
     pub fn turn(&self) -> Option<Player> {
         match self.state {
             GameState::Nominal(p) => Some(p),
-            GameState::PostTrollMove(_) => Some(Player::Troll),
+            GameState::PostTrollMove { .. } => Some(Player::Troll),
             GameState::GameEnded(_) => None,
         }
     }
@@ -43,7 +141,161 @@ impl Thud {
         self.board
     }
 
+    /// Get the [`Board`](struct.Board.html) the game started from and the player who moved first
+    pub fn start(&self) -> (Board, Player) {
+        (self.start, self.start_to_move)
+    }
+
+    /// Get how far through the first turn the game started, and any end offer waiting then
+    pub(crate) fn start_phase(&self) -> (GameState, Option<Player>) {
+        (self.start_state, self.start_offer)
+    }
+
+    /// Set up a game which has just been started partway through a turn.
+    ///
+    /// Returns [`Err(ThudError::BadAction)`](enum.ThudError.html) if the phase doesn't fit the
+    /// board, or an end offer is made other than at the start of a turn.
+    pub(crate) fn set_start_phase(
+        &mut self,
+        state: GameState,
+        end_offer: Option<Player>,
+    ) -> Result<(), ThudError> {
+        let fits = match state {
+            GameState::PostTrollMove { troll, .. } => self.board.get(troll) == Piece::Troll,
+            _ => true,
+        };
+        let offer_fits = match (state, end_offer) {
+            (_, None) => true,
+            (GameState::Nominal(player), Some(offerer)) => player == offerer,
+            _ => false,
+        };
+        if !self.history.is_empty() || !fits || !offer_fits {
+            return Err(ThudError::BadAction);
+        }
+
+        self.state = state;
+        self.start_state = state;
+        self.end_offer = end_offer;
+        self.start_offer = end_offer;
+        Ok(())
+    }
+
+    /// Get the player who lost the game on time, if one did
+    #[cfg(feature = "serialize")]
+    pub(crate) fn flagged(&self) -> Option<Player> {
+        self.flagged
+    }
+
+    /// Get a copy of the game as it was before any actions were taken
+    fn restart(&self) -> Self {
+        let mut game = Self::build(self.start, self.start_to_move, self.rules);
+        game.set_start_phase(self.start_state, self.start_offer)
+            .expect("the starting phase was checked when it was set");
+        game
+    }
+
+    /// Get every [`Action`](enum.Action.html) successfully taken so far, oldest first
+    pub fn history(&self) -> &[Action] {
+        &self.history
+    }
+
+    /// Take back the last [`Action`](enum.Action.html) in the [history](#method.history),
+    /// returning it, or `None` if nothing has been done yet.
+    ///
+    /// The game is rebuilt by replaying the rest of the history from the
+    /// [start](#method.start). Listeners are kept, but aren't told about the replay; they get
+    /// [`Event::Rewound`](enum.Event.html) instead, then whose turn it now is.
+    ///
+    /// If the game was lost on time, only the loss is taken back, and `None` is returned.
+    ///
+    /// ```
+    /// use thud::{Player, Thud};
+    ///
+    /// let mut game = Thud::new();
+    /// game.move_piece((6, 0).into(), (6, 5).into()).unwrap();
+    /// assert!(game.undo().is_some());
+    /// assert_eq!(game.turn(), Some(Player::Dwarf));
+    /// assert_eq!(game.undo(), None);
+    /// ```
+    pub fn undo(&mut self) -> Option<Action> {
+        let mut history = self.history.clone();
+        let last = match self.flagged {
+            Some(_) => None,
+            None => Some(history.pop()?),
+        };
+
+        let mut game = self.restart();
+        for action in history {
+            game.apply(action)
+                .expect("actions in the history can be replayed");
+        }
+        game.listeners = std::mem::take(&mut self.listeners);
+        *self = game;
+        self.listeners.emit(Event::Rewound);
+        self.announce();
+        last
+    }
+
+    /// Take an [`Action`](enum.Action.html) by calling the corresponding method.
+    ///
+    /// Will pass errors from that method.
+    pub fn apply(&mut self, action: Action) -> Result<(), ThudError> {
+        match action {
+            Action::Move(src, target) => self.move_piece(src, target),
+            Action::Attack(src, target) => self.attack(src, target),
+            Action::Capture(troll, targets) => self.troll_cap(troll, targets),
+            Action::Resign(player) => self.resign(player).map(|_| ()),
+            Action::OfferEnd => self.offer_end(),
+            // Only the opponent of the player who offered can answer
+            Action::AcceptEnd => self.accept_end(self.answerer()?).map(|_| ()),
+            Action::DeclineEnd => self.decline_end(self.answerer()?),
+        }
+    }
+
+    /// Register `listener` to be told about every [`Event`](enum.Event.html) in the game from
+    /// now on.
+    ///
+    /// Listeners are called in the order they were added, as each event happens. They are not
+    /// carried over when the game is cloned or serialised.
+    ///
+    /// ```
+    /// use std::sync::mpsc;
+    /// use thud::{Event, Piece, Player, Thud};
+    ///
+    /// let mut game = Thud::new();
+    /// let (sender, events) = mpsc::channel();
+    /// game.listen(move |event| sender.send(*event).unwrap());
+    ///
+    /// game.apply("G1-G6".parse().unwrap()).unwrap();
+    /// assert_eq!(
+    ///     events.try_iter().collect::<Vec<_>>(),
+    ///     vec![
+    ///         Event::Moved {
+    ///             player: Player::Dwarf,
+    ///             piece: Piece::Dwarf,
+    ///             from: (6, 0).into(),
+    ///             to: (6, 5).into(),
+    ///         },
+    ///         Event::TurnChanged { player: Player::Troll },
+    ///     ]
+    /// );
+    /// ```
+    pub fn listen<F>(&mut self, listener: F)
+    where
+        F: FnMut(&Event) + Send + 'static,
+    {
+        self.listeners.add(Box::new(listener));
+    }
+
+    /// Remove every listener added with [`.listen()`](#method.listen)
+    pub fn clear_listeners(&mut self) {
+        self.listeners.clear();
+    }
+
     /// Wrapper for [`Board::winner()`](struct.Board.html#method.winner)
+    ///
+    /// A game which reaches a position with no moves left ends as the turn passes; one set up
+    /// that way ends here, and listeners are told then.
     pub fn winner(&mut self) -> Option<EndState> {
         // Note: checks cached game state, otherwise runs Board::winner()
         match self.state {
@@ -51,6 +303,7 @@ impl Thud {
             _ => match self.board.winner() {
                 Some(p) => {
                     self.state = GameState::GameEnded(p);
+                    self.listeners.emit(Event::GameEnded(p));
                     Some(p)
                 }
                 None => None,
@@ -73,17 +326,27 @@ impl Thud {
     /// Will pass errors from [`Board.dwarf_move()`](struct.Board.html#method.dwarf_move)
     /// and [`Board.troll_move()`](struct.Board.html#method.troll_move).
     pub fn move_piece(&mut self, src: Coord, target: Coord) -> Result<(), ThudError> {
+        if self.end_offer.is_some() {
+            return Err(ThudError::BadAction);
+        }
         match self.state {
             // If it's the dwarf player, move the dwarf and end the turn
             GameState::Nominal(Player::Dwarf) => {
                 self.board.dwarf_move(src, target)?;
-                self.state = GameState::Nominal(Player::Troll);
+                self.history.push(Action::Move(src, target));
+                self.moved(Player::Dwarf, src, target);
+                self.end_turn(Player::Troll, false);
                 Ok(())
             }
             // If it's the troll player, move the troll and enter GameState::PostTrollMove
             GameState::Nominal(Player::Troll) => {
                 self.board.troll_move(src, target)?;
-                self.state = GameState::PostTrollMove(false);
+                self.history.push(Action::Move(src, target));
+                self.moved(Player::Troll, src, target);
+                self.state = GameState::PostTrollMove {
+                    shoved: false,
+                    troll: target,
+                };
                 Ok(())
             }
             // Otherwise we can't move
@@ -101,53 +364,604 @@ impl Thud {
     ///
     /// Will pass errors from [`Board.dwarf_hurl()` and `Board.troll_shove()`](struct.Board.html).
     pub fn attack(&mut self, src: Coord, target: Coord) -> Result<(), ThudError> {
+        if self.end_offer.is_some() {
+            return Err(ThudError::BadAction);
+        }
         match self.state {
             // If it's the dwarf player's turn, perform the hurl and end the turn
             GameState::Nominal(Player::Dwarf) => {
                 self.board.dwarf_hurl(src, target)?;
-                self.state = GameState::Nominal(Player::Troll);
+                self.history.push(Action::Attack(src, target));
+                self.moved(Player::Dwarf, src, target);
+                self.listeners.emit(Event::Captured {
+                    player: Player::Dwarf,
+                    piece: Piece::Troll,
+                    square: target,
+                });
+                self.end_turn(Player::Troll, true);
                 Ok(())
             }
             // If it's the troll player's turn, perform the shove and enter
             // GameState::PostTrollMove with the shove flag set
             GameState::Nominal(Player::Troll) => {
                 self.board.troll_shove(src, target)?;
-                self.state = GameState::PostTrollMove(true);
+                self.history.push(Action::Attack(src, target));
+                self.moved(Player::Troll, src, target);
+                self.state = GameState::PostTrollMove {
+                    shoved: true,
+                    troll: target,
+                };
                 Ok(())
             }
             _ => Err(ThudError::BadAction),
         }
     }
 
+    /// Find which dwarves the troll at `troll` could capture to finish the troll player's turn
+    ///
+    /// This may only be called after a move or a shove/attack on a troll player's turn, otherwise
+    /// [`Err(ThudError::BadAction)`](enum.ThudError.html) will be returned. Whether the previous
+    /// action was a shove is taken into account.
+    ///
+    /// Returns [`Err(ThudError::WrongTroll)`](enum.ThudError.html) if `troll` is not the troll
+    /// which was just moved.
+    pub fn capture_options(&self, troll: Coord) -> Result<CaptureOptions, ThudError> {
+        match self.state {
+            GameState::PostTrollMove {
+                shoved,
+                troll: moved,
+            } => {
+                if troll != moved {
+                    return Err(ThudError::WrongTroll {
+                        moved,
+                        chosen: troll,
+                    });
+                }
+                self.board.capture_options(troll, shoved)
+            }
+            _ => Err(ThudError::BadAction),
+        }
+    }
+
+    /// Find the troll which has just moved, if the troll player is yet to finish their turn with
+    /// [`.troll_cap()`](#method.troll_cap)
+    pub fn pending_capture(&self) -> Option<Coord> {
+        match self.state {
+            GameState::PostTrollMove { troll, .. } => Some(troll),
+            _ => None,
+        }
+    }
+
+    /// Get every move, attack and capture which can currently be taken.
+    ///
+    /// If an end to the game has been proposed, this will instead be
+    /// [`Action::AcceptEnd`](enum.Action.html) and [`Action::DeclineEnd`](enum.Action.html).
+    /// Resigning and proposing an end are never included.
+    ///
+    /// The order is fixed: pieces are taken in the order given by
+    /// [`Board::army()`](struct.Board.html#method.army), and for each piece its targets in the
+    /// order given by [`Board::available_moves()`](struct.Board.html#method.available_moves),
+    /// with a move before an attack. Captures are in the order given by
+    /// [`CaptureOptions::subsets()`](struct.CaptureOptions.html#method.subsets).
+    ///
+    /// ```
+    /// use thud::Thud;
+    ///
+    /// let game = Thud::new();
+    /// assert_eq!(game.legal_actions().len(), 656);
+    /// ```
+    pub fn legal_actions(&self) -> Vec<Action> {
+        if self.end_offer.is_some() {
+            return vec![Action::AcceptEnd, Action::DeclineEnd];
+        }
+
+        let mut actions = Vec::new();
+        match self.state {
+            GameState::Nominal(player) => {
+                let piece = match player {
+                    Player::Dwarf => Piece::Dwarf,
+                    Player::Troll => Piece::Troll,
+                };
+                for src in self.board.army(piece) {
+                    for target in self.board.available_moves(src) {
+                        // Try each action out on a copy of the board
+                        let (mut moved, mut attacked) = (self.board, self.board);
+                        let (move_result, attack_result) = match player {
+                            Player::Dwarf => (
+                                moved.dwarf_move(src, target),
+                                attacked.dwarf_hurl(src, target),
+                            ),
+                            Player::Troll => (
+                                moved.troll_move(src, target),
+                                attacked.troll_shove(src, target),
+                            ),
+                        };
+                        if move_result.is_ok() {
+                            actions.push(Action::Move(src, target));
+                        }
+                        if attack_result.is_ok() {
+                            actions.push(Action::Attack(src, target));
+                        }
+                    }
+                }
+            }
+            GameState::PostTrollMove { shoved, troll } => {
+                if let Ok(options) = self.board.capture_options(troll, shoved) {
+                    for targets in options.subsets() {
+                        actions.push(Action::Capture(troll, targets));
+                    }
+                }
+            }
+            GameState::GameEnded(_) => (),
+        }
+        actions
+    }
+
     /// Capture a number of dwarves with a troll
     ///
-    /// This may only be called after a move or a shove/attack on a troll player's turn.
+    /// This may only be called after a move or a shove/attack on a troll player's turn,
+    /// otherwise [`Err(ThudError::BadAction)`](enum.ThudError.html) will be returned, and
+    /// `troll` must be the troll which was moved, otherwise
+    /// [`Err(ThudError::WrongTroll)`](enum.ThudError.html) will be returned.
     ///
     /// If the previous action was a shove/attack then `targets` *must contain at least 1 valid
-    /// dwarf to take*, otherwise [`Err(ThudError::IllegalMove)`](enum.ThudError.html) will be
+    /// dwarf to take*, otherwise [`Err(ThudError::MustCapture)`](enum.ThudError.html) will be
     /// returned and the method must be called again before play can continue.
     ///
     /// Otherwise, the turn will be ticked over automatically.
+    ///
+    /// Only the `targets` which capture a dwarf are recorded in the
+    /// [history](#method.history), in the order given by
+    /// [`Direction::all()`](enum.Direction.html#method.all).
     pub fn troll_cap(&mut self, troll: Coord, targets: Vec<Direction>) -> Result<(), ThudError> {
-        match self.state {
-            // If this is after a shove, perform the move then ensure at least 1 dwarf was taken
-            // (error if not) then end the turn
-            GameState::PostTrollMove(true) => {
-                let captured = self.board.troll_capture(troll, targets)?;
-                if captured == 0 {
-                    Err(ThudError::IllegalMove)
-                } else {
-                    self.state = GameState::Nominal(Player::Dwarf);
-                    Ok(())
-                }
-            }
-            // If this is after a move, perform the move then end the turn
-            GameState::PostTrollMove(false) => {
-                self.board.troll_capture(troll, targets)?;
-                self.state = GameState::Nominal(Player::Dwarf);
+        let options = self.capture_options(troll)?;
+        let targets: Vec<Direction> = options
+            .directions()
+            .iter()
+            .filter(|dir| targets.contains(dir))
+            .copied()
+            .collect();
+
+        // If this is after a shove, ensure at least 1 dwarf is taken (error if not)
+        if options.must_capture() && targets.is_empty() {
+            return Err(ThudError::MustCapture(troll));
+        }
+
+        let squares: Vec<Coord> = targets
+            .iter()
+            .filter_map(|dir| dir.modify(troll).ok())
+            .filter(|&square| self.board.get(square) == Piece::Dwarf)
+            .collect();
+        let captured = self.board.troll_capture(troll, targets.clone())?;
+        self.history.push(Action::Capture(troll, targets));
+        for square in squares {
+            self.listeners.emit(Event::Captured {
+                player: Player::Troll,
+                piece: Piece::Dwarf,
+                square,
+            });
+        }
+        self.end_turn(Player::Dwarf, captured > 0);
+        Ok(())
+    }
+
+    /// Concede the game on behalf of `player`, handing victory to their opponent
+    ///
+    /// This may be done at any point before the game has ended, on either player's turn,
+    /// otherwise [`Err(ThudError::BadAction)`](enum.ThudError.html) will be returned.
+    pub fn resign(&mut self, player: Player) -> Result<EndState, ThudError> {
+        if let GameState::GameEnded(_) = self.state {
+            return Err(ThudError::BadAction);
+        }
+
+        let end = EndState::Won(player.opponent(), EndReason::Resignation);
+        self.history.push(Action::Resign(player));
+        self.end_offer = None;
+        self.state = GameState::GameEnded(end);
+        self.announce();
+        Ok(end)
+    }
+
+    /// End the game because `player` ran out of time, handing victory to their opponent
+    ///
+    /// Nothing is added to the history, the loss is kept apart from it. Does nothing if the game
+    /// has already ended.
+    pub(crate) fn out_of_time(&mut self, player: Player) {
+        if let GameState::GameEnded(_) = self.state {
+            return;
+        }
+        self.flagged = Some(player);
+        self.end_offer = None;
+        self.state = GameState::GameEnded(EndState::Won(player.opponent(), EndReason::OutOfTime));
+        self.announce();
+    }
+
+    /// Propose ending the game, on behalf of the player whose turn it is
+    ///
+    /// This can only be done at the start of a turn, otherwise
+    /// [`Err(ThudError::BadAction)`](enum.ThudError.html) will be returned.
+    ///
+    /// Until the opponent responds with [`.accept_end()`](#method.accept_end) or
+    /// [`.decline_end()`](#method.decline_end), no moves may be made.
+    pub fn offer_end(&mut self) -> Result<(), ThudError> {
+        match (self.state, self.end_offer) {
+            (GameState::Nominal(player), None) => {
+                self.history.push(Action::OfferEnd);
+                self.end_offer = Some(player);
                 Ok(())
             }
             _ => Err(ThudError::BadAction),
         }
     }
+
+    /// Find which player has proposed ending the game, if either
+    pub fn end_offer(&self) -> Option<Player> {
+        self.end_offer
+    }
+
+    /// Accept the opponent's proposal to end the game, on behalf of `player`
+    ///
+    /// The game will be scored as with [`Board::score()`](struct.Board.html#method.score) and
+    /// the result will carry [`EndReason::Agreement`](enum.EndReason.html).
+    ///
+    /// Returns [`Err(ThudError::BadAction)`](enum.ThudError.html) if `player`'s opponent hasn't
+    /// made a proposal.
+    pub fn accept_end(&mut self, player: Player) -> Result<EndState, ThudError> {
+        if self.answerer()? != player {
+            return Err(ThudError::BadAction);
+        }
+
+        self.end_offer = None;
+        let end = EndState::from_score(self.board.score(), EndReason::Agreement);
+        self.history.push(Action::AcceptEnd);
+        self.state = GameState::GameEnded(end);
+        self.announce();
+        Ok(end)
+    }
+
+    /// Turn down the opponent's proposal to end the game, on behalf of `player`
+    ///
+    /// The player who made the proposal continues their turn.
+    ///
+    /// Returns [`Err(ThudError::BadAction)`](enum.ThudError.html) if `player`'s opponent hasn't
+    /// made a proposal.
+    pub fn decline_end(&mut self, player: Player) -> Result<(), ThudError> {
+        if self.answerer()? != player {
+            return Err(ThudError::BadAction);
+        }
+
+        self.end_offer = None;
+        self.history.push(Action::DeclineEnd);
+        Ok(())
+    }
+
+    /// Find the player who has to answer the proposal to end the game
+    fn answerer(&self) -> Result<Player, ThudError> {
+        self.end_offer
+            .map(|player| player.opponent())
+            .ok_or(ThudError::BadAction)
+    }
+
+    /// Hand the turn over to `next`, ending the game if any of the optional rules say so or a
+    /// player has no moves left
+    fn end_turn(&mut self, next: Player, captured: bool) {
+        self.state = GameState::Nominal(next);
+
+        if captured {
+            // No position from before a capture can come up again
+            self.positions.clear();
+            self.quiet_turns = 0;
+        } else {
+            self.quiet_turns += 1;
+        }
+        let board = self.board;
+        let same = self
+            .positions
+            .entry(position_key(&board, next))
+            .or_default();
+        let seen = match same
+            .iter_mut()
+            .find(|(other, player, _)| *other == board && *player == next)
+        {
+            Some((_, _, seen)) => {
+                *seen += 1;
+                *seen
+            }
+            None => {
+                same.push((board, next, 1));
+                1
+            }
+        };
+
+        if matches!(self.rules.repetition, Some(limit) if seen >= limit) {
+            self.state = GameState::GameEnded(EndState::Draw(EndReason::Repetition));
+        } else if matches!(self.rules.move_limit, Some(limit) if self.quiet_turns >= limit) {
+            self.state = GameState::GameEnded(EndState::Draw(EndReason::MoveLimit));
+        } else if let Some(end) = self.board.winner() {
+            self.state = GameState::GameEnded(end);
+        }
+        self.announce();
+    }
+
+    /// Tell listeners that `player` moved the piece now at `target` there from `src`
+    fn moved(&mut self, player: Player, src: Coord, target: Coord) {
+        self.listeners.emit(Event::Moved {
+            player,
+            piece: self.board.get(target),
+            from: src,
+            to: target,
+        });
+    }
+
+    /// Tell listeners whose turn it now is, or how the game ended
+    fn announce(&mut self) {
+        match self.state {
+            GameState::Nominal(player) => self.listeners.emit(Event::TurnChanged { player }),
+            GameState::GameEnded(end) => self.listeners.emit(Event::GameEnded(end)),
+            GameState::PostTrollMove { .. } => (),
+        }
+    }
+}
+
+/// Identify a position along with the player to move
+fn position_key(board: &Board, to_move: Player) -> u64 {
+    match to_move {
+        Player::Dwarf => board.position_hash(),
+        Player::Troll => !board.position_hash(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resign() {
+        let mut game = Thud::new();
+        assert_eq!(
+            game.resign(Player::Troll),
+            Ok(EndState::Won(Player::Dwarf, EndReason::Resignation))
+        );
+        assert_eq!(game.turn(), None);
+        assert_eq!(game.resign(Player::Dwarf), Err(ThudError::BadAction));
+    }
+
+    #[test]
+    fn agreed_end() {
+        let mut game = Thud::new();
+        game.offer_end().unwrap();
+        assert_eq!(game.end_offer(), Some(Player::Dwarf));
+        assert_eq!(
+            game.move_piece((6, 0).into(), (6, 5).into()),
+            Err(ThudError::BadAction)
+        );
+        // The player who offered can't accept for their opponent
+        assert_eq!(game.accept_end(Player::Dwarf), Err(ThudError::BadAction));
+        assert_eq!(game.decline_end(Player::Dwarf), Err(ThudError::BadAction));
+        assert_eq!(
+            game.accept_end(Player::Troll),
+            Ok(EndState::Draw(EndReason::Agreement))
+        );
+        assert_eq!(game.winner(), Some(EndState::Draw(EndReason::Agreement)));
+    }
+
+    #[test]
+    fn declined_end() {
+        let mut game = Thud::new();
+        assert_eq!(game.accept_end(Player::Troll), Err(ThudError::BadAction));
+        game.offer_end().unwrap();
+        assert_eq!(game.offer_end(), Err(ThudError::BadAction));
+        game.decline_end(Player::Troll).unwrap();
+        assert_eq!(game.end_offer(), None);
+        game.move_piece((6, 0).into(), (6, 5).into()).unwrap();
+        assert_eq!(game.turn(), Some(Player::Troll));
+    }
+
+    #[test]
+    fn from_board() {
+        let mut board = Board::fresh();
+        board.place((8, 7).into(), Piece::Empty);
+        let game = Thud::from_board(board, Player::Troll).unwrap();
+        assert_eq!(game.turn(), Some(Player::Troll));
+        assert_eq!(game.score(), (32, 28));
+
+        board.place((0, 7).into(), Piece::Thudstone);
+        assert!(Thud::from_board(board, Player::Dwarf).is_err());
+        assert!(Thud::from_board(Board::default(), Player::Dwarf).is_err());
+    }
+
+    #[test]
+    fn history() {
+        let mut game = Thud::new();
+        let actions = [
+            Action::Move((6, 0).into(), (6, 5).into()),
+            Action::OfferEnd,
+            Action::DeclineEnd,
+            Action::Move((8, 7).into(), (9, 7).into()),
+            Action::Capture((9, 7).into(), vec![]),
+            Action::Resign(Player::Dwarf),
+        ];
+        for action in actions.iter() {
+            game.apply(action.clone()).unwrap();
+        }
+        assert_eq!(game.history(), &actions);
+        assert!(game.apply(Action::OfferEnd).is_err());
+        assert_eq!(game.history().len(), actions.len());
+
+        assert_eq!(game.undo(), Some(Action::Resign(Player::Dwarf)));
+        assert_eq!(game.turn(), Some(Player::Dwarf));
+        assert_eq!(game.board().get((9, 7).into()), Piece::Troll);
+        assert_eq!(game.history(), &actions[..actions.len() - 1]);
+        assert_eq!(game.pending_capture(), None);
+        game.undo();
+        assert_eq!(game.pending_capture(), Some((9, 7).into()));
+    }
+
+    #[test]
+    fn undo_out_of_time() {
+        let mut game = Thud::new();
+        game.move_piece((6, 0).into(), (6, 5).into()).unwrap();
+        game.out_of_time(Player::Troll);
+        assert_eq!(
+            game.winner(),
+            Some(EndState::Won(Player::Dwarf, EndReason::OutOfTime))
+        );
+
+        assert_eq!(game.undo(), None);
+        assert_eq!(game.turn(), Some(Player::Troll));
+        assert_eq!(game.history().len(), 1);
+        assert_eq!(
+            game.undo(),
+            Some(Action::Move((6, 0).into(), (6, 5).into()))
+        );
+    }
+
+    // Shuffle a dwarf and a troll back and forth, returning to the starting position
+    fn shuffle(game: &mut Thud) {
+        game.move_piece((6, 0).into(), (6, 5).into()).unwrap();
+        game.move_piece((8, 7).into(), (9, 7).into()).unwrap();
+        game.troll_cap((9, 7).into(), vec![]).unwrap();
+        game.move_piece((6, 5).into(), (6, 0).into()).unwrap();
+        game.move_piece((9, 7).into(), (8, 7).into()).unwrap();
+        game.troll_cap((8, 7).into(), vec![]).unwrap();
+    }
+
+    #[test]
+    fn repetition() {
+        let mut game = Thud::with_rules(Rules {
+            repetition: Some(3),
+            ..Rules::default()
+        })
+        .unwrap();
+        shuffle(&mut game);
+        assert_eq!(game.turn(), Some(Player::Dwarf));
+        shuffle(&mut game);
+        assert_eq!(game.turn(), None);
+        assert_eq!(game.winner(), Some(EndState::Draw(EndReason::Repetition)));
+    }
+
+    #[test]
+    fn repetition_hash_collision() {
+        let mut game = Thud::with_rules(Rules {
+            repetition: Some(2),
+            ..Rules::default()
+        })
+        .unwrap();
+        // Pretend another position has the same hash as the one G1-G6 reaches
+        let mut after = Board::fresh();
+        after.dwarf_move((6, 0).into(), (6, 5).into()).unwrap();
+        game.positions.insert(
+            position_key(&after, Player::Troll),
+            vec![(Board::fresh(), Player::Troll, 1)],
+        );
+        game.move_piece((6, 0).into(), (6, 5).into()).unwrap();
+        assert_eq!(game.turn(), Some(Player::Troll));
+    }
+
+    #[test]
+    fn invalid_rules() {
+        for (repetition, move_limit) in [(Some(0), None), (Some(1), None), (None, Some(0))] {
+            let rules = Rules {
+                repetition,
+                move_limit,
+            };
+            assert_eq!(
+                Thud::with_rules(rules).unwrap_err(),
+                ThudError::InvalidRules
+            );
+            assert_eq!(
+                Thud::from_board_with_rules(Board::fresh(), Player::Dwarf, rules).unwrap_err(),
+                ThudError::InvalidRules
+            );
+        }
+        assert!(Thud::with_rules(Rules {
+            repetition: Some(2),
+            move_limit: Some(1),
+        })
+        .is_ok());
+    }
+
+    #[test]
+    fn move_limit() {
+        let mut game = Thud::with_rules(Rules {
+            move_limit: Some(3),
+            ..Rules::default()
+        })
+        .unwrap();
+        game.move_piece((6, 0).into(), (6, 5).into()).unwrap();
+        game.move_piece((8, 7).into(), (9, 7).into()).unwrap();
+        game.troll_cap((9, 7).into(), vec![]).unwrap();
+        assert_eq!(game.turn(), Some(Player::Dwarf));
+        game.move_piece((6, 5).into(), (6, 0).into()).unwrap();
+        assert_eq!(game.winner(), Some(EndState::Draw(EndReason::MoveLimit)));
+    }
+
+    #[test]
+    fn events() {
+        use std::sync::mpsc;
+
+        let mut game = Thud::new();
+        let (sender, events) = mpsc::channel();
+        game.listen(move |event| sender.send(*event).unwrap());
+        game.move_piece((6, 0).into(), (6, 5).into()).unwrap();
+        events.try_iter().for_each(drop);
+
+        game.move_piece((7, 6).into(), (7, 5).into()).unwrap();
+        game.troll_cap((7, 5).into(), vec![Direction::Left, Direction::Right])
+            .unwrap();
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            vec![
+                Event::Moved {
+                    player: Player::Troll,
+                    piece: Piece::Troll,
+                    from: (7, 6).into(),
+                    to: (7, 5).into(),
+                },
+                Event::Captured {
+                    player: Player::Troll,
+                    piece: Piece::Dwarf,
+                    square: (6, 5).into(),
+                },
+                Event::TurnChanged {
+                    player: Player::Dwarf
+                },
+            ]
+        );
+
+        // Copies of the game are left without listeners
+        game.clone().resign(Player::Dwarf).unwrap();
+        assert_eq!(events.try_iter().next(), None);
+        game.undo();
+        assert_eq!(events.try_iter().collect::<Vec<_>>(), vec![Event::Rewound]);
+        game.resign(Player::Dwarf).unwrap();
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            vec![Event::GameEnded(EndState::Won(
+                Player::Troll,
+                EndReason::Resignation
+            ))]
+        );
+    }
+
+    #[test]
+    fn no_moves_left() {
+        use std::sync::mpsc;
+
+        let mut board = Board::default();
+        board.place((7, 7).into(), Piece::Thudstone);
+        board.place((5, 5).into(), Piece::Troll);
+        board.place((5, 7).into(), Piece::Dwarf);
+        let mut game = Thud::from_board(board, Player::Troll).unwrap();
+        let (sender, events) = mpsc::channel();
+        game.listen(move |event| sender.send(*event).unwrap());
+
+        game.move_piece((5, 5).into(), (5, 6).into()).unwrap();
+        game.troll_cap((5, 6).into(), vec![Direction::Up]).unwrap();
+        let end = EndState::Won(Player::Troll, EndReason::NoMoves);
+        assert_eq!(game.turn(), None);
+        assert_eq!(events.try_iter().last(), Some(Event::GameEnded(end)));
+        assert_eq!(game.winner(), Some(end));
+        assert_eq!(events.try_iter().next(), None);
+    }
 }
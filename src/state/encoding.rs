@@ -0,0 +1,556 @@
+use super::GameState;
+use crate::*;
+
+/// Version byte at the start of an encoded game
+const GAME_FORMAT: u8 = 1;
+
+// Phase flags, see `Thud::encode_position()`
+const TROLL_TO_MOVE: u8 = 1;
+const CAPTURE_PENDING: u8 = 1 << 1;
+const SHOVED: u8 = 1 << 2;
+const ENDED: u8 = 1 << 3;
+const END_OFFERED: u8 = 1 << 4;
+
+// Rule flags, see `Thud::encode()`
+const REPETITION: u8 = 1;
+const MOVE_LIMIT: u8 = 1 << 1;
+
+/// Reports a problem decoding a position or game encoded with
+/// [`Thud::encode_position()`](struct.Thud.html#method.encode_position) or
+/// [`Thud::encode()`](struct.Thud.html#method.encode)
+#[derive(Debug, PartialEq, Copy, Clone, Error)]
+pub enum DecodeError {
+    #[error("Ran out of bytes")]
+    UnexpectedEnd,
+    #[error("{0} bytes were left over")]
+    TrailingBytes(usize),
+    #[error("Integer is too large")]
+    Overflow,
+    #[error("Format version {0} is not supported")]
+    UnsupportedVersion(u8),
+    #[error("Phase flags {0:#x} are not valid")]
+    InvalidPhase(u8),
+    #[error("Board is not a valid Thud position")]
+    InvalidBoard,
+    #[error("Action {0} is not legal")]
+    IllegalAction(usize),
+    #[error("The player who lost on time is not valid")]
+    InvalidFlag,
+    #[error("Rules are not valid")]
+    InvalidRules,
+}
+
+impl Thud {
+    /// Encode the current position compactly, as bytes.
+    ///
+    /// The position consists of the [`Board`](struct.Board.html), whose turn it is, and how far
+    /// through the turn they are; the [history](#method.history) and
+    /// [`Rules`](struct.Rules.html) are not included. It is laid out as:
+    ///
+    /// - 2 bits per square on the board in the order `(0, 5), (0, 6), ..., (14, 9)`, ie. every
+    ///   valid [`Coord`](struct.Coord.html) by `x` then `y`, packed from the lowest bit upward
+    /// - A byte of phase flags, from the lowest bit: troll to move, troll capture pending,
+    ///   troll was shoved, game ended, end of game proposed
+    /// - If a troll capture is pending, a byte giving the troll's square as an index into the
+    ///   order above
+    /// - If the game ended, a byte giving the [`EndState`](enum.EndState.html)
+    ///
+    /// ```
+    /// use thud::Thud;
+    ///
+    /// let bytes = Thud::new().encode_position();
+    /// assert_eq!(bytes.len(), 43);
+    ///
+    /// let game = Thud::decode_position(&bytes).unwrap();
+    /// assert_eq!(game.board(), Thud::new().board());
+    /// ```
+    pub fn encode_position(&self) -> Vec<u8> {
+        let mut bytes = encode_board(&self.board);
+
+        let mut flags = 0;
+        let mut extra = Vec::new();
+        match self.state {
+            GameState::Nominal(Player::Dwarf) => (),
+            GameState::Nominal(Player::Troll) => flags |= TROLL_TO_MOVE,
+            GameState::PostTrollMove { shoved, troll } => {
+                flags |= TROLL_TO_MOVE | CAPTURE_PENDING;
+                if shoved {
+                    flags |= SHOVED;
+                }
+                extra.push(square_index(troll));
+            }
+            GameState::GameEnded(end) => {
+                flags |= ENDED;
+                extra.push(encode_end(end));
+            }
+        }
+        if self.end_offer.is_some() {
+            flags |= END_OFFERED;
+        }
+
+        bytes.push(flags);
+        bytes.extend(extra);
+        bytes
+    }
+
+    /// Decode a position encoded with [`.encode_position()`](#method.encode_position).
+    ///
+    /// The game returned starts from the decoded position, with no history and no optional
+    /// [`Rules`](struct.Rules.html).
+    pub fn decode_position(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader { bytes };
+        let game = read_position(&mut reader)?;
+        reader.finish()?;
+        Ok(game)
+    }
+
+    /// Encode the whole game compactly, as bytes.
+    ///
+    /// The starting position, laid out as with [`.encode_position()`](#method.encode_position),
+    /// [`Rules`](struct.Rules.html) and [history](#method.history) are stored. The rules are a
+    /// byte of flags, with the lowest bit set if there is a repetition limit and the next if
+    /// there is a move limit, then each limit present. Integers are written as
+    /// [LEB128](https://en.wikipedia.org/wiki/LEB128), and each
+    /// [`Action`](enum.Action.html) is encoded as an index into the [`.legal_actions()`](#method.legal_actions) at that point, followed by
+    /// proposing an end, then resigning as each player. Most actions take a single byte. A final
+    /// byte gives the player who lost on time, if either did: 1 for the dwarf player and 2 for
+    /// the troll player.
+    ///
+    /// ```
+    /// use thud::Thud;
+    ///
+    /// let mut game = Thud::new();
+    /// game.move_piece((6, 0).into(), (6, 5).into()).unwrap();
+    ///
+    /// let decoded = Thud::decode(&game.encode()).unwrap();
+    /// assert_eq!(decoded.history(), game.history());
+    /// ```
+    pub fn encode(&self) -> Vec<u8> {
+        let mut replay = self.restart();
+        let mut bytes = vec![GAME_FORMAT];
+        bytes.extend(replay.encode_position());
+        let mut flags = 0;
+        if self.rules.repetition.is_some() {
+            flags |= REPETITION;
+        }
+        if self.rules.move_limit.is_some() {
+            flags |= MOVE_LIMIT;
+        }
+        bytes.push(flags);
+        for limit in [self.rules.repetition, self.rules.move_limit]
+            .iter()
+            .flatten()
+        {
+            write_varint(&mut bytes, *limit);
+        }
+        write_varint(&mut bytes, self.history.len());
+
+        for action in self.history.iter() {
+            let index = coded_actions(&replay)
+                .iter()
+                .position(|legal| legal == action)
+                .expect("history holds only legal actions");
+            write_varint(&mut bytes, index);
+            replay
+                .apply(action.clone())
+                .expect("history holds only legal actions");
+        }
+        bytes.push(match self.flagged {
+            None => 0,
+            Some(Player::Dwarf) => 1,
+            Some(Player::Troll) => 2,
+        });
+        bytes
+    }
+
+    /// Decode a game encoded with [`.encode()`](#method.encode), replaying its history.
+    pub fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader { bytes };
+        let version = reader.byte()?;
+        if version != GAME_FORMAT {
+            return Err(DecodeError::UnsupportedVersion(version));
+        }
+        let mut game = read_position(&mut reader)?;
+        // Nothing has been played yet, so the rules can be swapped in
+        let flags = reader.byte()?;
+        if flags & !(REPETITION | MOVE_LIMIT) != 0 {
+            return Err(DecodeError::InvalidRules);
+        }
+        let mut limit = |flag| match flags & flag {
+            0 => Ok(None),
+            _ => reader.varint().map(Some),
+        };
+        game.rules = Rules {
+            repetition: limit(REPETITION)?,
+            move_limit: limit(MOVE_LIMIT)?,
+        };
+        game.rules
+            .validate()
+            .map_err(|_| DecodeError::InvalidRules)?;
+
+        let len = reader.varint()?;
+        for ply in 0..len {
+            let index = reader.varint()?;
+            let action = coded_actions(&game)
+                .into_iter()
+                .nth(index)
+                .ok_or(DecodeError::IllegalAction(ply))?;
+            game.apply(action)
+                .map_err(|_| DecodeError::IllegalAction(ply))?;
+        }
+        match reader.byte()? {
+            0 => (),
+            1 => game.out_of_time(Player::Dwarf),
+            2 => game.out_of_time(Player::Troll),
+            _ => return Err(DecodeError::InvalidFlag),
+        }
+
+        reader.finish()?;
+        Ok(game)
+    }
+}
+
+/// Every action which can be encoded in the current position, in the order used for encoding
+fn coded_actions(game: &Thud) -> Vec<Action> {
+    let mut actions = game.legal_actions();
+    match game.state {
+        GameState::GameEnded(_) => (),
+        GameState::Nominal(_) if game.end_offer.is_none() => {
+            actions.push(Action::OfferEnd);
+            actions.extend([Action::Resign(Player::Dwarf), Action::Resign(Player::Troll)]);
+        }
+        _ => actions.extend([Action::Resign(Player::Dwarf), Action::Resign(Player::Troll)]),
+    }
+    actions
+}
+
+/// Every valid square on the board, in encoding order
+fn squares() -> impl Iterator<Item = Coord> {
+    (0..15)
+        .flat_map(|x| (0..15).map(move |y| (x, y)))
+        .filter_map(|(x, y)| Coord::zero_based(x, y).ok())
+}
+
+fn square_index(square: Coord) -> u8 {
+    squares().position(|other| other == square).unwrap() as u8
+}
+
+fn encode_board(board: &Board) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for (i, square) in squares().enumerate() {
+        if i % 4 == 0 {
+            bytes.push(0);
+        }
+        *bytes.last_mut().unwrap() |= board.get(square).code() << ((i % 4) * 2);
+    }
+    bytes
+}
+
+fn encode_end(end: EndState) -> u8 {
+    let (result, reason) = match end {
+        EndState::Won(Player::Dwarf, reason) => (0, reason),
+        EndState::Won(Player::Troll, reason) => (1, reason),
+        EndState::Draw(reason) => (2, reason),
+    };
+    let reason = match reason {
+        EndReason::NoMoves => 0,
+        EndReason::Agreement => 1,
+        EndReason::Resignation => 2,
+        EndReason::Repetition => 3,
+        EndReason::MoveLimit => 4,
+        EndReason::OutOfTime => 5,
+    };
+    result | (reason << 2)
+}
+
+fn decode_end(byte: u8) -> Option<EndState> {
+    let reason = match byte >> 2 {
+        0 => EndReason::NoMoves,
+        1 => EndReason::Agreement,
+        2 => EndReason::Resignation,
+        3 => EndReason::Repetition,
+        4 => EndReason::MoveLimit,
+        5 => EndReason::OutOfTime,
+        _ => return None,
+    };
+    match byte & 0b11 {
+        0 => Some(EndState::Won(Player::Dwarf, reason)),
+        1 => Some(EndState::Won(Player::Troll, reason)),
+        2 => Some(EndState::Draw(reason)),
+        _ => None,
+    }
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: usize) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes.push(byte);
+            return;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+fn read_board(reader: &mut Reader) -> Result<Board, DecodeError> {
+    let mut board = Board::default();
+    let mut byte = 0;
+    for (i, square) in squares().enumerate() {
+        if i % 4 == 0 {
+            byte = reader.byte()?;
+        }
+        board.place(square, Piece::from_code(byte >> ((i % 4) * 2)));
+    }
+    Ok(board)
+}
+
+fn read_position(reader: &mut Reader) -> Result<Thud, DecodeError> {
+    let board = read_board(reader)?;
+    let flags = reader.byte()?;
+    let invalid = Err(DecodeError::InvalidPhase(flags));
+
+    let to_move = if flags & TROLL_TO_MOVE == 0 {
+        Player::Dwarf
+    } else {
+        Player::Troll
+    };
+    let mut game = Thud::from_board(board, to_move).map_err(|_| DecodeError::InvalidBoard)?;
+
+    let state = match flags & !END_OFFERED {
+        0 | TROLL_TO_MOVE => GameState::Nominal(to_move),
+        ENDED => match decode_end(reader.byte()?) {
+            Some(end) => GameState::GameEnded(end),
+            None => return invalid,
+        },
+        _ if flags & (TROLL_TO_MOVE | CAPTURE_PENDING) == TROLL_TO_MOVE | CAPTURE_PENDING
+            && flags & ENDED == 0 =>
+        {
+            match squares().nth(reader.byte()? as usize) {
+                Some(troll) => GameState::PostTrollMove {
+                    shoved: flags & SHOVED != 0,
+                    troll,
+                },
+                None => return invalid,
+            }
+        }
+        _ => return invalid,
+    };
+
+    let end_offer = match flags & END_OFFERED {
+        0 => None,
+        _ => Some(to_move),
+    };
+    match game.set_start_phase(state, end_offer) {
+        Ok(()) => Ok(game),
+        Err(_) => invalid,
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> Result<u8, DecodeError> {
+        let (first, rest) = self.bytes.split_first().ok_or(DecodeError::UnexpectedEnd)?;
+        self.bytes = rest;
+        Ok(*first)
+    }
+
+    fn varint(&mut self) -> Result<usize, DecodeError> {
+        let mut value: usize = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.byte()?;
+            let chunk = (byte & 0x7f) as usize;
+            // Make sure no bits are lost off the top
+            if shift >= usize::BITS || (chunk << shift) >> shift != chunk {
+                return Err(DecodeError::Overflow);
+            }
+            value |= chunk << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+            shift += 7;
+        }
+    }
+
+    fn finish(self) -> Result<(), DecodeError> {
+        match self.bytes.len() {
+            0 => Ok(()),
+            left => Err(DecodeError::TrailingBytes(left)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// Play a game by picking from the legal actions with `choices`
+    fn play(choices: &[usize], meta: bool) -> Thud {
+        play_from(Thud::new(), choices, meta)
+    }
+
+    /// Carry on `game` by picking from the legal actions with `choices`
+    fn play_from(mut game: Thud, choices: &[usize], meta: bool) -> Thud {
+        for choice in choices {
+            let actions = if meta {
+                coded_actions(&game)
+            } else {
+                game.legal_actions()
+            };
+            if actions.is_empty() {
+                break;
+            }
+            game.apply(actions[choice % actions.len()].clone()).unwrap();
+        }
+        game
+    }
+
+    #[test]
+    fn varint() {
+        for value in [0, 1, 127, 128, 300, 16384, usize::MAX] {
+            let mut bytes = Vec::new();
+            write_varint(&mut bytes, value);
+            let mut reader = Reader { bytes: &bytes };
+            assert_eq!(reader.varint(), Ok(value));
+            assert_eq!(reader.finish(), Ok(()));
+        }
+        let mut reader = Reader { bytes: &[0xff; 11] };
+        assert_eq!(reader.varint(), Err(DecodeError::Overflow));
+    }
+
+    #[test]
+    fn position_phases() {
+        let mut game = Thud::new();
+        game.move_piece((6, 0).into(), (6, 5).into()).unwrap();
+        game.move_piece((8, 7).into(), (9, 7).into()).unwrap();
+
+        let decoded = Thud::decode_position(&game.encode_position()).unwrap();
+        assert_eq!(decoded.state, game.state);
+        assert_eq!(decoded.board, game.board);
+
+        game.troll_cap((9, 7).into(), vec![]).unwrap();
+        game.offer_end().unwrap();
+        let decoded = Thud::decode_position(&game.encode_position()).unwrap();
+        assert_eq!(decoded.end_offer, Some(Player::Dwarf));
+
+        game.accept_end(Player::Troll).unwrap();
+        let decoded = Thud::decode_position(&game.encode_position()).unwrap();
+        assert_eq!(decoded.state, game.state);
+    }
+
+    #[test]
+    fn invalid() {
+        let bytes = Thud::new().encode_position();
+        assert_eq!(
+            Thud::decode_position(&bytes[..10]).unwrap_err(),
+            DecodeError::UnexpectedEnd
+        );
+        let mut long = bytes.clone();
+        long.push(0);
+        assert_eq!(
+            Thud::decode_position(&long).unwrap_err(),
+            DecodeError::TrailingBytes(1)
+        );
+        let mut bad_phase = bytes;
+        *bad_phase.last_mut().unwrap() = SHOVED;
+        assert_eq!(
+            Thud::decode_position(&bad_phase).unwrap_err(),
+            DecodeError::InvalidPhase(SHOVED)
+        );
+
+        let mut game = Thud::new().encode();
+        *game.last_mut().unwrap() = 3;
+        assert_eq!(Thud::decode(&game).unwrap_err(), DecodeError::InvalidFlag);
+        game.push(0xff);
+        assert!(Thud::decode(&game).is_err());
+    }
+
+    #[test]
+    fn rules() {
+        for rules in [
+            Rules::default(),
+            Rules {
+                repetition: Some(3),
+                move_limit: None,
+            },
+            Rules {
+                repetition: Some(usize::MAX),
+                move_limit: Some(usize::MAX),
+            },
+        ] {
+            let decoded = Thud::decode(&Thud::with_rules(rules).unwrap().encode()).unwrap();
+            assert_eq!(decoded.rules, rules);
+        }
+
+        // After the version byte and the position
+        let flags = 1 + Thud::new().encode_position().len();
+        let mut bytes = Thud::new().encode();
+        bytes[flags] = 1 << 2;
+        assert_eq!(Thud::decode(&bytes).unwrap_err(), DecodeError::InvalidRules);
+
+        let rules = Rules {
+            repetition: Some(3),
+            move_limit: None,
+        };
+        let mut bytes = Thud::with_rules(rules).unwrap().encode();
+        bytes[flags + 1] = 1;
+        assert_eq!(Thud::decode(&bytes).unwrap_err(), DecodeError::InvalidRules);
+    }
+
+    #[test]
+    fn out_of_time() {
+        let mut game = Thud::new();
+        game.move_piece((6, 0).into(), (6, 5).into()).unwrap();
+        game.out_of_time(Player::Troll);
+
+        let decoded = Thud::decode(&game.encode()).unwrap();
+        assert_eq!(decoded.state, game.state);
+        assert_eq!(decoded.flagged, Some(Player::Troll));
+    }
+
+    proptest! {
+        #[test]
+        fn game_round_trip(choices in prop::collection::vec(any::<usize>(), 0..120), meta: bool) {
+            let game = play(&choices, meta);
+            let decoded = Thud::decode(&game.encode()).unwrap();
+            prop_assert_eq!(decoded.history(), game.history());
+            prop_assert_eq!(decoded.board, game.board);
+            prop_assert_eq!(decoded.state, game.state);
+        }
+
+        #[test]
+        fn resumed_round_trip(
+            choices in prop::collection::vec(any::<usize>(), 0..60),
+            more in prop::collection::vec(any::<usize>(), 0..10),
+        ) {
+            // Resuming from a decoded position can start partway through a turn
+            let position = play(&choices, true).encode_position();
+            let game = play_from(Thud::decode_position(&position).unwrap(), &more, true);
+            let decoded = Thud::decode(&game.encode()).unwrap();
+            prop_assert_eq!(decoded.history(), game.history());
+            prop_assert_eq!(decoded.board, game.board);
+            prop_assert_eq!(decoded.state, game.state);
+            prop_assert_eq!(decoded.end_offer, game.end_offer);
+        }
+
+        #[test]
+        fn position_round_trip(choices in prop::collection::vec(any::<usize>(), 0..120)) {
+            let game = play(&choices, false);
+            let decoded = Thud::decode_position(&game.encode_position()).unwrap();
+            prop_assert_eq!(decoded.board, game.board);
+            prop_assert_eq!(decoded.state, game.state);
+            prop_assert_eq!(decoded.end_offer, game.end_offer);
+        }
+
+        #[test]
+        fn decode_garbage(bytes in prop::collection::vec(any::<u8>(), 0..64)) {
+            let _ = Thud::decode(&bytes);
+            let _ = Thud::decode_position(&bytes);
+        }
+    }
+}
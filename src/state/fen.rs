@@ -0,0 +1,146 @@
+use crate::*;
+
+impl Thud {
+    /// Write the position in a one-line text notation modelled on chess's FEN.
+    ///
+    /// The board is given row by row from row 15 down to row 1, separated by `/`. Each row lists
+    /// its 15 squares from column `A` to column `P`, with `d` for a dwarf, `T` for a troll, `O`
+    /// for the thudstone and a number for a run of empty squares; squares off the board count as
+    /// empty. After a space comes `d` if it is the dwarf player's turn or `t` for the troll
+    /// player.
+    ///
+    /// Only the pieces and whose turn it is are included, so the notation describes the start of
+    /// a turn; a pending troll capture, an end offer or the end of the game are not recorded.
+    ///
+    /// ```
+    /// use thud::Thud;
+    ///
+    /// let fen = Thud::new().fen();
+    /// assert!(fen.starts_with("5dd1dd5/4d5d4/"));
+    /// assert!(fen.ends_with(" d"));
+    /// assert_eq!(Thud::from_fen(&fen).unwrap().board(), Thud::new().board());
+    /// ```
+    pub fn fen(&self) -> String {
+        let squares = self.board.full_raw();
+        let mut rows = Vec::with_capacity(15);
+        for y in (0..15).rev() {
+            let mut row = String::new();
+            let mut empty = 0;
+            for column in squares.iter() {
+                let symbol = match column[y] {
+                    Piece::Dwarf => 'd',
+                    Piece::Troll => 'T',
+                    Piece::Thudstone => 'O',
+                    Piece::Empty => {
+                        empty += 1;
+                        continue;
+                    }
+                };
+                if empty > 0 {
+                    row.push_str(&empty.to_string());
+                    empty = 0;
+                }
+                row.push(symbol);
+            }
+            if empty > 0 {
+                row.push_str(&empty.to_string());
+            }
+            rows.push(row);
+        }
+
+        let side = match self.turn() {
+            Some(Player::Troll) => 't',
+            _ => 'd',
+        };
+        format!("{} {}", rows.join("/"), side)
+    }
+
+    /// Start a game from a position written as with [`.fen()`](#method.fen).
+    ///
+    /// Returns:
+    ///
+    /// - [`Err(ThudError::InvalidNotation)`](enum.ThudError.html) if the notation can't be read
+    /// - [`Err(ThudError::InvalidPosition)`](enum.ThudError.html) if a piece is off the board
+    /// - [`Err(ThudError::InvalidBoard)`](enum.ThudError.html) if the board fails
+    ///   [`Board::validate()`](struct.Board.html#method.validate)
+    pub fn from_fen(fen: &str) -> Result<Self, ThudError> {
+        let (rows, side) = match fen.split_whitespace().collect::<Vec<_>>()[..] {
+            [rows, side] => (rows, side),
+            _ => return Err(ThudError::InvalidNotation),
+        };
+        let to_move = match side {
+            "d" => Player::Dwarf,
+            "t" => Player::Troll,
+            _ => return Err(ThudError::InvalidNotation),
+        };
+
+        let rows: Vec<&str> = rows.split('/').collect();
+        if rows.len() != 15 {
+            return Err(ThudError::InvalidNotation);
+        }
+        let mut board = Board::default();
+        for (row, y) in rows.iter().zip((0..15).rev()) {
+            let mut x = 0;
+            let mut digits = String::new();
+            for symbol in row.chars().chain(std::iter::once('/')) {
+                if symbol.is_ascii_digit() {
+                    digits.push(symbol);
+                    continue;
+                }
+                if !digits.is_empty() {
+                    x += digits
+                        .parse::<usize>()
+                        .map_err(|_| ThudError::InvalidNotation)?;
+                    digits.clear();
+                }
+                let piece = match symbol {
+                    'd' => Piece::Dwarf,
+                    'T' => Piece::Troll,
+                    'O' => Piece::Thudstone,
+                    '/' => break,
+                    _ => return Err(ThudError::InvalidNotation),
+                };
+                if x >= 15 {
+                    return Err(ThudError::InvalidNotation);
+                }
+                board.place(Coord::zero_based(x, y)?, piece);
+                x += 1;
+            }
+            if x != 15 {
+                return Err(ThudError::InvalidNotation);
+            }
+        }
+
+        Thud::from_board(board, to_move)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test]
+    fn round_trip() {
+        let mut game = Thud::new();
+        game.move_piece((6, 0).into(), (6, 5).into()).unwrap();
+        let fen = game.fen();
+        assert!(fen.ends_with(" t"));
+
+        let loaded = Thud::from_fen(&fen).unwrap();
+        assert_eq!(loaded.board(), game.board());
+        assert_eq!(loaded.turn(), Some(Player::Troll));
+        assert_eq!(loaded.fen(), fen);
+    }
+
+    #[test_case("15/15/15/15/15/15/15/6OO7/15/15/15/15/15/15/15 d" => Err(ThudError::InvalidBoard); "two thudstones")]
+    #[test_case("15/15/15/15/15/15/15/7O7/15/15/15/15/15/15/15" => Err(ThudError::InvalidNotation); "no side")]
+    #[test_case("15/15/15/15/15/15/15/7O7/15/15/15/15/15/15 d" => Err(ThudError::InvalidNotation); "14 rows")]
+    #[test_case("15/15/15/15/15/15/15/7O8/15/15/15/15/15/15/15 d" => Err(ThudError::InvalidNotation); "long row")]
+    #[test_case("15/15/15/15/15/15/15/7O6/15/15/15/15/15/15/15 d" => Err(ThudError::InvalidNotation); "short row")]
+    #[test_case("15/15/15/15/15/15/15/7O7/15/15/15/15/15/15/15 x" => Err(ThudError::InvalidNotation); "bad side")]
+    #[test_case("d14/15/15/15/15/15/15/7O7/15/15/15/15/15/15/15 d" => Err(ThudError::InvalidPosition(0, 14)); "off board")]
+    fn invalid(fen: &str) -> Result<Board, ThudError> {
+        Thud::from_fen(fen).map(|game| game.board())
+    }
+}
@@ -0,0 +1,122 @@
+#[cfg(feature = "serialize")]
+use serde::{Deserialize, Serialize};
+
+/// One of the 8 ways the board can be turned or flipped onto itself
+///
+/// Apply one with [`Board::transform()`](struct.Board.html#method.transform),
+/// [`Coord::transform()`](struct.Coord.html#method.transform) or
+/// [`Direction::transform()`](enum.Direction.html#method.transform). Rotations are
+/// anticlockwise, with "up" the way the rows count.
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialize", serde(rename_all = "snake_case"))]
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+pub enum Symmetry {
+    Identity,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    /// Swap the left and right of the board
+    FlipHorizontal,
+    /// Swap the top and bottom of the board
+    FlipVertical,
+    /// Reflect in the diagonal from the bottom left to the top right
+    FlipDiagonal,
+    /// Reflect in the diagonal from the top left to the bottom right
+    FlipAntiDiagonal,
+}
+
+impl Symmetry {
+    /// Get all 8 `Symmetry`s, starting with [`Symmetry::Identity`](#variant.Identity)
+    pub fn all() -> [Self; 8] {
+        use Symmetry::*;
+        [
+            Identity,
+            Rotate90,
+            Rotate180,
+            Rotate270,
+            FlipHorizontal,
+            FlipVertical,
+            FlipDiagonal,
+            FlipAntiDiagonal,
+        ]
+    }
+
+    /// Get the `Symmetry` which undoes this one
+    ///
+    /// ```
+    /// use thud::{Coord, Symmetry};
+    ///
+    /// let square: Coord = (3, 6).into();
+    /// for symmetry in Symmetry::all() {
+    ///     assert_eq!(square.transform(symmetry).transform(symmetry.inverse()), square);
+    /// }
+    /// ```
+    pub fn inverse(&self) -> Self {
+        match self {
+            Symmetry::Rotate90 => Symmetry::Rotate270,
+            Symmetry::Rotate270 => Symmetry::Rotate90,
+            other => *other,
+        }
+    }
+
+    /// Transform an offset from the centre of the board
+    pub(crate) fn apply(&self, (x, y): (isize, isize)) -> (isize, isize) {
+        match self {
+            Symmetry::Identity => (x, y),
+            Symmetry::Rotate90 => (-y, x),
+            Symmetry::Rotate180 => (-x, -y),
+            Symmetry::Rotate270 => (y, -x),
+            Symmetry::FlipHorizontal => (-x, y),
+            Symmetry::FlipVertical => (x, -y),
+            Symmetry::FlipDiagonal => (y, x),
+            Symmetry::FlipAntiDiagonal => (-y, -x),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Board, Coord, Direction, Piece};
+
+    #[test]
+    fn fresh_board_is_symmetric() {
+        let board = Board::fresh();
+        for symmetry in Symmetry::all() {
+            assert_eq!(board.transform(symmetry), board);
+        }
+    }
+
+    #[test]
+    fn directions_follow_squares() {
+        let square: Coord = (7, 7).into();
+        for symmetry in Symmetry::all() {
+            for direction in Direction::all() {
+                assert_eq!(
+                    direction.modify(square).unwrap().transform(symmetry),
+                    direction
+                        .transform(symmetry)
+                        .modify(square.transform(symmetry))
+                        .unwrap()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn canonical() {
+        let mut board = Board::fresh();
+        board.dwarf_move((6, 0).into(), (6, 5).into()).unwrap();
+        board.place((7, 7).into(), Piece::Empty);
+
+        let canonical = board.canonical();
+        let hashes: Vec<u64> = Symmetry::all()
+            .iter()
+            .map(|&symmetry| board.transform(symmetry).position_hash())
+            .collect();
+        assert_eq!(Some(&canonical.position_hash()), hashes.iter().min());
+        for symmetry in Symmetry::all() {
+            assert_eq!(board.transform(symmetry).canonical(), canonical);
+        }
+    }
+}
@@ -0,0 +1,580 @@
+use crate::record::{parse_header, tokenize, write_tags, write_wrapped, Token};
+use crate::{Action, GameRecord, Thud, ThudError};
+#[cfg(feature = "serialize")]
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// A symbol judging an action, written straight after it in a record
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialize", serde(rename_all = "snake_case"))]
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+pub enum Mark {
+    /// `!`
+    Good,
+    /// `?`
+    Mistake,
+    /// `!!`
+    Brilliant,
+    /// `??`
+    Blunder,
+    /// `!?`
+    Interesting,
+    /// `?!`
+    Dubious,
+}
+
+impl fmt::Display for Mark {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Mark::Good => "!",
+            Mark::Mistake => "?",
+            Mark::Brilliant => "!!",
+            Mark::Blunder => "??",
+            Mark::Interesting => "!?",
+            Mark::Dubious => "?!",
+        })
+    }
+}
+
+/// Read a `Mark` from its symbol.
+///
+/// Returns [`Err(ThudError::InvalidNotation)`](enum.ThudError.html) for anything else.
+impl FromStr for Mark {
+    type Err = ThudError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "!" => Ok(Mark::Good),
+            "?" => Ok(Mark::Mistake),
+            "!!" => Ok(Mark::Brilliant),
+            "??" => Ok(Mark::Blunder),
+            "!?" => Ok(Mark::Interesting),
+            "?!" => Ok(Mark::Dubious),
+            _ => Err(ThudError::InvalidNotation),
+        }
+    }
+}
+
+/// Identifies a position in a [`GameTree`](struct.GameTree.html)
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+pub struct NodeId(usize);
+
+#[derive(Debug, Clone)]
+struct Node {
+    game: Thud,
+    // `None` only for the root
+    action: Option<Action>,
+    parent: Option<NodeId>,
+    // The main continuation first, then the variations
+    children: Vec<NodeId>,
+    comment: Option<String>,
+    evaluation: Option<f64>,
+    mark: Option<Mark>,
+}
+
+impl Node {
+    fn new(game: Thud, action: Option<Action>, parent: Option<NodeId>) -> Self {
+        Node {
+            game,
+            action,
+            parent,
+            children: Vec::new(),
+            comment: None,
+            evaluation: None,
+            mark: None,
+        }
+    }
+}
+
+/// A game along with lines which could have been played instead, for analysis
+///
+/// Each node of the tree is a position, reached from its parent by an
+/// [`Action`](enum.Action.html). The first child of a node is the main continuation and any
+/// others are variations. Nodes can carry a comment, an evaluation and a [`Mark`](enum.Mark.html).
+///
+/// The tree keeps a current node, which [`.play()`](#method.play) adds to and the navigation
+/// methods move around.
+///
+/// Trees are written in the format of a [`GameRecord`](struct.GameRecord.html), with these
+/// additions after the tags:
+///
+/// - A mark straight after an action, as in `G1-G6!?`
+/// - A comment after an action, or before the first to comment on the start, in braces:
+///   `{like this}`, with `\}` and `\\` escaping braces and backslashes. An evaluation is written
+///   at the start of the comment, as in `{[%eval 1.5] dwarves are ahead}`, and a comment which
+///   itself starts with `[` has it escaped as `\[`. The text is kept exactly, spaces and all
+/// - A variation after the action it replaces, in parentheses: `G1-G6 (G1-G5 H9-H10) H9-H10`
+///
+/// ```
+/// use thud::{GameTree, Mark, Thud};
+///
+/// let mut tree = GameTree::new(Thud::new());
+/// tree.play("G1-G6".parse().unwrap()).unwrap();
+/// tree.back();
+/// let variation = tree.play("G1-G5".parse().unwrap()).unwrap();
+/// tree.set_mark(variation, Some(Mark::Dubious));
+///
+/// assert_eq!(tree.mainline().len(), 2);
+/// tree.promote(variation);
+/// assert_eq!(tree.mainline()[1], variation);
+/// assert!(tree.to_string().ends_with("G1-G5?! (G1-G6)\n"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct GameTree {
+    /// Tag names and values, as in a [`GameRecord`](struct.GameRecord.html)
+    pub tags: Vec<(String, String)>,
+    nodes: Vec<Node>,
+    current: NodeId,
+}
+
+impl GameTree {
+    /// Start a tree from `game`, which becomes its root
+    pub fn new(game: Thud) -> Self {
+        GameTree {
+            tags: Vec::new(),
+            nodes: vec![Node::new(game, None, None)],
+            current: NodeId(0),
+        }
+    }
+
+    /// Make a tree holding only the main line of `record`, with its tags.
+    ///
+    /// Passes on errors from [`GameRecord::start()`](struct.GameRecord.html#method.start) and
+    /// from taking each action.
+    pub fn from_record(record: &GameRecord) -> Result<Self, ThudError> {
+        let mut tree = GameTree::new(record.start()?);
+        tree.tags = record.tags.clone();
+        for action in &record.actions {
+            tree.play(action.clone())?;
+        }
+        tree.current = tree.root();
+        Ok(tree)
+    }
+
+    /// Get the main line as a [`GameRecord`](struct.GameRecord.html), with the tree's tags
+    pub fn record(&self) -> GameRecord {
+        GameRecord {
+            tags: self.tags.clone(),
+            actions: self
+                .mainline()
+                .iter()
+                .filter_map(|&id| self.action(id).cloned())
+                .collect(),
+        }
+    }
+
+    fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id.0]
+    }
+
+    fn node_mut(&mut self, id: NodeId) -> &mut Node {
+        &mut self.nodes[id.0]
+    }
+
+    /// Get the node for the starting position
+    pub fn root(&self) -> NodeId {
+        NodeId(0)
+    }
+
+    /// Get the current node
+    pub fn current(&self) -> NodeId {
+        self.current
+    }
+
+    /// Get the game at the current node
+    pub fn game(&self) -> &Thud {
+        &self.node(self.current).game
+    }
+
+    /// Get the game at `id`
+    pub fn position(&self, id: NodeId) -> &Thud {
+        &self.node(id).game
+    }
+
+    /// Get the action which led to `id`, which is `None` for the root
+    pub fn action(&self, id: NodeId) -> Option<&Action> {
+        self.node(id).action.as_ref()
+    }
+
+    /// Get the node before `id`, which is `None` for the root
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.node(id).parent
+    }
+
+    /// Get the nodes after `id`, the main continuation first
+    pub fn children(&self, id: NodeId) -> &[NodeId] {
+        &self.node(id).children
+    }
+
+    /// Get the nodes of the main line, from the root
+    pub fn mainline(&self) -> Vec<NodeId> {
+        let mut line = vec![self.root()];
+        while let Some(&next) = self.children(*line.last().unwrap()).first() {
+            line.push(next);
+        }
+        line
+    }
+
+    /// Get the actions leading from the root to `id`
+    pub fn line(&self, id: NodeId) -> Vec<Action> {
+        let mut actions = Vec::new();
+        let mut node = Some(id);
+        while let Some(id) = node {
+            actions.extend(self.action(id).cloned());
+            node = self.parent(id);
+        }
+        actions.reverse();
+        actions
+    }
+
+    /// Make `id` the current node
+    pub fn go_to(&mut self, id: NodeId) {
+        self.current = id;
+    }
+
+    /// Move back to the parent of the current node, returning `false` at the root
+    pub fn back(&mut self) -> bool {
+        match self.parent(self.current) {
+            Some(parent) => {
+                self.current = parent;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Move on to the main continuation of the current node, returning `false` if there is none
+    pub fn forward(&mut self) -> bool {
+        match self.children(self.current).first() {
+            Some(&next) => {
+                self.current = next;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Take `action` from the current node, moving to the node it leads to.
+    ///
+    /// If `action` has been taken from here before, its node is reused; otherwise a new node is
+    /// added as the main continuation if there is none, or as a variation.
+    ///
+    /// Passes on errors from [`Thud::apply()`](struct.Thud.html#method.apply).
+    pub fn play(&mut self, action: Action) -> Result<NodeId, ThudError> {
+        let current = self.current;
+        let existing = self
+            .children(current)
+            .iter()
+            .copied()
+            .find(|&child| self.action(child) == Some(&action));
+        let id = match existing {
+            Some(id) => id,
+            None => {
+                let mut game = self.game().clone();
+                game.apply(action.clone())?;
+                game.winner();
+                let id = NodeId(self.nodes.len());
+                self.nodes
+                    .push(Node::new(game, Some(action), Some(current)));
+                self.node_mut(current).children.push(id);
+                id
+            }
+        };
+        self.current = id;
+        Ok(id)
+    }
+
+    /// Move `id` one place up among its siblings; if it was the first variation, it becomes the
+    /// main continuation
+    pub fn promote(&mut self, id: NodeId) {
+        if let Some(parent) = self.parent(id) {
+            let siblings = &mut self.node_mut(parent).children;
+            let index = siblings.iter().position(|&other| other == id).unwrap();
+            if index > 0 {
+                siblings.swap(index - 1, index);
+            }
+        }
+    }
+
+    /// Put `id` on the main line, by making it and every node before it the main continuation
+    pub fn make_mainline(&mut self, id: NodeId) {
+        let mut node = id;
+        while let Some(parent) = self.parent(node) {
+            let siblings = &mut self.node_mut(parent).children;
+            let index = siblings.iter().position(|&other| other == node).unwrap();
+            let moved = siblings.remove(index);
+            siblings.insert(0, moved);
+            node = parent;
+        }
+    }
+
+    /// Get the comment on `id`
+    pub fn comment(&self, id: NodeId) -> Option<&str> {
+        self.node(id).comment.as_deref()
+    }
+
+    /// Set or clear the comment on `id`
+    pub fn set_comment(&mut self, id: NodeId, comment: Option<String>) {
+        self.node_mut(id).comment = comment;
+    }
+
+    /// Get the evaluation of `id`, where positive numbers favour the dwarves
+    pub fn evaluation(&self, id: NodeId) -> Option<f64> {
+        self.node(id).evaluation
+    }
+
+    /// Set or clear the evaluation of `id`
+    pub fn set_evaluation(&mut self, id: NodeId, evaluation: Option<f64>) {
+        self.node_mut(id).evaluation = evaluation;
+    }
+
+    /// Get the mark on the action leading to `id`
+    pub fn mark(&self, id: NodeId) -> Option<Mark> {
+        self.node(id).mark
+    }
+
+    /// Set or clear the mark on the action leading to `id`
+    pub fn set_mark(&mut self, id: NodeId, mark: Option<Mark>) {
+        self.node_mut(id).mark = mark;
+    }
+
+    /// Add the words for `id`'s action, mark and annotations to `words`
+    fn write_node(&self, id: NodeId, words: &mut Vec<String>) {
+        let node = self.node(id);
+        if let Some(action) = &node.action {
+            let mark = node.mark.map(|mark| mark.to_string()).unwrap_or_default();
+            words.push(format!("{}{}", action, mark));
+        }
+        let mut comment = String::new();
+        if let Some(evaluation) = node.evaluation {
+            comment = format!("[%eval {}]", evaluation);
+        }
+        if let Some(text) = &node.comment {
+            if !comment.is_empty() {
+                comment.push(' ');
+            }
+            // Keep a comment starting with `[` from being read as an evaluation
+            if text.starts_with('[') {
+                comment.push('\\');
+            }
+            comment.push_str(&text.replace('\\', "\\\\").replace('}', "\\}"));
+        }
+        if node.comment.is_some() || node.evaluation.is_some() {
+            words.push(format!("{{{}}}", comment));
+        }
+    }
+
+    /// Add the words for everything after `id` to `words`, variations first
+    fn write_line(&self, mut id: NodeId, words: &mut Vec<String>) {
+        while let Some((&main, variations)) = self.children(id).split_first() {
+            self.write_node(main, words);
+            for &variation in variations {
+                let start = words.len();
+                self.write_node(variation, words);
+                self.write_line(variation, words);
+                words[start].insert(0, '(');
+                words.last_mut().unwrap().push(')');
+            }
+            id = main;
+        }
+    }
+
+    /// Add the comment and evaluation in `text`, still escaped, to `id`
+    fn annotate(&mut self, id: NodeId, text: &str) -> Result<(), ThudError> {
+        let mut text = text;
+        if let Some(rest) = text.strip_prefix("[%eval ") {
+            let (evaluation, rest) = rest.split_once(']').ok_or(ThudError::InvalidNotation)?;
+            let evaluation = evaluation
+                .trim()
+                .parse()
+                .map_err(|_| ThudError::InvalidNotation)?;
+            self.set_evaluation(id, Some(evaluation));
+            // The comment, if any, comes after a space
+            text = match rest.strip_prefix(' ') {
+                Some(rest) => rest,
+                None if rest.is_empty() => return Ok(()),
+                None => return Err(ThudError::InvalidNotation),
+            };
+        }
+        let mut comment = String::new();
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => comment.extend(chars.next()),
+                c => comment.push(c),
+            }
+        }
+        self.set_comment(id, Some(comment));
+        Ok(())
+    }
+}
+
+impl fmt::Display for GameTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_tags(f, &self.tags)?;
+        let mut words = Vec::new();
+        self.write_node(self.root(), &mut words);
+        self.write_line(self.root(), &mut words);
+        write_wrapped(f, &words)
+    }
+}
+
+/// Read a `GameTree` in the format it is written in, starting at its root.
+///
+/// Returns [`Err(ThudError::InvalidNotation)`](enum.ThudError.html) if the text can't be read,
+/// and passes on errors from setting up the start and taking each action.
+impl FromStr for GameTree {
+    type Err = ThudError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (tags, movetext) = parse_header(s)?;
+        let header = GameRecord {
+            tags,
+            actions: Vec::new(),
+        };
+        let mut tree = GameTree::new(header.start()?);
+        tree.tags = header.tags;
+
+        // Where to carry on from once each open variation ends
+        let mut resume = Vec::new();
+        for token in tokenize(&movetext)? {
+            match token {
+                Token::Action(action, mark) => {
+                    let id = tree.play(action.parse()?)?;
+                    tree.set_mark(id, mark);
+                }
+                Token::Comment(text) => tree.annotate(tree.current, &text)?,
+                Token::Open => {
+                    let parent = tree
+                        .parent(tree.current)
+                        .ok_or(ThudError::InvalidNotation)?;
+                    resume.push(tree.current);
+                    tree.current = parent;
+                }
+                Token::Close => tree.current = resume.pop().ok_or(ThudError::InvalidNotation)?,
+            }
+        }
+        if !resume.is_empty() {
+            return Err(ThudError::InvalidNotation);
+        }
+        tree.current = tree.root();
+        Ok(tree)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    fn action(notation: &str) -> Action {
+        notation.parse().unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut tree = GameTree::new(Thud::new());
+        tree.tags
+            .push(("Event".to_string(), "Analysis".to_string()));
+        tree.set_comment(tree.root(), Some("The usual start".to_string()));
+        let first = tree.play(action("G1-G6")).unwrap();
+        tree.set_evaluation(first, Some(-0.5));
+        tree.set_comment(first, Some("Risky {or not}".to_string()));
+        tree.play(action("H9-H10")).unwrap();
+        tree.play(action("H10*")).unwrap();
+
+        tree.go_to(tree.root());
+        let variation = tree.play(action("G1-G5")).unwrap();
+        tree.set_mark(variation, Some(Mark::Good));
+        tree.set_comment(variation, Some("a \n   b".to_string()));
+        tree.play(action("H9-H10")).unwrap();
+        tree.go_to(first);
+        let nested = tree.play(action("G7-F6")).unwrap();
+        tree.set_evaluation(nested, Some(2.0));
+        let resigned = tree.play(action("resign troll")).unwrap();
+        tree.set_mark(resigned, Some(Mark::Dubious));
+        tree.set_comment(resigned, Some("[%eval 9] is only text".to_string()));
+        tree.go_to(first);
+        let quiet = tree.play(action("H9-H10")).unwrap();
+        tree.set_comment(quiet, Some(String::new()));
+        let spaced = tree.play(action("H10*")).unwrap();
+        tree.set_evaluation(spaced, Some(1.0));
+        tree.set_comment(spaced, Some(" ".to_string()));
+
+        let text = tree.to_string();
+        assert_eq!(
+            text.split_whitespace().collect::<Vec<_>>().join(" "),
+            "[Event \"Analysis\"] {The usual start} G1-G6 {[%eval -0.5] Risky {or not\\}} \
+             (G1-G5! {a b} H9-H10) H9-H10 {} (G7-F6 {[%eval 2]} resign troll?! {\\[%eval 9] is only \
+             text}) H10* {[%eval 1] }"
+        );
+        let read: GameTree = text.parse().unwrap();
+        assert_eq!(read.to_string(), text);
+        assert_eq!(read.comment(read.root()), Some("The usual start"));
+        let first = read.children(read.root())[0];
+        assert_eq!(read.evaluation(first), Some(-0.5));
+        assert_eq!(read.comment(first), Some("Risky {or not}"));
+        let quiet = read.children(first)[0];
+        assert_eq!(read.comment(quiet), Some(""));
+        let spaced = read.children(quiet)[0];
+        assert_eq!(
+            (read.evaluation(spaced), read.comment(spaced)),
+            (Some(1.0), Some(" "))
+        );
+        let variation = read.children(read.root())[1];
+        assert_eq!(read.comment(variation), Some("a \n   b"));
+        let resigned = read.children(read.children(first)[1])[0];
+        assert_eq!(read.mark(resigned), Some(Mark::Dubious));
+        assert_eq!(read.evaluation(resigned), None);
+        assert_eq!(read.comment(resigned), Some("[%eval 9] is only text"));
+
+        // A plain record keeps the main line
+        let record: GameRecord = text.parse().unwrap();
+        assert_eq!(record, tree.record());
+        assert_eq!(record.actions.len(), 3);
+    }
+
+    #[test]
+    fn navigation() {
+        let mut tree = GameTree::new(Thud::new());
+        assert!(!tree.back());
+        let first = tree.play(action("G1-G6")).unwrap();
+        let reply = tree.play(action("H9-H10")).unwrap();
+        assert_eq!(tree.line(reply), vec![action("G1-G6"), action("H9-H10")]);
+        assert_eq!(tree.game().pending_capture(), Some((7, 9).into()));
+
+        tree.go_to(tree.root());
+        assert_eq!(tree.play(action("G1-G6")), Ok(first));
+        assert!(tree.play(action("G6-G7")).is_err());
+        assert_eq!(tree.current(), first);
+        let other = tree.play(action("J9-J10")).unwrap();
+        let deeper = tree.play(action("J10*")).unwrap();
+        assert_eq!(tree.children(first), &[reply, other]);
+
+        tree.make_mainline(deeper);
+        assert_eq!(tree.mainline(), vec![tree.root(), first, other, deeper]);
+        tree.promote(reply);
+        assert_eq!(tree.children(first), &[reply, other]);
+
+        tree.go_to(tree.root());
+        assert!(tree.forward());
+        assert!(tree.forward());
+        assert_eq!(tree.current(), reply);
+        assert!(!tree.forward());
+
+        let record = GameRecord::from_game(tree.position(deeper));
+        let tree = GameTree::from_record(&record).unwrap();
+        assert_eq!(tree.record(), record);
+    }
+
+    #[test_case("\n\n(G1-G6)"; "variation at the start")]
+    #[test_case("\n\nG1-G6 (G1-G5"; "unclosed variation")]
+    #[test_case("\n\nG1-G6 {[%eval lots]}"; "bad evaluation")]
+    fn invalid(text: &str) {
+        assert_eq!(
+            text.parse::<GameTree>().err(),
+            Some(ThudError::InvalidNotation)
+        );
+    }
+}
@@ -0,0 +1,237 @@
+//! JavaScript bindings, enabled with the `wasm` feature.
+//!
+//! Build a WebAssembly module with `cargo rustc --release --lib --crate-type cdylib --target
+//! wasm32-unknown-unknown --features wasm`, then run the
+//! [`wasm-bindgen`](https://rustwasm.github.io/wasm-bindgen/reference/cli.html) CLI on
+//! `target/wasm32-unknown-unknown/release/thud.wasm` to get JavaScript exposing `Thud`, `Board`
+//! and `Coord` classes. Squares are given as strings in standard Thud notation
+//! (see [`Coord`](../struct.Coord.html)), and pieces, players and directions are given as the
+//! strings used by the `serialize` feature, eg. `"troll"` or `"up_left"`. Errors are thrown as
+//! JavaScript `Error`s carrying the message from [`ThudError`](../enum.ThudError.html).
+
+use crate::{Board, Coord, Direction, EndState, Piece, Thud, ThudError};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use wasm_bindgen::prelude::*;
+
+/// A board as a plain JavaScript object
+#[derive(Serialize, Debug, PartialEq)]
+struct PlainBoard {
+    /// Every occupied square, keyed by square notation
+    squares: BTreeMap<String, Piece>,
+}
+
+impl From<&Board> for PlainBoard {
+    fn from(board: &Board) -> Self {
+        let mut squares = BTreeMap::new();
+        for piece in [Piece::Dwarf, Piece::Troll, Piece::Thudstone] {
+            for square in board.army(piece) {
+                squares.insert(square.to_string(), piece);
+            }
+        }
+        PlainBoard { squares }
+    }
+}
+
+/// The outcome of a game as a plain JavaScript object; `winner` is `null` for a draw
+#[derive(Serialize, Debug, PartialEq)]
+struct PlainOutcome {
+    winner: Option<crate::Player>,
+    reason: crate::EndReason,
+}
+
+impl From<EndState> for PlainOutcome {
+    fn from(end: EndState) -> Self {
+        match end {
+            EndState::Won(player, reason) => PlainOutcome {
+                winner: Some(player),
+                reason,
+            },
+            EndState::Draw(reason) => PlainOutcome {
+                winner: None,
+                reason,
+            },
+        }
+    }
+}
+
+fn to_js<T: Serialize>(value: &T) -> Result<JsValue, JsError> {
+    value
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|error| JsError::new(&error.to_string()))
+}
+
+fn thud_error(error: ThudError) -> JsError {
+    JsError::new(&error.to_string())
+}
+
+fn square(notation: &str) -> Result<Coord, JsError> {
+    notation.parse().map_err(thud_error)
+}
+
+fn squares(coords: Vec<Coord>) -> Vec<String> {
+    coords.iter().map(Coord::to_string).collect()
+}
+
+/// A square on the board, see [`Coord`](../struct.Coord.html)
+#[wasm_bindgen(js_name = Coord)]
+pub struct JsCoord(Coord);
+
+#[wasm_bindgen(js_class = Coord)]
+impl JsCoord {
+    /// Read a square from standard Thud notation, eg. `"H8"`
+    #[wasm_bindgen(constructor)]
+    pub fn new(notation: &str) -> Result<JsCoord, JsError> {
+        square(notation).map(JsCoord)
+    }
+
+    /// Make a square from zero-based axes values
+    #[wasm_bindgen(js_name = zeroBased)]
+    pub fn zero_based(x: usize, y: usize) -> Result<JsCoord, JsError> {
+        Coord::zero_based(x, y).map(JsCoord).map_err(thud_error)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn x(&self) -> usize {
+        self.0.value().0
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn y(&self) -> usize {
+        self.0.value().1
+    }
+
+    /// Write the square in standard Thud notation
+    #[wasm_bindgen(js_name = toString)]
+    pub fn to_notation(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+/// A configuration of pieces on the board, see [`Board`](../struct.Board.html)
+#[wasm_bindgen(js_name = Board)]
+pub struct JsBoard(Board);
+
+#[wasm_bindgen(js_class = Board)]
+impl JsBoard {
+    /// Get a board with the pieces in their starting positions
+    pub fn fresh() -> JsBoard {
+        JsBoard(Board::fresh())
+    }
+
+    /// Get the piece at a square, eg. `"troll"` or `"empty"`
+    pub fn get(&self, notation: &str) -> Result<JsValue, JsError> {
+        to_js(&self.0.get(square(notation)?))
+    }
+
+    /// Get the squares the piece at a square could move to or attack
+    #[wasm_bindgen(js_name = availableMoves)]
+    pub fn available_moves(&self, notation: &str) -> Result<Vec<String>, JsError> {
+        Ok(squares(self.0.available_moves(square(notation)?)))
+    }
+
+    /// Get the board as a plain object, `{ squares: { "A6": "dwarf", ... } }`, holding only the
+    /// occupied squares
+    #[wasm_bindgen(js_name = toObject)]
+    pub fn to_object(&self) -> Result<JsValue, JsError> {
+        to_js(&PlainBoard::from(&self.0))
+    }
+}
+
+/// A game of Thud, see [`Thud`](../struct.Thud.html)
+#[wasm_bindgen(js_name = Thud)]
+pub struct JsThud(Thud);
+
+#[wasm_bindgen(js_class = Thud)]
+impl JsThud {
+    /// Start a new game
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> JsThud {
+        JsThud(Thud::new())
+    }
+
+    /// Find whose turn it is, `"dwarf"` or `"troll"`, or `null` if the game is over
+    pub fn turn(&self) -> Result<JsValue, JsError> {
+        to_js(&self.0.turn())
+    }
+
+    /// Get a copy of the current board
+    pub fn board(&self) -> JsBoard {
+        JsBoard(self.0.board())
+    }
+
+    /// Get the scores as `{ dwarf, troll }`
+    pub fn score(&self) -> Result<JsValue, JsError> {
+        let (dwarf, troll) = self.0.score();
+        to_js(&BTreeMap::from([("dwarf", dwarf), ("troll", troll)]))
+    }
+
+    /// Find the outcome of the game as `{ winner, reason }`, or `null` if it is still going
+    pub fn winner(&mut self) -> Result<JsValue, JsError> {
+        to_js(&self.0.winner().map(PlainOutcome::from))
+    }
+
+    /// Move a piece of the player whose turn it is
+    #[wasm_bindgen(js_name = movePiece)]
+    pub fn move_piece(&mut self, src: &str, target: &str) -> Result<(), JsError> {
+        self.0
+            .move_piece(square(src)?, square(target)?)
+            .map_err(thud_error)
+    }
+
+    /// Hurl a dwarf or shove a troll, depending on whose turn it is
+    pub fn attack(&mut self, src: &str, target: &str) -> Result<(), JsError> {
+        self.0
+            .attack(square(src)?, square(target)?)
+            .map_err(thud_error)
+    }
+
+    /// Get the directions, eg. `"up_left"`, in which the troll at a square could capture
+    #[wasm_bindgen(js_name = captureOptions)]
+    pub fn capture_options(&self, troll: &str) -> Result<JsValue, JsError> {
+        let options = self.0.capture_options(square(troll)?).map_err(thud_error)?;
+        to_js(&options.directions())
+    }
+
+    /// Capture dwarves in an array of directions with the troll at a square
+    #[wasm_bindgen(js_name = trollCap)]
+    pub fn troll_cap(&mut self, troll: &str, targets: JsValue) -> Result<(), JsError> {
+        let targets: Vec<Direction> = serde_wasm_bindgen::from_value(targets)
+            .map_err(|error| JsError::new(&error.to_string()))?;
+        self.0
+            .troll_cap(square(troll)?, targets)
+            .map_err(thud_error)
+    }
+}
+
+impl Default for JsThud {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_board() {
+        let plain = PlainBoard::from(&Board::fresh());
+        assert_eq!(plain.squares.len(), 32 + 8 + 1);
+        assert_eq!(plain.squares["H8"], Piece::Thudstone);
+        assert_eq!(plain.squares["A6"], Piece::Dwarf);
+        assert_eq!(
+            serde_json::to_value(&plain).unwrap()["squares"]["G7"],
+            "troll"
+        );
+    }
+
+    #[test]
+    fn plain_outcome() {
+        let outcome = PlainOutcome::from(EndState::Draw(crate::EndReason::Agreement));
+        assert_eq!(
+            serde_json::to_string(&outcome).unwrap(),
+            r#"{"winner":null,"reason":"agreement"}"#
+        );
+    }
+}
@@ -0,0 +1,44 @@
+use std::env;
+use std::fs;
+use std::process::Command;
+use thud::{GameRecord, Outcome, Player};
+
+#[test]
+fn round_robin_of_engine_processes() {
+    let engine = env!("CARGO_BIN_EXE_thud-engine");
+    let records = env::temp_dir().join(format!("thud-arena-{}.txt", std::process::id()));
+    let output = Command::new(env!("CARGO_BIN_EXE_thud-arena"))
+        .args(["--engine", &format!("first={} --random --seed 1", engine)])
+        .args(["--engine", &format!("second={} --random --seed 2", engine)])
+        .args(["--engine", "missing=/nonexistent/engine"])
+        .args(["--move-limit", "10", "--timeout", "5000"])
+        .arg("--records")
+        .arg(&records)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let table = String::from_utf8(output.stdout).unwrap();
+    assert!(table.starts_with("Rank  Engine"));
+    let last = table.lines().last().unwrap();
+    assert!(last.contains("missing") && last.ends_with("0.0"));
+
+    let text = fs::read_to_string(&records).unwrap();
+    fs::remove_file(&records).unwrap();
+    let games = GameRecord::read_all(&text).unwrap();
+    assert_eq!(games.len(), 6);
+    for record in games {
+        let outcome = record.outcome().unwrap();
+        if record.tag("Termination") == Some("crash") {
+            let loser = match outcome {
+                Outcome::Won(Player::Dwarf) => record.tag("Troll"),
+                Outcome::Won(Player::Troll) => record.tag("Dwarf"),
+                Outcome::Draw => None,
+            };
+            assert_eq!(loser, Some("missing"));
+        } else {
+            let mut game = record.replay().unwrap();
+            assert_eq!(game.winner().map(Outcome::from), Some(outcome));
+        }
+    }
+}
@@ -0,0 +1,120 @@
+#![cfg(feature = "serialize")]
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::json;
+use std::fmt::Debug;
+use thud::*;
+
+fn round_trip<T>(value: &T)
+where
+    T: Serialize + DeserializeOwned + PartialEq + Debug,
+{
+    let json = serde_json::to_string(value).unwrap();
+    assert_eq!(&serde_json::from_str::<T>(&json).unwrap(), value);
+
+    let binary = bincode::serialize(value).unwrap();
+    assert_eq!(&bincode::deserialize::<T>(&binary).unwrap(), value);
+}
+
+#[test]
+fn simple_types() {
+    round_trip(&Coord::zero_based(3, 4).unwrap());
+    round_trip(&Piece::Thudstone);
+    round_trip(&Player::Troll);
+    round_trip(&EndState::Won(Player::Dwarf, EndReason::Resignation));
+    round_trip(&EndState::Draw(EndReason::Repetition));
+    round_trip(&ThudError::LineTooShort(3, 2));
+    round_trip(&BoardViolation::OutOfBounds(0, 0));
+    round_trip(&Handicap::ExtraDwarves);
+    round_trip(&Rules {
+        repetition: Some(3),
+        move_limit: None,
+    });
+    round_trip(&Action::Capture((7, 6).into(), vec![Direction::Up]));
+    round_trip(&Action::OfferEnd);
+    for dir in Direction::all() {
+        round_trip(&dir);
+    }
+}
+
+#[test]
+fn board() {
+    let board = Board::fresh();
+    let json = serde_json::to_string(&board).unwrap();
+    let from_json: Board = serde_json::from_str(&json).unwrap();
+    assert_eq!(from_json.full_raw(), board.full_raw());
+
+    let binary = bincode::serialize(&board).unwrap();
+    let from_binary: Board = bincode::deserialize(&binary).unwrap();
+    assert_eq!(from_binary.full_raw(), board.full_raw());
+
+    round_trip(&board.capture_options((7, 6).into(), false).unwrap());
+}
+
+#[test]
+fn thud() {
+    let mut game = Thud::new();
+    game.move_piece((6, 0).into(), (6, 5).into()).unwrap();
+    game.move_piece((8, 7).into(), (9, 7).into()).unwrap();
+
+    let json = serde_json::to_string(&game).unwrap();
+    let from_json: Thud = serde_json::from_str(&json).unwrap();
+    assert_eq!(serde_json::to_string(&from_json).unwrap(), json);
+    assert_eq!(from_json.turn(), Some(Player::Troll));
+
+    let binary = bincode::serialize(&game).unwrap();
+    let from_binary: Thud = bincode::deserialize(&binary).unwrap();
+    assert_eq!(bincode::serialize(&from_binary).unwrap(), binary);
+}
+
+fn to_json<T: Serialize>(value: &T) -> serde_json::Value {
+    serde_json::to_value(value).unwrap()
+}
+
+#[test]
+fn wire_format() {
+    assert_eq!(to_json(&Piece::Thudstone), json!("thudstone"));
+    assert_eq!(to_json(&Direction::UpLeft), json!("up_left"));
+    assert_eq!(
+        to_json(&EndState::Won(Player::Troll, EndReason::NoMoves)),
+        json!({"won": ["troll", "no_moves"]})
+    );
+    assert_eq!(
+        to_json(&Coord::zero_based(7, 7).unwrap()),
+        json!({"x": 7, "y": 7})
+    );
+
+    let game = to_json(&Thud::new());
+    assert_eq!(game["state"], json!({"to_move": "dwarf"}));
+    assert_eq!(game["end_offer"], json!(null));
+}
+
+#[test]
+fn invalid_coord() {
+    assert!(serde_json::from_value::<Coord>(json!({"x": 0, "y": 0})).is_err());
+}
+
+#[test]
+fn legacy_thud() {
+    // Saved by version 0.1 after G1-G6
+    let snapshot: Snapshot =
+        serde_json::from_str(include_str!("fixtures/legacy_to_move.json")).unwrap();
+    let game = snapshot.restore().unwrap();
+    assert_eq!(game.turn(), Some(Player::Troll));
+    assert_eq!(game.board().get((6, 5).into()), Piece::Dwarf);
+    assert!(game.history().is_empty());
+
+    // Saved by version 0.1 after G1-G6 J8-K8, before the troll captured
+    let snapshot: Snapshot =
+        serde_json::from_str(include_str!("fixtures/legacy_troll_capture.json")).unwrap();
+    let game = snapshot.restore().unwrap();
+    assert_eq!(game.turn(), Some(Player::Dwarf));
+    assert_eq!(game.board().get((9, 7).into()), Piece::Troll);
+
+    // Snapshots in the current format still load
+    let json = serde_json::to_string(&Snapshot::new(&game)).unwrap();
+    assert_eq!(
+        serde_json::from_str::<Snapshot>(&json).unwrap(),
+        Snapshot::new(&game)
+    );
+}